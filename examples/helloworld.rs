@@ -68,8 +68,8 @@ fn spawn_plane(
 ) {
     let mesh = meshes.add(Mesh::from(shape::Plane { size: 500.0 }));
     let material = materials.add(Color::rgb(0.3, 0.5, 0.3).into());
-    let px_geometry = px_geometries.add(bpx::Geometry::halfspace());
-    let px_material = px_materials.add(bpx::Material::new(&mut physics, 0.5, 0.5, 0.6));
+    let px_geometry = px_geometries.add(bpx::Geometry::halfspace_with_normal(Vec3::Y));
+    let px_material = px_materials.add(bpx::Material::new(&mut physics, 0.5, 0.5, 0.6).unwrap());
 
     commands.spawn_empty()
         .insert(PbrBundle {
@@ -77,18 +77,11 @@ fn spawn_plane(
             material,
             ..default()
         })
-        .with_children(|builder| {
-            builder.spawn_empty()
-                .insert(SpatialBundle::from_transform(
-                    // physx default plane is rotated compared to bevy plane, we undo that
-                    Transform::from_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_2))
-                ))
-                .insert(bpx::RigidBody::Static)
-                .insert(bpx::Shape {
-                    geometry: px_geometry,
-                    material: px_material,
-                    ..default()
-                });
+        .insert(bpx::RigidBody::Static)
+        .insert(bpx::Shape {
+            geometry: px_geometry,
+            material: px_material,
+            ..default()
         })
         .insert(Name::new("Plane"));
 }
@@ -152,7 +145,7 @@ fn spawn_dynamic(
     let material = materials.add(Color::rgb(0.8, 0.7, 0.6).into());
 
     let px_geometry = px_geometries.add(bpx::Geometry::ball(RADIUS));
-    let px_material = px_materials.add(bpx::Material::new(&mut physics, 0.5, 0.5, 0.6));
+    let px_material = px_materials.add(bpx::Material::new(&mut physics, 0.5, 0.5, 0.6).unwrap());
 
     let transform = Transform::from_translation(Vec3::new(0., 5., 12.5));
 
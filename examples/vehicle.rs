@@ -31,26 +31,6 @@ const COLLISION_FLAG_CHASSIS_AGAINST: u32 = COLLISION_FLAG_GROUND | COLLISION_FL
 pub const GRAVITY_FORCE: Vec3 = Vec3::new(0., -9.81, 0.);
 pub const HULL_MASS: f32 = 2800.;
 pub const CENTER_OF_MASS: Vec3 = Vec3::new(0., 0.7, 0.);
-pub const HULL_VERTICES : [Vec3; 18] = [
-    Vec3::new(-0.92657, 1.44990, -2.83907),
-    Vec3::new( 0.92657, 1.44990, -2.83907),
-    Vec3::new(-0.73964, 1.85914,  0.41819),
-    Vec3::new( 0.73964, 1.85914,  0.41819),
-    Vec3::new(-0.96609, 1.11038,  2.57245),
-    Vec3::new( 0.96609, 1.11038,  2.57245),
-    Vec3::new(-0.62205, 1.01440,  2.84896),
-    Vec3::new( 0.62205, 1.01440,  2.84896),
-    Vec3::new(-0.92108, 0.63051, -2.74199),
-    Vec3::new( 0.92108, 0.63051, -2.74199),
-    Vec3::new(-0.65192, 0.46546,  2.74609),
-    Vec3::new( 0.65192, 0.46546,  2.74609),
-    Vec3::new(-0.98115, 0.46546,  2.48097),
-    Vec3::new( 0.98115, 0.46546,  2.48097),
-    Vec3::new(-0.90621, 0.38511, -1.06282),
-    Vec3::new( 0.90621, 0.38511, -1.06282),
-    Vec3::new(-0.90621, 0.34191,  1.26607),
-    Vec3::new( 0.90621, 0.34191,  1.26607),
-];
 
 pub const WHEEL_MASS: f32 = 30.;
 pub const WHEEL_HALF_WIDTH: f32 = 0.17;
@@ -162,7 +142,8 @@ fn main() {
         .add_plugin(FlyingCameraPlugin)
         .add_startup_system(spawn_light)
         .add_startup_system(spawn_plane)
-        .add_startup_system(spawn_vehicle)
+        .add_startup_system(load_hull_mesh)
+        .add_system(spawn_vehicle)
         .add_system(apply_vehicle_nodrive_controls)
         .add_system(apply_vehicle_tank_controls)
         .add_system(apply_vehicle_drive_nw_controls)
@@ -189,8 +170,8 @@ fn spawn_plane(
     mut px_geometries: ResMut<Assets<bpx::Geometry>>,
     mut px_materials: ResMut<Assets<bpx::Material>>,
 ) {
-    let px_geometry = px_geometries.add(bpx::Geometry::halfspace());
-    let px_material = px_materials.add(bpx::Material::new(&mut physics, 0.5, 0.5, 0.6));
+    let px_geometry = px_geometries.add(bpx::Geometry::halfspace_with_normal(Vec3::Y));
+    let px_material = px_materials.add(bpx::Material::new(&mut physics, 0.5, 0.5, 0.6).unwrap());
 
     commands.spawn(InfiniteGridBundle {
         grid: InfiniteGrid {
@@ -199,19 +180,13 @@ fn spawn_plane(
         },
         ..default()
     })
-    .with_children(|builder| {
-        builder.spawn_empty()
-            .insert(SpatialBundle::from_transform(
-                // physx default plane is rotated compared to bevy plane, we undo that
-                Transform::from_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_2))
-            ))
-            .insert(bpx::RigidBody::Static)
-            .insert(bpx::Shape {
-                geometry: px_geometry,
-                material: px_material,
-                query_filter_data: FilterData::new(0, 0, 0, DRIVABLE_SURFACE),
-                simulation_filter_data: FilterData::new(COLLISION_FLAG_GROUND, COLLISION_FLAG_GROUND_AGAINST, 0, 0),
-            });
+    .insert(bpx::RigidBody::Static)
+    .insert(bpx::Shape {
+        geometry: px_geometry,
+        material: px_material,
+        query_filter_data: FilterData::new(0, 0, 0, DRIVABLE_SURFACE),
+        simulation_filter_data: FilterData::new(COLLISION_FLAG_GROUND, COLLISION_FLAG_GROUND_AGAINST, 0, 0),
+        ..default()
     })
     .insert(Name::new("Plane"));
 }
@@ -260,7 +235,6 @@ fn create_wheels_sim_data() -> Owner<VehicleWheelsSimData> {
     wheels_sim_data
 }
 
-#[allow(unused)]
 fn create_drive_nw_sim_data() -> Box<PxVehicleDriveSimDataNW> {
     let mut diff = VehicleDifferentialNWData::default();
     diff.set_driven_wheel(0, true);
@@ -283,37 +257,15 @@ fn create_drive_nw_sim_data() -> Box<PxVehicleDriveSimDataNW> {
     Box::new(drive_sim_data)
 }
 
-#[allow(unused)]
-fn create_drive_4w_sim_data() -> Box<PxVehicleDriveSimData4W> {
-    let mut drive_sim_data = PxVehicleDriveSimData4W::default();
+#[derive(Resource)]
+struct HullMesh(Handle<Mesh>);
 
-    drive_sim_data.set_diff_data(VehicleDifferential4WData {
-        diff_type: VehicleDifferential4WType::OpenRearWD,
-        ..default()
-    });
-    drive_sim_data.set_engine_data(VehicleEngineData {
-        peak_torque: 500.,
-        max_omega: 600.,
-        ..default()
-    });
-    drive_sim_data.set_gears_data(VehicleGearsData {
-        switch_time: 0.1,
-        ..default()
-    });
-    drive_sim_data.set_clutch_data(VehicleClutchData {
-        strength: 10.,
-        ..default()
-    });
-    drive_sim_data.set_ackermann_geometry_data(VehicleAckermannGeometryData {
-        front_width: (WHEEL_OFFSETS[1] - WHEEL_OFFSETS[0]).x.abs(),
-        rear_width: (WHEEL_OFFSETS[3] - WHEEL_OFFSETS[2]).x.abs(),
-        axle_separation: (WHEEL_OFFSETS[2] - WHEEL_OFFSETS[0]).z.abs(),
-        ..default()
-    });
-
-    Box::new(drive_sim_data)
+// body of the cybertruck, its convex hull is used as a chassis shape
+fn load_hull_mesh(mut commands: Commands, assets: Res<AssetServer>) {
+    commands.insert_resource(HullMesh(assets.load("cybertruck/hull.glb#Mesh9/Primitive0")));
 }
 
+#[allow(clippy::too_many_arguments)]
 fn spawn_vehicle(
     mut commands: Commands,
     assets: Res<AssetServer>,
@@ -322,7 +274,14 @@ fn spawn_vehicle(
     mut simulation: ResMut<VehicleSimulation>,
     mut px_geometries: ResMut<Assets<bpx::Geometry>>,
     mut px_materials: ResMut<Assets<bpx::Material>>,
+    meshes: Res<Assets<Mesh>>,
+    hull_mesh: Option<Res<HullMesh>>,
 ) {
+    // vehicle is spawned once, as soon as hull mesh is loaded
+    let Some(hull_mesh) = hull_mesh else { return; };
+    let Some(mesh) = meshes.get(&hull_mesh.0) else { return; };
+    commands.remove_resource::<HullMesh>();
+
     let camera = commands.spawn(FlyingCameraBundle {
         flying_camera: FlyingCamera {
             distance: 60.,
@@ -334,12 +293,12 @@ fn spawn_vehicle(
     .id();
 
     let hull_geometry = px_geometries.add(
-        bpx::Geometry::convex_mesh(&mut physics, &cooking, &HULL_VERTICES).unwrap()
+        bpx::Geometry::convex_mesh_from_mesh(&mut physics, &cooking, mesh).unwrap()
     );
     let wheel_geometry = px_geometries.add(
         bpx::Geometry::cylinder(&mut physics, &cooking, WHEEL_HALF_WIDTH, WHEEL_RADIUS, WHEEL_SEGMENTS).unwrap()
     );
-    let material = px_materials.add(bpx::Material::new(&mut physics, 0.5, 0.5, 0.6));
+    let material = px_materials.add(bpx::Material::new(&mut physics, 0.5, 0.5, 0.6).unwrap());
 
     let mut friction_pairs = VehicleDrivableSurfaceToTireFrictionPairs::new(
         1, 1, &[ &***(px_materials.get(&material).unwrap()) ], &[ VehicleDrivableSurfaceType(0) ]
@@ -360,6 +319,7 @@ fn spawn_vehicle(
                     geometry: wheel_geometry.clone(),
                     query_filter_data: FilterData::new(0, 0, 0, UNDRIVABLE_SURFACE),
                     simulation_filter_data: FilterData::new(COLLISION_FLAG_WHEEL, COLLISION_FLAG_WHEEL_AGAINST, 0, 0),
+                    ..default()
                 })
                 .with_children(|builder| {
                     builder.spawn(SceneBundle {
@@ -376,58 +336,74 @@ fn spawn_vehicle(
         );
     }
 
-    commands.spawn_empty()
+    let mut vehicle = commands.spawn_empty();
+
+    vehicle
         .insert(SceneBundle {
             scene: assets.load("cybertruck/hull.glb#Scene0"),
             ..default()
         })
-        .insert(bpx::RigidBody::Dynamic)
-        .insert(MassProperties::mass_with_center(HULL_MASS, CENTER_OF_MASS))
-
-        ////////////////////////////////////////////////////////
-        // uncomment this to test vehicle without a drive
-        /*.insert(Vehicle::NoDrive {
-            wheels: wheels.clone(),
-            wheels_sim_data: create_wheels_sim_data(),
-        })
-        .insert(PlayerControlledNoDrive)*/
-        ////////////////////////////////////////////////////////
-
-        ////////////////////////////////////////////////////////
-        // uncomment this to test a tank
-        /*.insert(Vehicle::DriveTank {
-            wheels: wheels.clone(),
-            wheels_sim_data: create_wheels_sim_data(),
-            drive_sim_data: Box::default(),
-        })
-        .insert(PlayerControlledDriveTank::default())*/
-        ////////////////////////////////////////////////////////
-
-        ////////////////////////////////////////////////////////
-        // uncomment this to test vehicle with N wheels
-        /*.insert(Vehicle::DriveNW {
-            wheels: wheels.clone(),
-            wheels_sim_data: create_wheels_sim_data(),
-            drive_sim_data: create_drive_nw_sim_data(),
-        })
-        .insert(PlayerControlledDriveNW::default())*/
-        ////////////////////////////////////////////////////////
-
-        ////////////////////////////////////////////////////////
-        // uncomment this to test vehicle with 4 wheels
-        .insert(Vehicle::Drive4W {
-            wheels: wheels.clone(),
-            wheels_sim_data: create_wheels_sim_data(),
-            drive_sim_data: create_drive_4w_sim_data(),
-        })
-        .insert(PlayerControlledDrive4W::default())
-        ////////////////////////////////////////////////////////
-
-        .insert(bpx::Shape {
-            material,
-            geometry: hull_geometry,
-            query_filter_data: FilterData::new(0, 0, 0, UNDRIVABLE_SURFACE),
-            simulation_filter_data: FilterData::new(COLLISION_FLAG_CHASSIS, COLLISION_FLAG_CHASSIS_AGAINST, 0, 0),
+        .insert(bpx::RigidBody::Dynamic);
+
+    // drive type can be selected with a command line argument,
+    // e.g. `cargo run --example vehicle -- tank`
+    match std::env::args().nth(1).as_deref() {
+        // vehicle without a drive
+        Some("nodrive") => {
+            vehicle
+                .insert(MassProperties::mass_with_center(HULL_MASS, CENTER_OF_MASS))
+                .insert(Vehicle::NoDrive {
+                    wheels: wheels.clone(),
+                    wheels_sim_data: create_wheels_sim_data(),
+                })
+                .insert(PlayerControlledNoDrive);
+        }
+        // tank
+        Some("tank") => {
+            vehicle
+                .insert(MassProperties::mass_with_center(HULL_MASS, CENTER_OF_MASS))
+                .insert(Vehicle::DriveTank {
+                    wheels: wheels.clone(),
+                    wheels_sim_data: create_wheels_sim_data(),
+                    drive_sim_data: Box::default(),
+                })
+                .insert(PlayerControlledDriveTank::default());
+        }
+        // vehicle with N wheels
+        Some("nw") => {
+            vehicle
+                .insert(MassProperties::mass_with_center(HULL_MASS, CENTER_OF_MASS))
+                .insert(Vehicle::DriveNW {
+                    wheels: wheels.clone(),
+                    wheels_sim_data: create_wheels_sim_data(),
+                    drive_sim_data: create_drive_nw_sim_data(),
+                })
+                .insert(PlayerControlledDriveNW::default());
+        }
+        // vehicle with 4 wheels, set up from cybertruck.vehicle.ron
+        _ => {
+            vehicle
+                .insert(DescribedVehicle {
+                    descriptor: assets.load("cybertruck/cybertruck.vehicle.ron"),
+                    wheels: wheels.clone(),
+                })
+                .insert(PlayerControlledDrive4W::default());
+        }
+    }
+
+    vehicle
+        .with_children(|builder| {
+            // same rotation as the mesh node in hull.glb
+            builder.spawn_empty()
+                .insert(SpatialBundle::from_transform(Transform::from_rotation(Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2))))
+                .insert(bpx::Shape {
+                    material,
+                    geometry: hull_geometry,
+                    query_filter_data: FilterData::new(0, 0, 0, UNDRIVABLE_SURFACE),
+                    simulation_filter_data: FilterData::new(COLLISION_FLAG_CHASSIS, COLLISION_FLAG_CHASSIS_AGAINST, 0, 0),
+                    ..default()
+                })
+                .insert(Name::new("Hull"));
         })
         .insert(Name::new("Vehicle"))
         .insert_children(0, &wheels)
@@ -1,26 +1,200 @@
 use bevy::prelude::*;
 use bevy::reflect::TypeUuid;
+use bevy::render::mesh::{Indices, VertexAttributeValues};
+use bevy::render::render_resource::PrimitiveTopology;
 use physx::convex_mesh::ConvexMesh;
+use physx::material::{Material as _, MaterialFlag, MaterialFlags};
 use physx::cooking::{TriangleMeshCookingResult, PxTriangleMeshDesc, ConvexMeshCookingResult, PxConvexMeshDesc, PxHeightFieldDesc};
 use physx::prelude::*;
+use physx::traits::Class;
 use physx::triangle_mesh::TriangleMesh;
 use physx_sys::{
     PxConvexFlags, PxConvexFlag, PxHeightFieldSample, PxBitAndByte, PxHeightFieldFormat, PxConvexMeshGeometryFlags,
-    PxMeshGeometryFlags, PxConvexMeshGeometryFlag, PxMeshGeometryFlag
+    PxMeshGeometryFlags, PxConvexMeshGeometryFlag, PxMeshGeometryFlag, PxGeometry, PxMeshScale_new_3,
 };
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::collections::HashSet;
 use std::ffi::c_void;
 use std::sync::{Arc, Mutex};
+use crate::cooking::CookingError;
+use crate::decomposition::{convex_decomposition, ConvexDecompositionParams};
 use crate::prelude as bpx;
 use crate::prelude::*;
 use super::PxMaterial;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Reflect, Serialize, Deserialize)]
+pub enum CombineMode {
+    #[default]
+    Average,
+    Min,
+    Multiply,
+    Max,
+}
+
+impl From<CombineMode> for physx::material::CombineMode {
+    fn from(value: CombineMode) -> Self {
+        match value {
+            CombineMode::Average => Self::Average,
+            CombineMode::Min => Self::Min,
+            CombineMode::Multiply => Self::Multiply,
+            CombineMode::Max => Self::Max,
+        }
+    }
+}
+
+impl From<physx::material::CombineMode> for CombineMode {
+    fn from(value: physx::material::CombineMode) -> Self {
+        match value {
+            physx::material::CombineMode::Average => Self::Average,
+            physx::material::CombineMode::Min => Self::Min,
+            physx::material::CombineMode::Multiply => Self::Multiply,
+            physx::material::CombineMode::Max => Self::Max,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Reflect, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaterialDescriptor {
+    pub static_friction: f32,
+    pub dynamic_friction: f32,
+    pub restitution: f32,
+    pub friction_combine_mode: CombineMode,
+    pub restitution_combine_mode: CombineMode,
+    pub disable_friction: bool,
+    pub disable_strong_friction: bool,
+    pub improved_patch_friction: bool,
+}
+
+impl Default for MaterialDescriptor {
+    fn default() -> Self {
+        // same as PhysX defaults, apart from friction and restitution
+        Self {
+            static_friction: 0.5,
+            dynamic_friction: 0.5,
+            restitution: 0.6,
+            friction_combine_mode: CombineMode::Average,
+            restitution_combine_mode: CombineMode::Average,
+            disable_friction: false,
+            disable_strong_friction: false,
+            improved_patch_friction: false,
+        }
+    }
+}
+
+impl MaterialDescriptor {
+    pub fn new(static_friction: f32, dynamic_friction: f32, restitution: f32) -> Self {
+        Self { static_friction, dynamic_friction, restitution, ..default() }
+    }
+}
+
+/// Arbitrary user value attached to a material (e.g. "ice", "mud", an `Entity` or a `Handle`),
+/// reported back in contact events and scene query hits.
+#[derive(Clone)]
+pub struct SurfaceTag(Arc<dyn Any + Send + Sync>);
+
+impl SurfaceTag {
+    pub fn new<T: Any + Send + Sync>(value: T) -> Self {
+        Self(Arc::new(value))
+    }
+
+    pub fn is<T: Any>(&self) -> bool {
+        self.0.is::<T>()
+    }
+
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.0.downcast_ref::<T>()
+    }
+
+    /// Surface tag of a raw material, as found in contact pairs and query hits.
+    ///
+    /// # Safety
+    /// `material` must be null or point to a material created by this plugin.
+    pub unsafe fn from_raw_material(material: *const physx_sys::PxMaterial) -> Option<Self> {
+        let material = (material as *const PxMaterial).as_ref()?;
+        material.get_user_data().as_deref().cloned()
+    }
+}
+
+impl std::fmt::Debug for SurfaceTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("SurfaceTag").finish_non_exhaustive()
+    }
+}
+
+// boxed, so it fits into PxMaterial::userData without extra allocation done by physx crate
+pub(crate) type MaterialUserData = Option<Box<SurfaceTag>>;
+
+/// PhysX material, shared by all shapes using it.
+///
+/// Changing it with [`Material::set_descriptor`] applies to every shape immediately,
+/// replacing the asset itself re-assigns materials of every shape on the next frame.
 #[derive(TypeUuid, Deref, DerefMut)]
 #[uuid = "5351ec05-c0fd-426a-b35e-62008a6b10e1"]
 pub struct Material(Owner<PxMaterial>);
 
 impl Material {
-    pub fn new(physics: &mut bpx::Physics, static_friction: f32, dynamic_friction: f32, restitution: f32) -> Self {
-        physics.create_material(static_friction, dynamic_friction, restitution, ()).unwrap().into()
+    /// `None` if PhysX rejects the parameters, e.g. negative friction.
+    pub fn new(physics: &mut bpx::Physics, static_friction: f32, dynamic_friction: f32, restitution: f32) -> Option<Self> {
+        Self::from_descriptor(physics, &MaterialDescriptor::new(static_friction, dynamic_friction, restitution))
+    }
+
+    /// `None` if PhysX rejects the descriptor, e.g. negative friction.
+    pub fn from_descriptor(physics: &mut bpx::Physics, desc: &MaterialDescriptor) -> Option<Self> {
+        let mut material: Self = physics.create_material(desc.static_friction, desc.dynamic_friction, desc.restitution, None)?
+            .into();
+        material.set_descriptor(desc);
+        Some(material)
+    }
+
+    pub fn descriptor(&self) -> MaterialDescriptor {
+        let flags = self.get_flags();
+        MaterialDescriptor {
+            static_friction: self.get_static_friction(),
+            dynamic_friction: self.get_dynamic_friction(),
+            restitution: self.get_restitution(),
+            friction_combine_mode: self.get_friction_combine_mode().into(),
+            restitution_combine_mode: self.get_restitution_combine_mode().into(),
+            disable_friction: flags.contains(MaterialFlag::DisableFriction),
+            disable_strong_friction: flags.contains(MaterialFlag::DisableStrongFriction),
+            improved_patch_friction: flags.contains(MaterialFlag::ImprovedPatchFriction),
+        }
+    }
+
+    pub fn with_surface(mut self, surface: SurfaceTag) -> Self {
+        self.set_surface(Some(surface));
+        self
+    }
+
+    pub fn surface(&self) -> Option<&SurfaceTag> {
+        self.get_user_data().as_deref()
+    }
+
+    pub fn set_surface(&mut self, surface: Option<SurfaceTag>) {
+        *self.get_user_data_mut() = surface.map(Box::new);
+    }
+
+    pub fn set_descriptor(&mut self, desc: &MaterialDescriptor) {
+        let mut flags = MaterialFlags::empty();
+        if desc.disable_friction { flags |= MaterialFlag::DisableFriction; }
+        if desc.disable_strong_friction { flags |= MaterialFlag::DisableStrongFriction; }
+        if desc.improved_patch_friction { flags |= MaterialFlag::ImprovedPatchFriction; }
+
+        self.set_static_friction(desc.static_friction);
+        self.set_dynamic_friction(desc.dynamic_friction);
+        self.set_restitution(desc.restitution);
+        self.set_friction_combined_mode(desc.friction_combine_mode.into());
+        self.set_restitution_combine_mode(desc.restitution_combine_mode.into());
+        self.set_flags(flags);
+    }
+}
+
+impl Drop for Material {
+    fn drop(&mut self) {
+        // PxMaterial may outlive this asset while shapes still reference it,
+        // so leave user data empty instead of dangling
+        self.set_surface(None);
     }
 }
 
@@ -34,6 +208,9 @@ impl From<Owner<PxMaterial>> for Material {
 #[uuid = "db246120-e6af-4ebf-a95a-a6efe1c54d9f"]
 pub struct Geometry {
     pub obj: GeometryInner,
+    /// rotation added to local pose of shapes using this geometry, PhysX capsules
+    /// and planes are aligned to X axis, and this turns them to other axes
+    pub axis_rotation: Quat,
 }
 
 #[derive(Clone)]
@@ -52,25 +229,25 @@ pub enum GeometryInner {
 
 impl From<PxSphereGeometry> for Geometry {
     fn from(value: PxSphereGeometry) -> Self {
-        Self { obj: GeometryInner::Sphere(value) }
+        Self { obj: GeometryInner::Sphere(value), axis_rotation: Quat::IDENTITY }
     }
 }
 
 impl From<PxPlaneGeometry> for Geometry {
     fn from(value: PxPlaneGeometry) -> Self {
-        Self { obj: GeometryInner::Plane(value) }
+        Self { obj: GeometryInner::Plane(value), axis_rotation: Quat::IDENTITY }
     }
 }
 
 impl From<PxCapsuleGeometry> for Geometry {
     fn from(value: PxCapsuleGeometry) -> Self {
-        Self { obj: GeometryInner::Capsule(value) }
+        Self { obj: GeometryInner::Capsule(value), axis_rotation: Quat::IDENTITY }
     }
 }
 
 impl From<PxBoxGeometry> for Geometry {
     fn from(value: PxBoxGeometry) -> Self {
-        Self { obj: GeometryInner::Box(value) }
+        Self { obj: GeometryInner::Box(value), axis_rotation: Quat::IDENTITY }
     }
 }
 
@@ -81,7 +258,7 @@ impl From<Owner<ConvexMesh>> for Geometry {
             scale: Vec3::ONE,
             rotation: Quat::IDENTITY,
             flags: PxConvexMeshGeometryFlags { mBits: 0 },
-        }) }
+        }), axis_rotation: Quat::IDENTITY }
     }
 }
 
@@ -92,7 +269,7 @@ impl From<Owner<TriangleMesh>> for Geometry {
             scale: Vec3::ONE,
             rotation: Quat::IDENTITY,
             flags: PxMeshGeometryFlags { mBits: 0 },
-        }) }
+        }), axis_rotation: Quat::IDENTITY }
     }
 }
 
@@ -102,7 +279,8 @@ impl From<Owner<HeightField>> for Geometry {
             hfield: Arc::new(Mutex::new(value)),
             scale: Vec3::ONE,
             flags: PxMeshGeometryFlags { mBits: 0 },
-        }) }
+            materials: vec![],
+        }), axis_rotation: Quat::IDENTITY }
     }
 }
 
@@ -111,14 +289,46 @@ impl Geometry {
         PxSphereGeometry::new(radius).into()
     }
 
+    /// Plane through the origin with normal pointing along +X.
     pub fn halfspace() -> Self {
         PxPlaneGeometry::new().into()
     }
 
+    /// Plane through the origin with the given normal, e.g. `Vec3::Y` for ground,
+    /// zero normal falls back to +X.
+    pub fn halfspace_with_normal(normal: Vec3) -> Self {
+        Self::halfspace().with_axis(normal)
+    }
+
+    /// Capsule aligned to X axis.
     pub fn capsule(half_height: f32, radius: f32) -> Self {
         PxCapsuleGeometry::new(radius, half_height).into()
     }
 
+    pub fn capsule_y(half_height: f32, radius: f32) -> Self {
+        Self::capsule(half_height, radius).with_axis(Vec3::Y)
+    }
+
+    pub fn capsule_z(half_height: f32, radius: f32) -> Self {
+        Self::capsule(half_height, radius).with_axis(Vec3::Z)
+    }
+
+    // rotates X axis of the geometry to `axis`, zero axis keeps it
+    fn with_axis(mut self, axis: Vec3) -> Self {
+        self.axis_rotation = Quat::from_rotation_arc(Vec3::X, axis.try_normalize().unwrap_or(Vec3::X));
+        self
+    }
+
+    /// Local pose of a shape using this geometry, placed at `transform` relative to its actor.
+    pub(crate) fn shape_local_pose(&self, transform: &Transform) -> Transform {
+        transform.mul_transform(Transform::from_rotation(self.axis_rotation))
+    }
+
+    /// Transform relative to its actor of a shape using this geometry, inverse of `shape_local_pose`.
+    pub(crate) fn shape_transform(&self, local_pose: &Transform) -> Transform {
+        local_pose.mul_transform(Transform::from_rotation(self.axis_rotation.inverse()))
+    }
+
     pub fn cuboid(hx: f32, hy: f32, hz: f32) -> Self {
         PxBoxGeometry::new(hx / 2., hy / 2., hz / 2.).into()
     }
@@ -128,48 +338,158 @@ impl Geometry {
         cooking: &Cooking,
         verts: &[Vec3],
     ) -> Result<Self, ConvexMeshCookingError> {
-        let verts = verts.iter().map(|v| v.to_physx()).collect::<Vec<_>>();
+        if cooking.params().mesh_validation {
+            let report = validate_convex_mesh(cooking, verts);
+            if !report.is_valid() { return Err(ConvexMeshCookingError::Invalid(report)); }
+        }
 
-        let mut mesh_desc = PxConvexMeshDesc::new();
-        mesh_desc.obj.points.count = verts.len() as u32;
-        mesh_desc.obj.points.stride = std::mem::size_of::<PxVec3>() as u32;
-        mesh_desc.obj.points.data = verts.as_ptr() as *const c_void;
-        mesh_desc.obj.flags = PxConvexFlags { mBits: PxConvexFlag::eCOMPUTE_CONVEX as u16 };
+        let px_verts = verts.iter().map(|v| v.to_physx()).collect::<Vec<_>>();
+        let mesh_desc = convex_mesh_desc(&px_verts, cooking.params());
 
         match cooking.create_convex_mesh(physics.physics_mut(), &mesh_desc) {
             ConvexMeshCookingResult::Success(mesh) => Ok(mesh.into()),
-            ConvexMeshCookingResult::Failure => Err(ConvexMeshCookingError::Failure),
+            ConvexMeshCookingResult::Failure => Err(convex_mesh_failure(cooking, verts)),
             ConvexMeshCookingResult::InvalidDescriptor => Err(ConvexMeshCookingError::InvalidDescriptor),
             ConvexMeshCookingResult::PolygonsLimitReached => Err(ConvexMeshCookingError::PolygonsLimitReached),
             ConvexMeshCookingResult::ZeroAreaTestFailed => Err(ConvexMeshCookingError::ZeroAreaTestFailed),
         }
     }
 
+    /// Same as [`Geometry::convex_mesh`], but cooked with different parameters than `cooking` was created with.
+    pub fn convex_mesh_with_params(
+        physics: &mut bpx::Physics,
+        cooking: &Cooking,
+        verts: &[Vec3],
+        params: &CookingDescriptor,
+    ) -> Result<Self, ConvexMeshCookingError> {
+        let cooking = cooking.with_params(physics, params);
+        Self::convex_mesh(physics, &cooking, verts)
+    }
+
     pub fn trimesh(
         physics: &mut bpx::Physics,
         cooking: &Cooking,
         verts: &[Vec3],
         indices: &[[u32; 3]],
     ) -> Result<Self, TriangleMeshCookingError> {
-        let verts = verts.iter().map(|v| v.to_physx()).collect::<Vec<_>>();
+        Self::trimesh_with_material_indices(physics, cooking, verts, indices, &[])
+    }
+
+    /// Triangle mesh with per-face material indices into `bpx::Shape::materials`,
+    /// `material_indices` must be empty or have one index per triangle.
+    pub fn trimesh_with_material_indices(
+        physics: &mut bpx::Physics,
+        cooking: &Cooking,
+        verts: &[Vec3],
+        indices: &[[u32; 3]],
+        material_indices: &[u16],
+    ) -> Result<Self, TriangleMeshCookingError> {
+        if !material_indices.is_empty() && material_indices.len() != indices.len() {
+            return Err(TriangleMeshCookingError::InvalidDescriptor);
+        }
 
-        let mut mesh_desc = PxTriangleMeshDesc::new();
-        mesh_desc.obj.points.count = verts.len() as u32;
-        mesh_desc.obj.points.stride = std::mem::size_of::<PxVec3>() as u32;
-        mesh_desc.obj.points.data = verts.as_ptr() as *const c_void;
+        if cooking.params().mesh_validation {
+            let report = validate_triangle_mesh(cooking, verts, indices);
+            if !report.is_valid() { return Err(TriangleMeshCookingError::Invalid(report)); }
+        }
 
-        mesh_desc.obj.triangles.count = indices.len() as u32;
-        mesh_desc.obj.triangles.stride = std::mem::size_of::<[u32; 3]>() as u32;
-        mesh_desc.obj.triangles.data = indices.as_ptr() as *const c_void;
+        let px_verts = verts.iter().map(|v| v.to_physx()).collect::<Vec<_>>();
+        let mut mesh_desc = triangle_mesh_desc(&px_verts, indices);
+
+        if !material_indices.is_empty() {
+            mesh_desc.obj.materialIndices.stride = std::mem::size_of::<u16>() as u32;
+            mesh_desc.obj.materialIndices.data = material_indices.as_ptr();
+        }
 
         match cooking.create_triangle_mesh(physics.physics_mut(), &mesh_desc) {
             TriangleMeshCookingResult::Success(mesh) => Ok(mesh.into()),
-            TriangleMeshCookingResult::Failure => Err(TriangleMeshCookingError::Failure),
+            TriangleMeshCookingResult::Failure => Err(triangle_mesh_failure(cooking, verts, indices)),
             TriangleMeshCookingResult::InvalidDescriptor => Err(TriangleMeshCookingError::InvalidDescriptor),
             TriangleMeshCookingResult::LargeTriangle => Err(TriangleMeshCookingError::LargeTriangle),
         }
     }
 
+    /// Same as [`Geometry::trimesh`], but cooked with different parameters than `cooking` was created with.
+    pub fn trimesh_with_params(
+        physics: &mut bpx::Physics,
+        cooking: &Cooking,
+        verts: &[Vec3],
+        indices: &[[u32; 3]],
+        params: &CookingDescriptor,
+    ) -> Result<Self, TriangleMeshCookingError> {
+        let cooking = cooking.with_params(physics, params);
+        Self::trimesh(physics, &cooking, verts, indices)
+    }
+
+    /// Cook convex hull of all vertices of a bevy mesh.
+    pub fn convex_mesh_from_mesh(
+        physics: &mut bpx::Physics,
+        cooking: &Cooking,
+        mesh: &Mesh,
+    ) -> Result<Self, ConvexMeshCookingError> {
+        let (verts, _) = extract_mesh_buffers(mesh).ok_or(ConvexMeshCookingError::InvalidMesh)?;
+        Self::convex_mesh(physics, cooking, &verts)
+    }
+
+    /// Same as [`Geometry::convex_mesh_from_mesh`], but cooked with different parameters than `cooking` was created with.
+    pub fn convex_mesh_from_mesh_with_params(
+        physics: &mut bpx::Physics,
+        cooking: &Cooking,
+        mesh: &Mesh,
+        params: &CookingDescriptor,
+    ) -> Result<Self, ConvexMeshCookingError> {
+        let cooking = cooking.with_params(physics, params);
+        Self::convex_mesh_from_mesh(physics, &cooking, mesh)
+    }
+
+    /// Cook triangle mesh from a bevy mesh, only `PrimitiveTopology::TriangleList` is supported.
+    pub fn trimesh_from_mesh(
+        physics: &mut bpx::Physics,
+        cooking: &Cooking,
+        mesh: &Mesh,
+    ) -> Result<Self, TriangleMeshCookingError> {
+        let (verts, indices) = extract_mesh_buffers(mesh).ok_or(TriangleMeshCookingError::InvalidMesh)?;
+        Self::trimesh(physics, cooking, &verts, &indices)
+    }
+
+    /// Same as [`Geometry::trimesh_from_mesh`], but cooked with different parameters than `cooking` was created with.
+    pub fn trimesh_from_mesh_with_params(
+        physics: &mut bpx::Physics,
+        cooking: &Cooking,
+        mesh: &Mesh,
+        params: &CookingDescriptor,
+    ) -> Result<Self, TriangleMeshCookingError> {
+        let cooking = cooking.with_params(physics, params);
+        Self::trimesh_from_mesh(physics, &cooking, mesh)
+    }
+
+    /// Split concave mesh into convex hulls, so it can be used with dynamic actors.
+    ///
+    /// Each hull is cooked separately, so a hull that fails to cook doesn't discard the others.
+    pub fn convex_decomposition(
+        physics: &mut bpx::Physics,
+        cooking: &Cooking,
+        verts: &[Vec3],
+        indices: &[[u32; 3]],
+        params: &ConvexDecompositionParams,
+    ) -> Vec<Result<Self, ConvexMeshCookingError>> {
+        convex_decomposition(verts, indices, params)
+            .iter()
+            .map(|hull| Self::convex_mesh(physics, cooking, hull))
+            .collect()
+    }
+
+    pub fn convex_decomposition_from_mesh(
+        physics: &mut bpx::Physics,
+        cooking: &Cooking,
+        mesh: &Mesh,
+        params: &ConvexDecompositionParams,
+    ) -> Result<Vec<Result<Self, ConvexMeshCookingError>>, ConvexMeshCookingError> {
+        let (verts, indices) = extract_mesh_buffers(mesh).ok_or(ConvexMeshCookingError::InvalidMesh)?;
+        Ok(Self::convex_decomposition(physics, cooking, &verts, &indices, params))
+    }
+
+    /// Convex cylinder aligned to X axis, same as capsules.
     pub fn cylinder(
         physics: &mut bpx::Physics,
         cooking: &Cooking,
@@ -177,6 +497,39 @@ impl Geometry {
         radius: f32,
         segments: usize,
     ) -> Result<Self, ConvexMeshCookingError> {
+        Self::cylinder_along(physics, cooking, half_height, radius, segments, Vec3::X)
+    }
+
+    pub fn cylinder_y(
+        physics: &mut bpx::Physics,
+        cooking: &Cooking,
+        half_height: f32,
+        radius: f32,
+        segments: usize,
+    ) -> Result<Self, ConvexMeshCookingError> {
+        Self::cylinder_along(physics, cooking, half_height, radius, segments, Vec3::Y)
+    }
+
+    pub fn cylinder_z(
+        physics: &mut bpx::Physics,
+        cooking: &Cooking,
+        half_height: f32,
+        radius: f32,
+        segments: usize,
+    ) -> Result<Self, ConvexMeshCookingError> {
+        Self::cylinder_along(physics, cooking, half_height, radius, segments, Vec3::Z)
+    }
+
+    // cylinder is a cooked mesh, so the axis is baked into its vertices instead of shape pose
+    fn cylinder_along(
+        physics: &mut bpx::Physics,
+        cooking: &Cooking,
+        half_height: f32,
+        radius: f32,
+        segments: usize,
+        axis: Vec3,
+    ) -> Result<Self, ConvexMeshCookingError> {
+        let rotation = Quat::from_rotation_arc(Vec3::X, axis);
         let mut points = vec![Vec3::default(); 2 * segments];
 
         for i in 0..segments {
@@ -184,43 +537,118 @@ impl Geometry {
             let sin_theta = (i as f32 * std::f32::consts::PI * 2. / segments as f32).sin();
             let y = radius * cos_theta;
             let z = radius * sin_theta;
-            points[2 * i]    = Vec3::new(-half_height, y, z);
-            points[2 * i + 1] = Vec3::new(half_height, y, z);
+            points[2 * i]    = rotation * Vec3::new(-half_height, y, z);
+            points[2 * i + 1] = rotation * Vec3::new(half_height, y, z);
         }
 
         Self::convex_mesh(physics, cooking, &points)
     }
 
+    /// Fails if `heights` doesn't have `num_rows * num_cols` samples, or PhysX rejects them.
     pub fn heightfield(
         physics: &mut bpx::Physics,
         cooking: &Cooking,
         heights: &[i16],
         num_rows: usize,
         num_cols: usize,
-    ) -> Self {
-        assert_eq!(heights.len(), num_rows * num_cols, "invalid number of heights provided");
+    ) -> Result<Self, CookingError> {
+        if heights.len() != num_rows * num_cols { return Err(CookingError::HeightField); }
+
+        let samples = heightfield_samples(heights);
+        let hfield_desc = heightfield_desc(&samples, num_rows, num_cols);
+
+        let mesh = cooking.create_height_field(physics.physics_mut(), &hfield_desc)
+            .ok_or(CookingError::HeightField)?;
+
+        Ok(mesh.into())
+    }
+
+    /// Same as [`Geometry::heightfield`], but cooked with different parameters than `cooking` was created with.
+    pub fn heightfield_with_params(
+        physics: &mut bpx::Physics,
+        cooking: &Cooking,
+        heights: &[i16],
+        num_rows: usize,
+        num_cols: usize,
+        params: &CookingDescriptor,
+    ) -> Result<Self, CookingError> {
+        let cooking = cooking.with_params(physics, params);
+        Self::heightfield(physics, &cooking, heights, num_rows, num_cols)
+    }
+
+    /// Modifies triangle mesh vertices in place and refits its BVH, without cooking it again.
+    ///
+    /// Vertices are in cooked order (see `TriangleMesh::get_vertices`), which is the input order
+    /// unless mesh cleaning removed duplicate or unused vertices. Returns refitted bounds in mesh space.
+    ///
+    /// Shapes using this geometry keep stale bounds until their geometry is set again,
+    /// use [`crate::deformable::TriangleMeshEdit`] to do both.
+    pub fn modify_trimesh_vertices(&self, f: impl FnOnce(&mut [Vec3])) -> Option<(Vec3, Vec3)> {
+        let GeometryInner::TriangleMesh(ref geom) = self.obj else {
+            bevy::log::warn!("unable to modify vertices, wrong geometry type (not TriangleMesh)");
+            return None;
+        };
 
-        let mut samples = Vec::with_capacity(num_rows * num_cols);
+        let mut mesh = geom.mesh.lock().unwrap();
+        let px_verts = mesh.get_vertices_for_modification();
+        let mut verts = px_verts.iter().map(|v| v.to_bevy()).collect::<Vec<_>>();
 
-        for height in heights.iter().copied() {
-            samples.push(PxHeightFieldSample {
-                height,
-                materialIndex0: PxBitAndByte { mData: 0 },
-                materialIndex1: PxBitAndByte { mData: 0 },
-            });
+        f(&mut verts);
+
+        for (px_vert, vert) in px_verts.iter_mut().zip(verts) {
+            *px_vert = vert.to_physx();
         }
 
-        let mut hfield_desc = PxHeightFieldDesc::new();
-        hfield_desc.obj.format = PxHeightFieldFormat::eS16_TM;
-        hfield_desc.obj.nbColumns = num_cols as u32;
-        hfield_desc.obj.nbRows = num_rows as u32;
-        hfield_desc.obj.samples.stride = std::mem::size_of::<PxHeightFieldSample>() as u32;
-        hfield_desc.obj.samples.data = samples.as_ptr() as *const c_void;
+        let bounds: physx_sys::PxBounds3 = mesh.refit_bvh().into();
+        let (min, max) = (bounds.minimum, bounds.maximum);
+        Some((Vec3::new(min.x, min.y, min.z), Vec3::new(max.x, max.y, max.z)))
+    }
 
-        let mesh = cooking.create_height_field(physics.physics_mut(), &hfield_desc)
-            .expect("create_height_field failure");
+    /// Calls `f` with PhysX geometry built from this geometry, meshes stay locked during the call.
+    pub(crate) fn with_px_geometry<R>(&self, f: impl FnOnce(*const PxGeometry) -> R) -> R {
+        match self.obj {
+            GeometryInner::Sphere(geom)  => f(geom.as_ptr()),
+            GeometryInner::Plane(geom)   => f(geom.as_ptr()),
+            GeometryInner::Capsule(geom) => f(geom.as_ptr()),
+            GeometryInner::Box(geom)     => f(geom.as_ptr()),
+            GeometryInner::ConvexMesh(ref geom) => {
+                let mut mesh = geom.mesh.lock().unwrap();
+                let geometry = PxConvexMeshGeometry::new(
+                    mesh.as_mut(),
+                    unsafe { &PxMeshScale_new_3(geom.scale.to_physx_sys().as_ptr(), geom.rotation.to_physx().as_ptr()) },
+                    geom.flags,
+                );
+                f(geometry.as_ptr())
+            },
+            GeometryInner::TriangleMesh(ref geom) => {
+                let mut mesh = geom.mesh.lock().unwrap();
+                let geometry = PxTriangleMeshGeometry::new(
+                    mesh.as_mut(),
+                    unsafe { &PxMeshScale_new_3(geom.scale.to_physx_sys().as_ptr(), geom.rotation.to_physx().as_ptr()) },
+                    geom.flags,
+                );
+                f(geometry.as_ptr())
+            },
+            GeometryInner::HeightField(ref geom) => {
+                let mut hfield = geom.hfield.lock().unwrap();
+                let geometry = PxHeightFieldGeometry::new(
+                    hfield.as_mut(),
+                    geom.flags,
+                    geom.scale.y,
+                    geom.scale.x,
+                    geom.scale.z,
+                );
+                f(geometry.as_ptr())
+            },
+        }
+    }
 
-        mesh.into()
+    /// Materials used by the geometry itself (heightfield cells), overriding shape material.
+    pub fn materials(&self) -> &[Handle<bpx::Material>] {
+        match &self.obj {
+            GeometryInner::HeightField(obj) => &obj.materials,
+            _ => &[],
+        }
     }
 
     pub fn with_scale(mut self, scale: Vec3) -> Self {
@@ -286,12 +714,201 @@ impl Geometry {
     }
 }
 
+// descriptors only store pointers, so buffers must outlive them
+
+pub(crate) fn convex_mesh_desc(verts: &[PxVec3], params: &CookingDescriptor) -> PxConvexMeshDesc {
+    let mut flags = PxConvexFlag::eCOMPUTE_CONVEX;
+    if !params.mesh_validation {
+        flags |= PxConvexFlag::eDISABLE_MESH_VALIDATION;
+    }
+
+    let mut mesh_desc = PxConvexMeshDesc::new();
+    mesh_desc.obj.points.count = verts.len() as u32;
+    mesh_desc.obj.points.stride = std::mem::size_of::<PxVec3>() as u32;
+    mesh_desc.obj.points.data = verts.as_ptr() as *const c_void;
+    mesh_desc.obj.flags = PxConvexFlags { mBits: flags as u16 };
+    mesh_desc.obj.vertexLimit = params.convex_vertex_limit;
+    mesh_desc
+}
+
+pub(crate) fn triangle_mesh_desc(verts: &[PxVec3], indices: &[[u32; 3]]) -> PxTriangleMeshDesc {
+    let mut mesh_desc = PxTriangleMeshDesc::new();
+    mesh_desc.obj.points.count = verts.len() as u32;
+    mesh_desc.obj.points.stride = std::mem::size_of::<PxVec3>() as u32;
+    mesh_desc.obj.points.data = verts.as_ptr() as *const c_void;
+
+    mesh_desc.obj.triangles.count = indices.len() as u32;
+    mesh_desc.obj.triangles.stride = std::mem::size_of::<[u32; 3]>() as u32;
+    mesh_desc.obj.triangles.data = indices.as_ptr() as *const c_void;
+    mesh_desc
+}
+
+pub(crate) fn heightfield_samples(heights: &[i16]) -> Vec<PxHeightFieldSample> {
+    heights.iter().copied().map(|height| PxHeightFieldSample {
+        height,
+        materialIndex0: PxBitAndByte { mData: 0 },
+        materialIndex1: PxBitAndByte { mData: 0 },
+    }).collect()
+}
+
+pub(crate) fn heightfield_desc(samples: &[PxHeightFieldSample], num_rows: usize, num_cols: usize) -> PxHeightFieldDesc {
+    let mut hfield_desc = PxHeightFieldDesc::new();
+    hfield_desc.obj.format = PxHeightFieldFormat::eS16_TM;
+    hfield_desc.obj.nbColumns = num_cols as u32;
+    hfield_desc.obj.nbRows = num_rows as u32;
+    hfield_desc.obj.samples.stride = std::mem::size_of::<PxHeightFieldSample>() as u32;
+    hfield_desc.obj.samples.data = samples.as_ptr() as *const c_void;
+    hfield_desc
+}
+
+/// Returns vertex positions and triangles of a mesh.
+///
+/// Mesh must be a `PrimitiveTopology::TriangleList` with `Float32x3` positions,
+/// non-indexed meshes are treated as consecutive triangles.
+pub fn extract_mesh_buffers(mesh: &Mesh) -> Option<(Vec<Vec3>, Vec<[u32; 3]>)> {
+    if mesh.primitive_topology() != PrimitiveTopology::TriangleList { return None; }
+
+    let verts = match mesh.attribute(Mesh::ATTRIBUTE_POSITION)? {
+        VertexAttributeValues::Float32x3(positions) => positions.iter().copied().map(Vec3::from).collect::<Vec<_>>(),
+        _ => return None,
+    };
+
+    let indices = match mesh.indices() {
+        Some(Indices::U16(indices)) => indices.iter().map(|i| *i as u32).collect::<Vec<_>>(),
+        Some(Indices::U32(indices)) => indices.clone(),
+        None => (0..verts.len() as u32).collect(),
+    };
+
+    let triangles = indices.chunks_exact(3).map(|t| [t[0], t[1], t[2]]).collect();
+
+    Some((verts, triangles))
+}
+
+/// Problems found in mesh data, either before cooking or after PhysX failed to cook it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MeshValidationReport {
+    pub vertex_count: usize,
+    pub triangle_count: usize,
+    /// vertices with NaN or infinite coordinates
+    pub non_finite_vertices: usize,
+    /// indices pointing past the end of the vertex buffer
+    pub out_of_range_indices: usize,
+    /// only checked when mesh cleaning is disabled, otherwise PhysX welds them
+    pub duplicate_vertices: usize,
+    /// triangles with repeated indices or zero area,
+    /// only checked when mesh cleaning is disabled, otherwise PhysX removes them
+    pub degenerate_triangles: usize,
+    pub first_invalid_triangle: Option<usize>,
+    /// convex hull has less than 4 vertices or all of them are coplanar
+    pub degenerate_hull: bool,
+    /// `PxCooking::validate_*` returned false
+    pub rejected_by_physx: bool,
+}
+
+impl MeshValidationReport {
+    pub fn is_valid(&self) -> bool {
+        *self == Self { vertex_count: self.vertex_count, triangle_count: self.triangle_count, ..default() }
+    }
+
+    fn check_vertices(&mut self, verts: &[Vec3]) {
+        self.vertex_count = verts.len();
+        self.non_finite_vertices = verts.iter().filter(|v| !v.is_finite()).count();
+    }
+
+    fn count_duplicate_vertices(&mut self, verts: &[Vec3]) {
+        let mut seen = HashSet::with_capacity(verts.len());
+        self.duplicate_vertices = verts.iter()
+            .filter(|v| !seen.insert(v.to_array().map(f32::to_bits)))
+            .count();
+    }
+}
+
+/// Checks vertices before they are cooked into a convex hull,
+/// PhysX validation is only used if these checks pass.
+pub fn validate_convex_mesh(cooking: &Cooking, verts: &[Vec3]) -> MeshValidationReport {
+    let mut report = MeshValidationReport::default();
+    report.check_vertices(verts);
+    if report.non_finite_vertices > 0 { return report; }
+
+    // find 4 points spanning the largest tetrahedron we can cheaply get,
+    // if its volume is zero, all points are on a plane or a line
+    let farthest = |metric: &dyn Fn(Vec3) -> f32| {
+        verts.iter().copied().map(|v| (v, metric(v))).fold((Vec3::ZERO, 0.), |a, b| if b.1 > a.1 { b } else { a })
+    };
+
+    report.degenerate_hull = match verts.first() {
+        Some(&p0) if verts.len() >= 4 => {
+            let (p1, extent) = farthest(&|v: Vec3| v.distance(p0));
+            let (p2, _) = farthest(&|v: Vec3| (v - p0).cross(p1 - p0).length());
+            let normal = (p1 - p0).cross(p2 - p0).normalize_or_zero();
+            let (_, height) = farthest(&|v: Vec3| (v - p0).dot(normal).abs());
+            height <= extent * 1e-5
+        }
+        _ => true,
+    };
+
+    if report.is_valid() {
+        let px_verts = verts.iter().map(|v| v.to_physx()).collect::<Vec<_>>();
+        report.rejected_by_physx = !cooking.validate_convex_mesh(&convex_mesh_desc(&px_verts, cooking.params()));
+    }
+
+    report
+}
+
+/// Checks triangle mesh data, PhysX validation is only used if mesh cleaning is disabled,
+/// as it rejects meshes which cleaning would otherwise fix.
+pub fn validate_triangle_mesh(cooking: &Cooking, verts: &[Vec3], indices: &[[u32; 3]]) -> MeshValidationReport {
+    let mut report = MeshValidationReport { triangle_count: indices.len(), ..default() };
+    report.check_vertices(verts);
+
+    let cleaning = cooking.params().mesh_cleaning;
+    if !cleaning { report.count_duplicate_vertices(verts); }
+
+    for (idx, triangle) in indices.iter().enumerate() {
+        let out_of_range = triangle.iter().filter(|i| **i as usize >= verts.len()).count();
+        report.out_of_range_indices += out_of_range;
+
+        let degenerate = !cleaning && out_of_range == 0 && {
+            let [a, b, c] = triangle.map(|i| verts[i as usize]);
+            triangle[0] == triangle[1] || triangle[1] == triangle[2] || triangle[0] == triangle[2]
+                || (b - a).cross(c - a).length_squared() == 0.
+        };
+        if degenerate { report.degenerate_triangles += 1; }
+
+        if (out_of_range > 0 || degenerate) && report.first_invalid_triangle.is_none() {
+            report.first_invalid_triangle = Some(idx);
+        }
+    }
+
+    if !cleaning && report.is_valid() {
+        let px_verts = verts.iter().map(|v| v.to_physx()).collect::<Vec<_>>();
+        report.rejected_by_physx = !cooking.validate_triangle_mesh(&triangle_mesh_desc(&px_verts, indices));
+    }
+
+    report
+}
+
+// cooking failed without specific reason, try to find out why
+
+pub(crate) fn convex_mesh_failure(cooking: &Cooking, verts: &[Vec3]) -> ConvexMeshCookingError {
+    let report = validate_convex_mesh(cooking, verts);
+    if report.is_valid() { ConvexMeshCookingError::Failure } else { ConvexMeshCookingError::Invalid(report) }
+}
+
+pub(crate) fn triangle_mesh_failure(cooking: &Cooking, verts: &[Vec3], indices: &[[u32; 3]]) -> TriangleMeshCookingError {
+    let report = validate_triangle_mesh(cooking, verts, indices);
+    if report.is_valid() { TriangleMeshCookingError::Failure } else { TriangleMeshCookingError::Invalid(report) }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ConvexMeshCookingError {
     Failure,
     InvalidDescriptor,
     PolygonsLimitReached,
     ZeroAreaTestFailed,
+    /// bevy mesh is not a triangle list or has no positions
+    InvalidMesh,
+    Invalid(MeshValidationReport),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -299,6 +916,9 @@ pub enum TriangleMeshCookingError {
     Failure,
     InvalidDescriptor,
     LargeTriangle,
+    /// bevy mesh is not a triangle list or has no positions
+    InvalidMesh,
+    Invalid(MeshValidationReport),
 }
 
 #[derive(Clone)]
@@ -322,4 +942,32 @@ pub struct GeometryInnerHeightField {
     pub hfield: Arc<Mutex<Owner<HeightField>>>,
     pub scale: Vec3,
     pub flags: PxMeshGeometryFlags,
+    /// materials referenced by per-cell material indices, shape material is used if empty
+    pub materials: Vec<Handle<bpx::Material>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn axis_rotation_is_undone_by_shape_transform() {
+        let geometry = Geometry::capsule_y(1., 0.5);
+        let transform = Transform::from_xyz(1., 2., 3.).with_rotation(Quat::from_rotation_z(0.3));
+
+        let local_pose = geometry.shape_local_pose(&transform);
+        assert!((local_pose.rotation * Vec3::X).abs_diff_eq(transform.rotation * Vec3::Y, 1e-6));
+
+        let result = geometry.shape_transform(&local_pose);
+        assert!(result.translation.abs_diff_eq(transform.translation, 1e-6));
+        assert!(result.rotation.abs_diff_eq(transform.rotation, 1e-6));
+    }
+
+    #[test]
+    fn zero_halfspace_normal_falls_back_to_x() {
+        let geometry = Geometry::halfspace_with_normal(Vec3::ZERO);
+
+        assert!(geometry.axis_rotation.is_finite());
+        assert!((geometry.axis_rotation * Vec3::X).abs_diff_eq(Vec3::X, 1e-6));
+    }
 }
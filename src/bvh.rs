@@ -0,0 +1,112 @@
+//! Bounding volume hierarchies over arbitrary sets of AABBs.
+use bevy::prelude::*;
+use physx::bvh_structure::BvhStructure;
+use physx::cooking::PxBvhStructureDesc;
+use physx::math::PxBounds3;
+use physx::prelude::*;
+use physx::traits::Class;
+use physx_sys::{
+    PxBounds3_new_1,
+    PxRigidActor_getNbShapes,
+    PxRigidActor_getShapes,
+    PxShapeExt_getWorldBounds_mut,
+};
+
+use crate::prelude as bpx;
+use crate::prelude::*;
+
+/// PhysX BVH structure, used to speed up scene queries against actors with lots of shapes
+/// (see `bpx::ActorBvh`), or on its own to query a set of bounds without a scene.
+///
+/// Queries return indices of the bounds the structure was built from.
+pub struct Bvh {
+    bvh: Owner<BvhStructure>,
+}
+
+impl Bvh {
+    /// Each bound is a `(min, max)` pair, same as bounds returned by `bpx::Geometry::modify_trimesh_vertices`.
+    pub fn new(physics: &mut bpx::Physics, cooking: &bpx::Cooking, bounds: &[(Vec3, Vec3)]) -> Option<Self> {
+        let bounds = bounds.iter()
+            .map(|(min, max)| unsafe { PxBounds3_new_1(&min.to_physx_sys(), &max.to_physx_sys()) })
+            .collect::<Vec<_>>();
+
+        Self::from_px_bounds(physics, cooking, &bounds)
+    }
+
+    // bounds of actor shapes in world space, in the same order as the shapes
+    pub(crate) fn from_actor_shapes(
+        physics: &mut bpx::Physics,
+        cooking: &bpx::Cooking,
+        actor: *const physx_sys::PxRigidActor,
+    ) -> Option<Self> {
+        let bounds = unsafe {
+            let mut shapes = vec![std::ptr::null_mut(); PxRigidActor_getNbShapes(actor) as usize];
+            let count = PxRigidActor_getShapes(actor, shapes.as_mut_ptr(), shapes.len() as u32, 0);
+            shapes.truncate(count as usize);
+
+            shapes.iter()
+                // inflation is the PhysX default
+                .map(|shape| PxShapeExt_getWorldBounds_mut(*shape, actor, 1.01))
+                .collect::<Vec<_>>()
+        };
+
+        Self::from_px_bounds(physics, cooking, &bounds)
+    }
+
+    fn from_px_bounds(physics: &mut bpx::Physics, cooking: &bpx::Cooking, bounds: &[physx_sys::PxBounds3]) -> Option<Self> {
+        if bounds.is_empty() { return None; }
+
+        let mut desc = PxBvhStructureDesc::new();
+        desc.obj.bounds.data = bounds.as_ptr() as *const std::ffi::c_void;
+        desc.obj.bounds.stride = std::mem::size_of::<physx_sys::PxBounds3>() as u32;
+        desc.obj.bounds.count = bounds.len() as u32;
+
+        let bvh = cooking.create_bvh_structure(physics.physics_mut(), &desc)?;
+        Some(Self { bvh })
+    }
+
+    pub fn len(&self) -> usize {
+        self.bvh.get_nb_bounds() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Bounds of the structure as `(min, max)` pairs.
+    pub fn bounds(&self) -> Vec<(Vec3, Vec3)> {
+        self.bvh.get_bounds().iter().map(|bounds| {
+            let bounds: physx_sys::PxBounds3 = unsafe { *bounds.as_ptr() };
+            (bounds.minimum.to_bevy(), bounds.maximum.to_bevy())
+        }).collect()
+    }
+
+    /// Indices of the bounds hit by a ray, in no particular order, empty if `direction` is zero.
+    pub fn raycast(&self, origin: Vec3, direction: Vec3, max_distance: f32, max_hits: u32) -> Vec<u32> {
+        let direction = direction.normalize_or_zero();
+        if direction == Vec3::ZERO { return vec![]; }
+
+        self.bvh.raycast(&origin.to_physx(), &direction.to_physx(), max_distance, max_hits)
+    }
+
+    /// Indices of the bounds overlapping an AABB given by `min` and `max`.
+    pub fn overlap(&self, min: Vec3, max: Vec3, max_hits: u32) -> Vec<u32> {
+        self.bvh.overlap(&px_bounds(min, max), max_hits)
+    }
+
+    /// Indices of the bounds hit by an AABB swept along `direction`, empty if `direction` is zero.
+    pub fn sweep(&self, min: Vec3, max: Vec3, direction: Vec3, max_distance: f32, max_hits: u32) -> Vec<u32> {
+        let direction = direction.normalize_or_zero();
+        if direction == Vec3::ZERO { return vec![]; }
+
+        self.bvh.sweep(&px_bounds(min, max), &direction.to_physx(), max_distance, max_hits)
+    }
+
+    pub(crate) fn as_ptr(&self) -> *const physx_sys::PxBVHStructure {
+        self.bvh.as_ptr()
+    }
+}
+
+fn px_bounds(min: Vec3, max: Vec3) -> PxBounds3 {
+    unsafe { PxBounds3_new_1(&min.to_physx_sys(), &max.to_physx_sys()) }.into()
+}
@@ -1,9 +1,98 @@
 use physx::prelude::*;
 use super::*;
 
-pub struct OnCollision;
+use std::sync::{Arc, Mutex};
+use physx::shape::Shape as _;
+use physx_sys::{PxContactPairFlag, PxContactPairPoint, PxContactPair_extractContacts, PxPairFlag, PxShape_getMaterialFromInternalFaceIndex};
+use crate::assets::SurfaceTag;
+use crate::resources::actor_entity;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContactEventKind {
+    Found,
+    Persists,
+    Lost,
+}
+
+#[derive(Debug, Clone)]
+pub struct ContactPoint {
+    pub position: Vec3,
+    pub normal: Vec3,
+    pub impulse: Vec3,
+    pub separation: f32,
+    /// surface tags of materials at the contact point on each shape,
+    /// per-face materials of triangle meshes and heightfields are taken into account
+    pub surfaces: [Option<SurfaceTag>; 2],
+}
+
+/// Contact between two shapes, sent for pairs the simulation filter shader
+/// requested touch notifications and contact points for.
+#[derive(Debug, Clone)]
+pub struct ContactEvent {
+    pub kind: ContactEventKind,
+    /// entities of shapes in contact
+    pub shapes: [Entity; 2],
+    /// entities of actors in contact
+    pub actors: [Entity; 2],
+    pub points: Vec<ContactPoint>,
+}
+
+// shape pointers are queued along with events, shared shapes are mapped to entities by `Scene` afterwards
+pub(crate) type ContactEventQueue = Arc<Mutex<Vec<(ContactEvent, [usize; 2])>>>;
+
+pub struct OnCollision(ContactEventQueue);
+
+impl OnCollision {
+    pub(crate) fn new(queue: ContactEventQueue) -> Self {
+        Self(queue)
+    }
+}
+
 impl CollisionCallback for OnCollision {
-    fn on_collision(&mut self, _header: &physx_sys::PxContactPairHeader, _pairs: &[physx_sys::PxContactPair]) {}
+    fn on_collision(&mut self, header: &physx_sys::PxContactPairHeader, pairs: &[physx_sys::PxContactPair]) {
+        let mut queue = self.0.lock().unwrap();
+
+        for pair in pairs {
+            // user data of removed shapes is no longer valid
+            let removed = (PxContactPairFlag::eREMOVED_SHAPE_0 | PxContactPairFlag::eREMOVED_SHAPE_1) as u16;
+            if pair.flags.mBits & removed != 0 { continue; }
+
+            let kind = if pair.events.mBits & PxPairFlag::eNOTIFY_TOUCH_FOUND as u16 != 0 {
+                ContactEventKind::Found
+            } else if pair.events.mBits & PxPairFlag::eNOTIFY_TOUCH_LOST as u16 != 0 {
+                ContactEventKind::Lost
+            } else {
+                ContactEventKind::Persists
+            };
+
+            let shapes = unsafe { [ &*(pair.shapes[0] as *const PxShape), &*(pair.shapes[1] as *const PxShape) ] };
+
+            let mut buffer: Vec<PxContactPairPoint> = Vec::with_capacity(pair.contactCount as usize);
+            let points = unsafe {
+                let count = PxContactPair_extractContacts(pair, buffer.as_mut_ptr(), buffer.capacity() as u32);
+                buffer.set_len(count as usize);
+
+                buffer.iter().map(|point| ContactPoint {
+                    position: point.position.to_bevy(),
+                    normal: point.normal.to_bevy(),
+                    impulse: point.impulse.to_bevy(),
+                    separation: point.separation,
+                    surfaces: [
+                        SurfaceTag::from_raw_material(PxShape_getMaterialFromInternalFaceIndex(pair.shapes[0], point.internalFaceIndex0)),
+                        SurfaceTag::from_raw_material(PxShape_getMaterialFromInternalFaceIndex(pair.shapes[1], point.internalFaceIndex1)),
+                    ],
+                }).collect()
+            };
+
+            queue.push((ContactEvent {
+                kind,
+                shapes: [ *shapes[0].get_user_data(), *shapes[1].get_user_data() ],
+                // actors of a pair with removed shapes are skipped above, so both are valid
+                actors: unsafe { [ actor_entity(header.actors[0]), actor_entity(header.actors[1]) ] },
+                points,
+            }, [ pair.shapes[0] as usize, pair.shapes[1] as usize ]));
+        }
+    }
 }
 
 pub struct OnTrigger;
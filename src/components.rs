@@ -1,12 +1,18 @@
 use std::collections::HashMap;
+use std::ops::{Index, IndexMut, Range};
 use std::ptr::drop_in_place;
+use std::sync::Mutex;
 
 use bevy::prelude::*;
 use derive_more::{Deref, DerefMut};
 use physx::prelude::*;
 use physx::traits::{Class, PxFlags};
 use physx_sys::{
-    PxShape_release_mut, PxPhysics_createShape_mut, PxFilterData, PxFilterData_new_2, PxMeshScale_new_3,
+    PxShape_release_mut, PxPhysics_createShape_mut_1, PxFilterData, PxFilterData_new_2,
+    PxShape_getContactOffset, PxShape_setContactOffset_mut, PxShape_setRestOffset_mut,
+    PxShape_acquireReference_mut, PxShape_isExclusive,
+    PxArticulationCache, PxArticulationLink_getInboundJoint,
+    PxAggregate_getScene_mut, PxScene_removeAggregate_mut,
 };
 
 use physx::vehicles::{
@@ -15,27 +21,126 @@ use physx::vehicles::{
     PxVehicleDrive4W, PxVehicleDriveNW, VehicleDrive4W, VehicleDriveNW, VehicleWheelsSimData
 };
 
-use crate::assets::GeometryInner;
-use crate::bpx::{IntoPxVec3, IntoPxQuat};
+use crate::decomposition::ConvexDecompositionParams;
+use crate::errors::PhysicsError;
+use crate::bpx::IntoBevyTransform;
 use crate::prelude as bpx;
 use crate::resources::SceneRwLock;
-use super::{PxRigidStatic, PxRigidDynamic, PxShape};
+use super::{PxAggregate, PxArticulationReducedCoordinate, PxRigidStatic, PxRigidDynamic, PxShape};
 
 #[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum RigidBody {
     Dynamic,
+    /// dynamic actor moved only through its `Transform`, pushing other actors out of its way
+    Kinematic,
     Static,
+    ArticulationLink,
 }
 
 #[derive(Component, Clone, Default)]
 pub struct Shape {
     pub geometry: Handle<bpx::Geometry>,
     pub material: Handle<bpx::Material>,
+    /// materials indexed by per-face material indices of triangle meshes and heightfields,
+    /// `material` is ignored if this is not empty
+    pub materials: Vec<Handle<bpx::Material>>,
     pub query_filter_data: FilterData,
     pub simulation_filter_data: FilterData,
 }
 
-#[derive(Clone, Copy, Default, PartialEq, Eq)]
+impl Shape {
+    /// Materials PhysX shape is created with: `materials` if set,
+    /// otherwise materials of the geometry itself (heightfield cells), otherwise `material`.
+    pub fn material_handles(&self, geometry: &bpx::Geometry) -> Vec<Handle<bpx::Material>> {
+        if !self.materials.is_empty() { return self.materials.clone(); }
+        if !geometry.materials().is_empty() { return geometry.materials().to_vec(); }
+        vec![ self.material.clone() ]
+    }
+}
+
+/// Optional companion of `bpx::Shape`, changes are applied to existing shapes as well,
+/// so colliders can be disabled temporarily without despawning them.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Reflect)]
+pub struct ShapeSettings {
+    /// shape takes part in raycasts, sweeps and overlaps
+    pub scene_query: bool,
+    /// shape takes part in collisions
+    pub simulation: bool,
+    pub visualization: bool,
+    /// `None` keeps PhysX default (0.02 scaled by tolerances length)
+    pub contact_offset: Option<f32>,
+    /// `None` keeps PhysX default (zero)
+    pub rest_offset: Option<f32>,
+    /// Reuse one non-exclusive PhysX shape among all shapes with the same geometry, materials,
+    /// filter data, local pose and settings. Only used at creation; as shared shapes are,
+    /// well, shared, later changes of settings don't apply to them.
+    pub shared: bool,
+}
+
+impl Default for ShapeSettings {
+    fn default() -> Self {
+        Self {
+            scene_query: true,
+            simulation: true,
+            visualization: true,
+            contact_offset: None,
+            rest_offset: None,
+            shared: false,
+        }
+    }
+}
+
+impl ShapeSettings {
+    pub fn query_only() -> Self {
+        Self { simulation: false, ..default() }
+    }
+
+    pub fn simulation_only() -> Self {
+        Self { scene_query: false, ..default() }
+    }
+
+    pub fn disabled() -> Self {
+        Self { scene_query: false, simulation: false, visualization: false, ..default() }
+    }
+
+    pub fn shared() -> Self {
+        Self { shared: true, ..default() }
+    }
+
+    pub fn flags(&self) -> ShapeFlags {
+        let mut flags = ShapeFlags::empty();
+        if self.scene_query { flags |= ShapeFlag::SceneQueryShape; }
+        if self.simulation { flags |= ShapeFlag::SimulationShape; }
+        if self.visualization { flags |= ShapeFlag::Visualization; }
+        flags
+    }
+
+    pub fn apply(&self, shape: &mut ShapeHandle) {
+        use physx::shape::Shape;
+
+        shape.set_flag(ShapeFlag::SceneQueryShape, self.scene_query);
+        shape.set_flag(ShapeFlag::SimulationShape, self.simulation);
+        shape.set_flag(ShapeFlag::Visualization, self.visualization);
+
+        unsafe {
+            let ptr = shape.as_mut_ptr();
+
+            // PhysX requires contact offset to stay greater than rest offset,
+            // so rest offset goes first unless it grows past the current contact offset
+            let rest_first = self.rest_offset.map_or(true, |rest_offset| rest_offset < PxShape_getContactOffset(ptr));
+
+            if rest_first {
+                if let Some(rest_offset) = self.rest_offset { PxShape_setRestOffset_mut(ptr, rest_offset); }
+                if let Some(contact_offset) = self.contact_offset { PxShape_setContactOffset_mut(ptr, contact_offset); }
+            } else {
+                if let Some(contact_offset) = self.contact_offset { PxShape_setContactOffset_mut(ptr, contact_offset); }
+                if let Some(rest_offset) = self.rest_offset { PxShape_setRestOffset_mut(ptr, rest_offset); }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
 pub struct FilterData([ u32; 4 ]);
 
 impl FilterData {
@@ -59,52 +164,82 @@ impl ShapeHandle {
         Self(Some(px_shape))
     }
 
-    pub fn create_shape(physics: &mut bpx::Physics, geometry: &mut bpx::Geometry, material: &mut bpx::Material, user_data: Entity) -> Self {
-        let geometry_ptr = match geometry.obj {
-            GeometryInner::Sphere(geom)  => { geom.as_ptr() },
-            GeometryInner::Plane(geom)   => { geom.as_ptr() },
-            GeometryInner::Capsule(geom) => { geom.as_ptr() },
-            GeometryInner::Box(geom)     => { geom.as_ptr() },
-            GeometryInner::ConvexMesh(ref mut geom) => {
-                PxConvexMeshGeometry::new(
-                    geom.mesh.lock().unwrap().as_mut(),
-                    unsafe { &PxMeshScale_new_3(geom.scale.to_physx_sys().as_ptr(), geom.rotation.to_physx().as_ptr()) },
-                    geom.flags,
-                ).as_ptr()
-            },
-            GeometryInner::TriangleMesh(ref mut geom) => {
-                PxTriangleMeshGeometry::new(
-                    geom.mesh.lock().unwrap().as_mut(),
-                    unsafe { &PxMeshScale_new_3(geom.scale.to_physx_sys().as_ptr(), geom.rotation.to_physx().as_ptr()) },
-                    geom.flags,
-                ).as_ptr()
-            },
-            GeometryInner::HeightField(ref mut geom) => {
-                PxHeightFieldGeometry::new(
-                    geom.hfield.lock().unwrap().as_mut(),
-                    geom.flags,
-                    geom.scale.y,
-                    geom.scale.x,
-                    geom.scale.z,
-                ).as_ptr()
-            },
-        };
+    pub fn create_shape(
+        physics: &mut bpx::Physics,
+        geometry: &mut bpx::Geometry,
+        material: &mut bpx::Material,
+        user_data: Entity,
+    ) -> Result<Self, PhysicsError> {
+        Self::create_shape_with_materials(physics, geometry, &[ &*material ], user_data)
+    }
+
+    /// Multiple materials are only allowed for geometry with per-triangle material indices
+    /// (triangle meshes and heightfields), PhysX rejects them for other geometry types.
+    pub fn create_shape_with_materials(
+        physics: &mut bpx::Physics,
+        geometry: &mut bpx::Geometry,
+        materials: &[&bpx::Material],
+        user_data: Entity,
+    ) -> Result<Self, PhysicsError> {
+        Self::create_shape_with_settings(physics, geometry, materials, &default(), user_data)
+    }
+
+    /// Errors are reported for `user_data`, as it's the entity shape is created for.
+    pub fn create_shape_with_settings(
+        physics: &mut bpx::Physics,
+        geometry: &mut bpx::Geometry,
+        materials: &[&bpx::Material],
+        settings: &ShapeSettings,
+        user_data: Entity,
+    ) -> Result<Self, PhysicsError> {
+        if materials.is_empty() {
+            return Err(PhysicsError::InvalidDescriptor { entity: user_data, message: "shape requires at least one material" });
+        }
+
+        let material_ptrs: Vec<*const physx_sys::PxMaterial> = materials.iter().map(|material| material.as_ptr()).collect();
 
         //let shape = physics.create_shape(geometry, materials, is_exclusive, shape_flags, user_data)
-        let shape : Owner<PxShape> = unsafe {
+        let shape : Option<Owner<PxShape>> = geometry.with_px_geometry(|geometry_ptr| unsafe {
             physx::shape::Shape::from_raw(
-                PxPhysics_createShape_mut(
+                PxPhysics_createShape_mut_1(
                     physics.physics_mut().as_mut_ptr(),
                     geometry_ptr,
-                    material.as_ptr(),
-                    true,
-                    (ShapeFlag::SceneQueryShape | ShapeFlag::SimulationShape | ShapeFlag::Visualization).into_px(),
+                    material_ptrs.as_ptr(),
+                    material_ptrs.len() as u16,
+                    !settings.shared,
+                    settings.flags().into_px(),
                 ),
                 user_data
-            ).unwrap()
+            )
+        });
+
+        let Some(shape) = shape else {
+            return Err(PhysicsError::InvalidDescriptor { entity: user_data, message: "failed to create shape" });
         };
 
-        Self::new(shape)
+        let mut shape_handle = Self::new(shape);
+        if settings.contact_offset.is_some() || settings.rest_offset.is_some() {
+            settings.apply(&mut shape_handle);
+        }
+        Ok(shape_handle)
+    }
+
+    /// Another handle to the same shape, each handle holds its own PhysX reference.
+    /// Only non-exclusive shapes can be attached to more than one actor.
+    pub fn share(&mut self) -> Self {
+        use physx::shape::Shape;
+
+        // user data is shared as well, it stays pointing to the entity shape was created for
+        let user_data = *self.get_user_data();
+
+        unsafe {
+            PxShape_acquireReference_mut(self.as_mut_ptr());
+            Self::new(Shape::from_raw(self.as_mut_ptr(), user_data).unwrap())
+        }
+    }
+
+    pub fn is_exclusive(&self) -> bool {
+        unsafe { PxShape_isExclusive(self.as_ptr()) }
     }
 }
 
@@ -170,6 +305,127 @@ impl RigidStaticHandle {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComputedCollider {
+    TriMesh,
+    ConvexHull,
+}
+
+/// Generates shapes for every mesh of a scene spawned on this entity.
+///
+/// Once the scene instance is ready, each entity with `Handle<Mesh>` gets a `Shape` and this
+/// component is removed. Actor on the same entity is not created until that happens.
+#[derive(Component, Clone)]
+pub struct AsyncSceneCollider {
+    /// collider used for meshes not listed in `named_shapes`, `None` skips them
+    pub shape: Option<ComputedCollider>,
+    /// per mesh overrides by entity name (or its parent name for gltf primitives)
+    pub named_shapes: HashMap<String, Option<ComputedCollider>>,
+    pub material: Handle<bpx::Material>,
+}
+
+impl Default for AsyncSceneCollider {
+    fn default() -> Self {
+        Self {
+            shape: Some(ComputedCollider::TriMesh),
+            named_shapes: default(),
+            material: default(),
+        }
+    }
+}
+
+/// Replaces a concave mesh with a set of convex hulls.
+///
+/// Once the mesh is loaded, each hull is spawned as a child entity with `Shape` and this
+/// component is removed. Actor on the same entity is not created until that happens.
+#[derive(Component, Clone, Default)]
+pub struct ConvexDecompositionCollider {
+    pub mesh: Handle<Mesh>,
+    pub params: ConvexDecompositionParams,
+    pub material: Handle<bpx::Material>,
+}
+
+/// Puts every physics actor nested under this entity into a single PhysX aggregate.
+///
+/// Aggregate occupies one broad-phase entry, and pairs between its own actors are only
+/// generated if `self_collision` is set. Actors spawned together with the aggregate are
+/// added to the scene as a unit.
+///
+/// Actors that don't fit into `max_actors` are added to the scene on their own, and reported
+/// as `PhysicsError::InvalidDescriptor`. Removing this component keeps its actors in the scene
+/// without an aggregate, while despawning it takes them out of the scene together with it.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Aggregate {
+    /// maximum number of actors (including articulation links), PhysX limit is 128
+    pub max_actors: u32,
+    pub self_collision: bool,
+}
+
+impl Default for Aggregate {
+    fn default() -> Self {
+        Self { max_actors: 128, self_collision: false }
+    }
+}
+
+impl Aggregate {
+    pub fn new(max_actors: u32, self_collision: bool) -> Self {
+        Self { max_actors, self_collision }
+    }
+}
+
+#[derive(Component, Deref, DerefMut)]
+pub struct AggregateHandle {
+    #[deref]
+    #[deref_mut]
+    pub handle: SceneRwLock<Owner<PxAggregate>>,
+    pub in_scene: bool,
+}
+
+impl AggregateHandle {
+    pub fn new(px_aggregate: Owner<PxAggregate>) -> Self {
+        Self { handle: SceneRwLock::new(px_aggregate), in_scene: false }
+    }
+}
+
+impl Drop for AggregateHandle {
+    fn drop(&mut self) {
+        if !self.in_scene { return; }
+
+        // released aggregate would put its actors back into the scene one by one,
+        // so it's removed together with them instead
+        unsafe {
+            // SAFETY: handle is being dropped, nothing else can access the aggregate
+            let aggregate = self.handle.get_mut_unsafe().as_mut_ptr();
+            let scene = PxAggregate_getScene_mut(aggregate);
+
+            if !scene.is_null() {
+                PxScene_removeAggregate_mut(scene, aggregate, true);
+            }
+        }
+    }
+}
+
+/// Adds static actors nested under this entity to the scene with one pruning structure, removing it takes them out together.
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct StaticChunk;
+
+/// Inserted on actors and articulations waiting for geometry or material assets of their shapes,
+/// removed once they are created. Assets that failed to load or don't exist are reported as `PhysicsError`.
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct PhysicsPending;
+
+/// Marks a `StaticChunk` whose actors were added to the scene.
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct StaticChunkHandle;
+
+/// Cooks a BVH structure over shapes of this actor and adds the actor to the scene with it,
+/// which speeds up scene queries against actors with thousands of shapes (e.g. merged building pieces).
+///
+/// Structure is built once, from shapes the actor is created with. Static actors inside
+/// a `StaticChunk` are added by the chunk pruning structure, so it doesn't apply to them.
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct ActorBvh;
+
 #[derive(Component, Debug, Default, PartialEq, Reflect, Clone, Copy)]
 pub struct Velocity {
     pub linvel: Vec3,
@@ -217,6 +473,16 @@ pub enum Vehicle {
     },
 }
 
+/// Vehicle set up from `bpx::VehicleDescriptor` asset, can be used instead of `bpx::Vehicle`.
+///
+/// Wheels are matched with wheels of the descriptor by index. Actor is created once
+/// the descriptor is loaded, and uses its chassis mass unless `bpx::MassProperties` is present.
+#[derive(Component, Clone)]
+pub struct DescribedVehicle {
+    pub descriptor: Handle<bpx::VehicleDescriptor>,
+    pub wheels: Vec<Entity>,
+}
+
 #[derive(Component)]
 pub enum VehicleHandle {
     NoDrive(SceneRwLock<Owner<PxVehicleNoDrive>>),
@@ -226,7 +492,12 @@ pub enum VehicleHandle {
 }
 
 impl VehicleHandle {
-    pub fn new(vehicle_desc: &mut Vehicle, physics: &mut bpx::Physics, actor: &mut PxRigidDynamic) -> Self {
+    /// Wheels are mapped to shapes of `actor`, errors name the entity of the actor.
+    pub fn new(vehicle_desc: &mut Vehicle, physics: &mut bpx::Physics, actor: &mut PxRigidDynamic) -> Result<Self, PhysicsError> {
+        use physx::shape::Shape;
+
+        let entity = *actor.get_user_data();
+
         let (wheels, wheels_sim_data) = match vehicle_desc {
             Vehicle::NoDrive { wheels, wheels_sim_data } => (wheels, wheels_sim_data),
             Vehicle::Drive4W { wheels, wheels_sim_data, .. } => (wheels, wheels_sim_data),
@@ -236,49 +507,69 @@ impl VehicleHandle {
 
         let mut shape_mapping = HashMap::new();
         for (idx, shape) in actor.get_shapes().into_iter().enumerate() {
-            use physx::shape::Shape;
             shape_mapping.insert(*shape.get_user_data(), idx as i32);
         }
 
-        for (wheel_id, entity) in wheels.iter().enumerate() {
-            wheels_sim_data.set_wheel_shape_mapping(wheel_id as u32, *shape_mapping.get(entity).unwrap());
+        for (wheel_id, wheel) in wheels.iter().enumerate() {
+            let shape_id = shape_mapping.get(wheel).ok_or(PhysicsError::WheelMapping { entity, wheel: *wheel })?;
+            wheels_sim_data.set_wheel_shape_mapping(wheel_id as u32, *shape_id);
         }
 
-        match vehicle_desc {
+        let invalid = |message| PhysicsError::InvalidDescriptor { entity, message };
+
+        Ok(match vehicle_desc {
             Vehicle::NoDrive { wheels: _, wheels_sim_data } => {
-                Self::NoDrive(
-                    SceneRwLock::new(VehicleNoDrive::new(physics.physics_mut(), actor, wheels_sim_data).unwrap())
-                )
+                Self::NoDrive(SceneRwLock::new(
+                    VehicleNoDrive::new(physics.physics_mut(), actor, wheels_sim_data)
+                        .ok_or_else(|| invalid("failed to create BPxVehicle::NoDrive"))?
+                ))
             }
             Vehicle::Drive4W { wheels, wheels_sim_data, drive_sim_data } => {
-                Self::Drive4W(
-                    SceneRwLock::new(VehicleDrive4W::new(physics.physics_mut(), actor, wheels_sim_data, drive_sim_data.as_ref(), wheels.len() as u32 - 4).unwrap())
-                )
+                let non_driven_wheels = (wheels.len() as u32).checked_sub(4)
+                    .ok_or_else(|| invalid("BPxVehicle::Drive4W requires at least 4 wheels"))?;
+
+                Self::Drive4W(SceneRwLock::new(
+                    VehicleDrive4W::new(physics.physics_mut(), actor, wheels_sim_data, drive_sim_data.as_ref(), non_driven_wheels)
+                        .ok_or_else(|| invalid("failed to create BPxVehicle::Drive4W"))?
+                ))
             }
             Vehicle::DriveNW { wheels, wheels_sim_data, drive_sim_data } => {
-                Self::DriveNW(
-                    SceneRwLock::new(VehicleDriveNW::new(physics.physics_mut(), actor, wheels_sim_data, drive_sim_data.as_ref(), wheels.len() as u32).unwrap())
-                )
+                Self::DriveNW(SceneRwLock::new(
+                    VehicleDriveNW::new(physics.physics_mut(), actor, wheels_sim_data, drive_sim_data.as_ref(), wheels.len() as u32)
+                        .ok_or_else(|| invalid("failed to create BPxVehicle::DriveNW"))?
+                ))
             }
             Vehicle::DriveTank { wheels, wheels_sim_data, drive_sim_data } => {
-                Self::DriveTank(
-                    SceneRwLock::new(VehicleDriveTank::new(physics.physics_mut(), actor, wheels_sim_data, drive_sim_data.as_ref(), wheels.len() as u32).unwrap())
-                )
+                Self::DriveTank(SceneRwLock::new(
+                    VehicleDriveTank::new(physics.physics_mut(), actor, wheels_sim_data, drive_sim_data.as_ref(), wheels.len() as u32)
+                        .ok_or_else(|| invalid("failed to create BPxVehicle::DriveTank"))?
+                ))
             }
-        }
+        })
     }
 }
 
 #[derive(Component, Debug, Clone)]
 pub enum MassProperties {
+    /// Density of shapes without `ColliderDensity`.
     Density {
         density: f32,
         center: Vec3,
     },
+    /// Total mass, distributed over shapes according to their `ColliderDensity` (if any).
     Mass {
         mass: f32,
         center: Vec3,
     },
+    /// Mass, center and inertia are used as is, shapes are not taken into account.
+    Explicit {
+        mass: f32,
+        center: Vec3,
+        /// diagonal of the inertia tensor in mass space
+        inertia: Vec3,
+        /// rotation of mass space relative to the actor
+        principal_axes: Quat,
+    },
 }
 
 impl MassProperties {
@@ -297,4 +588,703 @@ impl MassProperties {
     pub fn mass_with_center(mass: f32, center: Vec3) -> Self {
         Self::Mass { mass, center }
     }
+
+    pub fn explicit(mass: f32, center: Vec3, inertia: Vec3) -> Self {
+        Self::Explicit { mass, center, inertia, principal_axes: Quat::IDENTITY }
+    }
+
+    pub fn explicit_with_axes(mass: f32, center: Vec3, inertia: Vec3, principal_axes: Quat) -> Self {
+        Self::Explicit { mass, center, inertia, principal_axes }
+    }
+}
+
+/// Density of a single shape, overriding density of its actor's `MassProperties`.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Deref, DerefMut)]
+pub struct ColliderDensity(pub f32);
+
+/// Mass properties of a dynamic actor or articulation link as computed by PhysX,
+/// updated by the plugin whenever mass is recalculated.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct ComputedMassProperties {
+    pub(crate) mass: f32,
+    pub(crate) center: Vec3,
+    pub(crate) inertia: Vec3,
+    pub(crate) principal_axes: Quat,
+}
+
+impl ComputedMassProperties {
+    pub fn mass(&self) -> f32 {
+        self.mass
+    }
+
+    pub fn center(&self) -> Vec3 {
+        self.center
+    }
+
+    /// Diagonal of the inertia tensor in mass space.
+    pub fn inertia(&self) -> Vec3 {
+        self.inertia
+    }
+
+    /// Rotation of mass space relative to the actor.
+    pub fn principal_axes(&self) -> Quat {
+        self.principal_axes
+    }
+}
+
+/// Marks the root link of a reduced coordinate articulation.
+///
+/// Entity with this component is the root link, and every descendant entity with
+/// `RigidBody::ArticulationLink` becomes a link attached to its nearest ancestor link.
+#[derive(Component, Debug, Default, Clone)]
+pub struct Articulation {
+    pub fix_base: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ArticulationJointDrive {
+    pub stiffness: f32,
+    pub damping: f32,
+    pub max_force: f32,
+    pub drive_type: ArticulationDriveType,
+    pub target: f32,
+}
+
+impl ArticulationJointDrive {
+    pub fn new(stiffness: f32, damping: f32, max_force: f32) -> Self {
+        Self { stiffness, damping, max_force, drive_type: ArticulationDriveType::Force, target: 0. }
+    }
+
+    pub fn with_target(mut self, target: f32) -> Self {
+        self.target = target;
+        self
+    }
+}
+
+/// Inbound joint of an articulation link, connecting it to its parent link.
+///
+/// Arrays are indexed by `ArticulationAxis as usize`.
+#[derive(Component, Debug, Clone)]
+pub struct ArticulationJoint {
+    pub joint_type: ArticulationJointType,
+    /// joint frame relative to the child link, parent frame is computed from current transforms
+    pub frame: Transform,
+    pub motion: [ArticulationMotion; 6],
+    pub limits: [(f32, f32); 6],
+    pub drives: [Option<ArticulationJointDrive>; 6],
+}
+
+impl Default for ArticulationJoint {
+    fn default() -> Self {
+        Self {
+            joint_type: ArticulationJointType::Fix,
+            frame: Transform::IDENTITY,
+            motion: [ArticulationMotion::Locked; 6],
+            limits: [(0., 0.); 6],
+            drives: [None; 6],
+        }
+    }
+}
+
+impl ArticulationJoint {
+    pub fn fixed() -> Self {
+        Self::default()
+    }
+
+    /// revolute joint rotating around X axis of the joint frame
+    pub fn revolute() -> Self {
+        let mut joint = Self { joint_type: ArticulationJointType::Revolute, ..default() };
+        joint.motion[ArticulationAxis::Twist as usize] = ArticulationMotion::Free;
+        joint
+    }
+
+    /// prismatic joint sliding along X axis of the joint frame
+    pub fn prismatic() -> Self {
+        let mut joint = Self { joint_type: ArticulationJointType::Prismatic, ..default() };
+        joint.motion[ArticulationAxis::X as usize] = ArticulationMotion::Free;
+        joint
+    }
+
+    pub fn spherical() -> Self {
+        let mut joint = Self { joint_type: ArticulationJointType::Spherical, ..default() };
+        for axis in ArticulationAxis::angular_axes() {
+            joint.motion[*axis as usize] = ArticulationMotion::Free;
+        }
+        joint
+    }
+
+    pub fn with_frame(mut self, frame: Transform) -> Self {
+        self.frame = frame;
+        self
+    }
+
+    pub fn with_limit(mut self, axis: ArticulationAxis, min: f32, max: f32) -> Self {
+        self.motion[axis as usize] = ArticulationMotion::Limited;
+        self.limits[axis as usize] = (min, max);
+        self
+    }
+
+    pub fn with_drive(mut self, axis: ArticulationAxis, drive: ArticulationJointDrive) -> Self {
+        self.drives[axis as usize] = Some(drive);
+        self
+    }
+
+    /// axes that are not locked, in the order PhysX stores their dofs
+    pub fn dof_axes(&self) -> Vec<ArticulationAxis> {
+        ArticulationAxis::all_axes()
+            .copied()
+            .filter(|axis| !matches!(self.motion[*axis as usize], ArticulationMotion::Locked))
+            .collect()
+    }
+}
+
+#[derive(Component, Deref, DerefMut)]
+pub struct ArticulationHandle {
+    #[deref]
+    #[deref_mut]
+    pub handle: SceneRwLock<Owner<PxArticulationReducedCoordinate>>,
+    /// link entities, indexed by PhysX link index
+    pub links: Vec<Entity>,
+    // used for change detection
+    pub cached_transform: GlobalTransform,
+    // created on first use, articulation has to be in the scene by then
+    cache: Mutex<Option<SharedCache>>,
+}
+
+struct SharedCache {
+    cache: ArticulationCache,
+    layout: DofLayout,
+}
+
+// SAFETY: cache is only accessed behind the mutex, while holding a lock on the articulation
+unsafe impl Send for SharedCache {}
+unsafe impl Sync for SharedCache {}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArticulationLinkHandle {
+    pub articulation: Entity,
+    pub index: usize,
+}
+
+impl ArticulationHandle {
+    pub fn new(px_articulation: Owner<PxArticulationReducedCoordinate>, links: Vec<Entity>, transform: GlobalTransform) -> Self {
+        Self { handle: SceneRwLock::new(px_articulation), links, cached_transform: transform, cache: default() }
+    }
+
+    pub fn link_index(&self, entity: Entity) -> Option<usize> {
+        self.links.iter().position(|link| *link == entity)
+    }
+
+    /// Current joint positions of all links.
+    pub fn joint_positions(&self, scene: &bpx::Scene) -> Option<JointVector> {
+        self.with_cache(scene, |_, cache, layout| {
+            Some(JointVector::from_cache(layout, cache_slice(cache, |c| c.jointPosition, layout.total_dofs())?))
+        })
+    }
+
+    /// Current joint velocities of all links.
+    pub fn joint_velocities(&self, scene: &bpx::Scene) -> Option<JointVector> {
+        self.with_cache(scene, |_, cache, layout| {
+            Some(JointVector::from_cache(layout, cache_slice(cache, |c| c.jointVelocity, layout.total_dofs())?))
+        })
+    }
+
+    /// Joint forces required to counteract gravity in the current pose.
+    pub fn generalized_gravity_force(&self, scene: &bpx::Scene) -> Option<JointVector> {
+        self.with_cache(scene, |articulation, cache, layout| {
+            articulation.compute_generalized_gravity_force(cache);
+            Some(JointVector::from_cache(layout, cache_slice(cache, |c| c.jointForce, layout.total_dofs())?))
+        })
+    }
+
+    /// Joint forces required to counteract coriolis and centrifugal forces at current velocities.
+    pub fn coriolis_and_centrifugal_force(&self, scene: &bpx::Scene) -> Option<JointVector> {
+        self.with_cache(scene, |articulation, cache, layout| {
+            articulation.compute_coriolis_and_centrifugal_force(cache);
+            Some(JointVector::from_cache(layout, cache_slice(cache, |c| c.jointForce, layout.total_dofs())?))
+        })
+    }
+
+    /// Joint forces required to produce given joint accelerations (inverse dynamics).
+    ///
+    /// Gravity and velocity dependent terms are not included, add them separately
+    /// with `generalized_gravity_force` and `coriolis_and_centrifugal_force`.
+    /// Returns `None` if `accelerations` doesn't match dofs of the articulation.
+    pub fn joint_force(&self, scene: &bpx::Scene, accelerations: &JointVector) -> Option<JointVector> {
+        self.with_cache(scene, |articulation, cache, layout| {
+            if accelerations.len() != layout.total_dofs() { return None; }
+            cache_slice_mut(cache, |c| c.jointAcceleration, layout.total_dofs())?
+                .copy_from_slice(accelerations.as_slice());
+            articulation.compute_joint_force(cache);
+            Some(JointVector::from_cache(layout, cache_slice(cache, |c| c.jointForce, layout.total_dofs())?))
+        })
+    }
+
+    /// Joint space mass matrix, `total_dofs x total_dofs`.
+    pub fn mass_matrix(&self, scene: &bpx::Scene) -> Option<MassMatrix> {
+        self.with_cache(scene, |articulation, cache, layout| {
+            articulation.compute_generalized_mass_matrix(cache);
+            let dofs = layout.total_dofs();
+            Some(MassMatrix {
+                matrix: ArticulationMatrix::from_cache(dofs, dofs, cache_slice(cache, |c| c.massMatrix, dofs * dofs)?),
+                layout: layout.clone(),
+            })
+        })
+    }
+
+    /// Dense jacobian mapping joint velocities to link spatial velocities.
+    pub fn dense_jacobian(&self, scene: &bpx::Scene) -> Option<DenseJacobian> {
+        self.with_cache(scene, |articulation, cache, layout| {
+            let (cols, rows) = articulation.compute_dense_jacobian(cache);
+            let (cols, rows) = (cols as usize, rows as usize);
+            Some(DenseJacobian {
+                matrix: ArticulationMatrix::from_cache(rows, cols, cache_slice(cache, |c| c.denseJacobian, rows * cols)?),
+                floating_base: cols > layout.total_dofs(),
+                layout: layout.clone(),
+            })
+        })
+    }
+
+    /// Joint positions, dense jacobian and world poses of all links (indexed by link index),
+    /// computed from a single cache update, for solvers that need all of them each tick.
+    pub fn kinematic_state(&self, scene: &bpx::Scene) -> Option<(JointVector, DenseJacobian, Vec<Transform>)> {
+        self.with_cache(scene, |articulation, cache, layout| {
+            let positions = JointVector::from_cache(layout, cache_slice(cache, |c| c.jointPosition, layout.total_dofs())?);
+
+            let (cols, rows) = articulation.compute_dense_jacobian(cache);
+            let (cols, rows) = (cols as usize, rows as usize);
+            let jacobian = DenseJacobian {
+                matrix: ArticulationMatrix::from_cache(rows, cols, cache_slice(cache, |c| c.denseJacobian, rows * cols)?),
+                floating_base: cols > layout.total_dofs(),
+                layout: layout.clone(),
+            };
+
+            let mut poses = vec![Transform::IDENTITY; layout.nb_links()];
+            for link in articulation.get_links() {
+                if let Some(pose) = poses.get_mut(link.get_link_index() as usize) {
+                    *pose = link.get_global_pose().to_bevy();
+                }
+            }
+
+            Some((positions, jacobian, poses))
+        })
+    }
+
+    /// Current world pose of a link.
+    pub fn link_global_pose(&self, scene: &bpx::Scene, link_index: usize) -> Option<Transform> {
+        let articulation = self.handle.get(scene);
+        let link = articulation.get_links().into_iter()
+            .find(|link| link.get_link_index() as usize == link_index)?;
+        Some(link.get_global_pose().to_bevy())
+    }
+
+    /// Set drive target of the inbound joint of a link, waking up the articulation.
+    pub fn set_drive_target(&mut self, scene: &mut bpx::Scene, link_index: usize, axis: ArticulationAxis, target: f32) {
+        let mut articulation = self.handle.get_mut(scene);
+        let Some(link) = articulation.get_links_mut().into_iter()
+            .find(|link| link.get_link_index() as usize == link_index) else { return; };
+
+        let joint = unsafe {
+            (PxArticulationLink_getInboundJoint(Class::<physx_sys::PxArticulationLink>::as_ptr(link))
+                as *mut ArticulationJointReducedCoordinate).as_mut()
+        };
+
+        if let Some(joint) = joint {
+            joint.set_drive_target(target, axis);
+            articulation.wake_up();
+        }
+    }
+
+    // cache is refreshed from the current state of the articulation before each use
+    fn with_cache<R>(
+        &self,
+        scene: &bpx::Scene,
+        f: impl FnOnce(&PxArticulationReducedCoordinate, &mut ArticulationCache, &DofLayout) -> Option<R>,
+    ) -> Option<R> {
+        let articulation = self.handle.get(scene);
+        let articulation: &PxArticulationReducedCoordinate = &articulation;
+        let mut shared = self.cache.lock().unwrap();
+
+        if shared.is_none() {
+            let cache = articulation.create_cache()?;
+            let layout = DofLayout::new(&cache, articulation.get_nb_links());
+            *shared = Some(SharedCache { cache, layout });
+        }
+
+        let SharedCache { cache, layout } = shared.as_mut()?;
+        articulation.copy_internal_state_to_cache(
+            cache,
+            ArticulationCacheFlag::Position | ArticulationCacheFlag::Velocity | ArticulationCacheFlag::Root,
+        );
+        f(articulation, cache, layout)
+    }
+}
+
+impl Drop for ArticulationHandle {
+    fn drop(&mut self) {
+        if let Some(SharedCache { cache, .. }) = self.cache.get_mut().unwrap().take() {
+            // SAFETY: handle is being dropped, nothing else can access the articulation
+            unsafe { self.handle.get_unsafe() }.release_cache(cache);
+        }
+    }
+}
+
+fn cache_slice(cache: &ArticulationCache, field: impl Fn(&PxArticulationCache) -> *mut f32, len: usize) -> Option<Vec<f32>> {
+    unsafe {
+        let ptr = field(&*cache.as_ptr());
+        if ptr.is_null() { return None; }
+        Some(std::slice::from_raw_parts(ptr, len).to_vec())
+    }
+}
+
+fn cache_slice_mut(cache: &mut ArticulationCache, field: impl Fn(&PxArticulationCache) -> *mut f32, len: usize) -> Option<&mut [f32]> {
+    unsafe {
+        let ptr = field(&*cache.as_mut_ptr());
+        if ptr.is_null() { return None; }
+        Some(std::slice::from_raw_parts_mut(ptr, len))
+    }
+}
+
+/// Location of each link's dofs inside joint space vectors.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DofLayout {
+    offsets: Vec<usize>,
+    dofs: Vec<usize>,
+}
+
+impl DofLayout {
+    fn new(cache: &ArticulationCache, nb_links: usize) -> Self {
+        Self {
+            offsets: (0..nb_links).map(|link| cache.get_offset(link) as usize).collect(),
+            dofs: (0..nb_links).map(|link| cache.get_dofs(link) as usize).collect(),
+        }
+    }
+
+    pub fn nb_links(&self) -> usize {
+        self.offsets.len()
+    }
+
+    pub fn total_dofs(&self) -> usize {
+        self.dofs.iter().sum()
+    }
+
+    pub fn link_dofs(&self, link_index: usize) -> usize {
+        self.dofs[link_index]
+    }
+
+    pub fn link_range(&self, link_index: usize) -> Range<usize> {
+        self.offsets[link_index]..self.offsets[link_index] + self.dofs[link_index]
+    }
+
+    /// index of a (link, dof) pair in joint space vectors
+    ///
+    /// # Panics
+    /// If the link doesn't exist or has fewer dofs, see [`DofLayout::try_index`].
+    pub fn index(&self, link_index: usize, dof: usize) -> usize {
+        assert!(dof < self.dofs[link_index], "link {link_index} has only {} dofs", self.dofs[link_index]);
+        self.offsets[link_index] + dof
+    }
+
+    /// same as [`DofLayout::index`], `None` if the link doesn't exist or has fewer dofs
+    pub fn try_index(&self, link_index: usize, dof: usize) -> Option<usize> {
+        (dof < *self.dofs.get(link_index)?).then(|| self.offsets[link_index] + dof)
+    }
+}
+
+/// Joint space vector (positions, velocities, forces), indexed by `(link, dof)`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct JointVector {
+    layout: DofLayout,
+    values: Vec<f32>,
+}
+
+impl JointVector {
+    /// Returns `None` if number of values doesn't match the layout.
+    pub fn new(layout: &DofLayout, values: Vec<f32>) -> Option<Self> {
+        (values.len() == layout.total_dofs()).then(|| Self::from_cache(layout, values))
+    }
+
+    pub fn zeros(layout: &DofLayout) -> Self {
+        Self::from_cache(layout, vec![0.; layout.total_dofs()])
+    }
+
+    // size is known to match, as both come from the same cache
+    fn from_cache(layout: &DofLayout, values: Vec<f32>) -> Self {
+        Self { layout: layout.clone(), values }
+    }
+
+    pub fn layout(&self) -> &DofLayout {
+        &self.layout
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn link(&self, link_index: usize) -> &[f32] {
+        &self.values[self.layout.link_range(link_index)]
+    }
+
+    pub fn link_mut(&mut self, link_index: usize) -> &mut [f32] {
+        let range = self.layout.link_range(link_index);
+        &mut self.values[range]
+    }
+
+    /// value of a (link, dof) pair, `None` if the link doesn't exist or has fewer dofs
+    pub fn get(&self, link_index: usize, dof: usize) -> Option<f32> {
+        Some(self.values[self.layout.try_index(link_index, dof)?])
+    }
+
+    pub fn as_slice(&self) -> &[f32] {
+        &self.values
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [f32] {
+        &mut self.values
+    }
+}
+
+/// # Panics
+/// If the link doesn't exist or has fewer dofs, see [`JointVector::get`].
+impl Index<(usize, usize)> for JointVector {
+    type Output = f32;
+
+    fn index(&self, (link_index, dof): (usize, usize)) -> &Self::Output {
+        &self.values[self.layout.index(link_index, dof)]
+    }
+}
+
+impl IndexMut<(usize, usize)> for JointVector {
+    fn index_mut(&mut self, (link_index, dof): (usize, usize)) -> &mut Self::Output {
+        let index = self.layout.index(link_index, dof);
+        &mut self.values[index]
+    }
+}
+
+/// Row-major dense matrix.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ArticulationMatrix {
+    nb_rows: usize,
+    nb_cols: usize,
+    data: Vec<f32>,
+}
+
+impl ArticulationMatrix {
+    /// Returns `None` if size of `data` is not `nb_rows * nb_cols`.
+    pub fn new(nb_rows: usize, nb_cols: usize, data: Vec<f32>) -> Option<Self> {
+        (data.len() == nb_rows * nb_cols).then(|| Self::from_cache(nb_rows, nb_cols, data))
+    }
+
+    fn from_cache(nb_rows: usize, nb_cols: usize, data: Vec<f32>) -> Self {
+        Self { nb_rows, nb_cols, data }
+    }
+
+    pub fn nb_rows(&self) -> usize {
+        self.nb_rows
+    }
+
+    pub fn nb_cols(&self) -> usize {
+        self.nb_cols
+    }
+
+    pub fn row(&self, row: usize) -> &[f32] {
+        &self.data[row * self.nb_cols..(row + 1) * self.nb_cols]
+    }
+
+    /// `None` if `row` or `col` is out of bounds
+    pub fn get(&self, row: usize, col: usize) -> Option<f32> {
+        (row < self.nb_rows && col < self.nb_cols).then(|| self.data[row * self.nb_cols + col])
+    }
+
+    pub fn as_slice(&self) -> &[f32] {
+        &self.data
+    }
+}
+
+/// # Panics
+/// If `row` or `col` is out of bounds, see [`ArticulationMatrix::get`].
+impl Index<(usize, usize)> for ArticulationMatrix {
+    type Output = f32;
+
+    fn index(&self, (row, col): (usize, usize)) -> &Self::Output {
+        assert!(row < self.nb_rows && col < self.nb_cols, "matrix index out of bounds");
+        &self.data[row * self.nb_cols + col]
+    }
+}
+
+/// Joint space mass matrix, rows and columns are indexed by `(link, dof)`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MassMatrix {
+    pub matrix: ArticulationMatrix,
+    pub layout: DofLayout,
+}
+
+impl MassMatrix {
+    /// # Panics
+    /// If any of the links doesn't exist or has fewer dofs, see [`MassMatrix::try_get`].
+    pub fn get(&self, row: (usize, usize), col: (usize, usize)) -> f32 {
+        self.matrix[(self.layout.index(row.0, row.1), self.layout.index(col.0, col.1))]
+    }
+
+    pub fn try_get(&self, row: (usize, usize), col: (usize, usize)) -> Option<f32> {
+        self.matrix.get(self.layout.try_index(row.0, row.1)?, self.layout.try_index(col.0, col.1)?)
+    }
+}
+
+/// Dense jacobian of the articulation.
+///
+/// Each link has 6 rows (linear velocity, then angular velocity). Columns are joint dofs,
+/// preceded by 6 root dofs if the articulation has floating base.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DenseJacobian {
+    pub matrix: ArticulationMatrix,
+    pub layout: DofLayout,
+    pub floating_base: bool,
+}
+
+impl DenseJacobian {
+    /// first row of a link, root link has no rows with fixed base
+    pub fn link_row(&self, link_index: usize) -> Option<usize> {
+        if self.floating_base {
+            Some(link_index * 6)
+        } else {
+            link_index.checked_sub(1).map(|idx| idx * 6)
+        }
+    }
+
+    /// # Panics
+    /// If the link doesn't exist or has fewer dofs.
+    pub fn dof_col(&self, link_index: usize, dof: usize) -> usize {
+        self.layout.index(link_index, dof) + if self.floating_base { 6 } else { 0 }
+    }
+
+    /// derivative of link velocity component (0..6) with respect to joint dof
+    ///
+    /// # Panics
+    /// If the link, component or joint is out of range, see [`DenseJacobian::try_get`].
+    pub fn get(&self, link_index: usize, component: usize, joint: (usize, usize)) -> f32 {
+        let Some(row) = self.link_row(link_index) else { return 0.; };
+        self.matrix[(row + component, self.dof_col(joint.0, joint.1))]
+    }
+
+    pub fn try_get(&self, link_index: usize, component: usize, joint: (usize, usize)) -> Option<f32> {
+        if component >= 6 { return None; }
+        let Some(row) = self.link_row(link_index) else { return Some(0.); };
+        let col = self.layout.try_index(joint.0, joint.1)? + if self.floating_base { 6 } else { 0 };
+        self.matrix.get(row + component, col)
+    }
+}
+
+/// Moves an articulation link towards the target pose by driving joints of its parent chain.
+///
+/// Each tick one damped least squares step is computed from the articulation jacobian,
+/// clamped by joint limits and written as drive targets. Only axes with a drive
+/// configured in `ArticulationJoint` are affected.
+#[derive(Component, Debug, Clone)]
+pub struct InverseKinematics {
+    /// target pose of the link in world space
+    pub target: Transform,
+    /// if false, only position of the link is tracked
+    pub orientation: bool,
+    /// damping factor of damped least squares, larger is more stable near singularities
+    pub damping: f32,
+    /// fraction of the computed step applied each tick
+    pub gain: f32,
+    /// solver stops updating targets when error is below this value
+    pub tolerance: f32,
+}
+
+impl Default for InverseKinematics {
+    fn default() -> Self {
+        Self {
+            target: Transform::IDENTITY,
+            orientation: true,
+            damping: 0.1,
+            gain: 1.,
+            tolerance: 1e-4,
+        }
+    }
+}
+
+impl InverseKinematics {
+    pub fn new(target: Transform) -> Self {
+        Self { target, ..default() }
+    }
+
+    pub fn position(target: Vec3) -> Self {
+        Self { target: Transform::from_translation(target), orientation: false, ..default() }
+    }
+
+    /// Error between current and target pose, linear part followed by angular part (if tracked).
+    pub fn error(&self, current: &Transform) -> Vec<f32> {
+        let mut error = (self.target.translation - current.translation).to_array().to_vec();
+
+        if self.orientation {
+            let mut rotation = self.target.rotation * current.rotation.inverse();
+            // take the shortest path
+            if rotation.w < 0. { rotation = -rotation; }
+            let (axis, angle) = rotation.to_axis_angle();
+            error.extend((axis * angle).to_array());
+        }
+
+        error
+    }
+
+    /// Joint position change for given jacobian rows (`error.len() x nb_cols`, row-major).
+    ///
+    /// Computes `J^T (J J^T + damping^2 I)^-1 error`, returns `None` if jacobian size doesn't match.
+    pub fn solve(&self, jacobian: &[f32], nb_cols: usize, error: &[f32]) -> Option<Vec<f32>> {
+        let nb_rows = error.len();
+        if jacobian.len() != nb_rows * nb_cols { return None; }
+
+        let mut system = vec![0.; nb_rows * nb_rows];
+        for i in 0..nb_rows {
+            for j in 0..nb_rows {
+                system[i * nb_rows + j] = (0..nb_cols)
+                    .map(|k| jacobian[i * nb_cols + k] * jacobian[j * nb_cols + k])
+                    .sum::<f32>();
+            }
+            system[i * nb_rows + i] += self.damping * self.damping;
+        }
+
+        let Some(y) = solve_linear_system(system, error.to_vec(), nb_rows) else {
+            return Some(vec![0.; nb_cols]);
+        };
+
+        Some((0..nb_cols)
+            .map(|k| (0..nb_rows).map(|i| jacobian[i * nb_cols + k] * y[i]).sum::<f32>() * self.gain)
+            .collect())
+    }
+}
+
+// gaussian elimination with partial pivoting, matrix is row-major n x n
+fn solve_linear_system(mut matrix: Vec<f32>, mut rhs: Vec<f32>, n: usize) -> Option<Vec<f32>> {
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|a, b| matrix[a * n + col].abs().total_cmp(&matrix[b * n + col].abs()))?;
+        if matrix[pivot * n + col].abs() < f32::EPSILON { return None; }
+
+        if pivot != col {
+            for k in 0..n { matrix.swap(pivot * n + k, col * n + k); }
+            rhs.swap(pivot, col);
+        }
+
+        for row in col + 1..n {
+            let factor = matrix[row * n + col] / matrix[col * n + col];
+            for k in col..n { matrix[row * n + k] -= factor * matrix[col * n + k]; }
+            rhs[row] -= factor * rhs[col];
+        }
+    }
+
+    let mut result = vec![0.; n];
+    for row in (0..n).rev() {
+        let sum = (row + 1..n).map(|k| matrix[row * n + k] * result[k]).sum::<f32>();
+        result[row] = (rhs[row] - sum) / matrix[row * n + row];
+    }
+
+    Some(result)
 }
@@ -0,0 +1,657 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use bevy::asset::{AssetLoader, AssetPath, HandleId, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::utils::BoxedFuture;
+use bevy::tasks::{AsyncComputeTaskPool, Task};
+use futures_lite::future;
+use physx::prelude::*;
+use physx::traits::Class;
+use physx_sys::{
+    get_default_allocator,
+    PxAllocatorCallback,
+    PxConvexMeshCookingResult,
+    PxCooking_cookConvexMesh,
+    PxCooking_cookHeightField,
+    PxCooking_cookTriangleMesh,
+    PxDefaultMemoryInputData,
+    PxDefaultMemoryInputData_delete,
+    PxDefaultMemoryInputData_new_alloc,
+    PxDefaultMemoryOutputStream,
+    PxDefaultMemoryOutputStream_delete,
+    PxDefaultMemoryOutputStream_getData,
+    PxDefaultMemoryOutputStream_getSize,
+    PxDefaultMemoryOutputStream_new_alloc,
+    PxInputStream,
+    PxTriangleMeshCookingResult,
+};
+
+use crate::assets::{
+    convex_mesh_desc, convex_mesh_failure, heightfield_desc, heightfield_samples, triangle_mesh_desc,
+    triangle_mesh_failure, validate_convex_mesh, validate_triangle_mesh,
+    ConvexMeshCookingError, TriangleMeshCookingError,
+};
+use crate::prelude as bpx;
+use crate::prelude::*;
+use crate::resources::SharedShapes;
+
+/// Output of PhysX cooking, which can be turned into geometry without cooking again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CookedGeometry {
+    ConvexMesh(Vec<u8>),
+    TriangleMesh(Vec<u8>),
+    HeightField(Vec<u8>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CookingError {
+    ConvexMesh(ConvexMeshCookingError),
+    TriangleMesh(TriangleMeshCookingError),
+    HeightField,
+    /// cooked data was rejected by PhysX when creating the mesh
+    InvalidData,
+    /// cooked geometry file has wrong magic, version or is truncated
+    InvalidFile,
+}
+
+impl std::fmt::Display for CookingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ConvexMesh(err) => write!(f, "convex mesh cooking failed: {err:?}"),
+            Self::TriangleMesh(err) => write!(f, "triangle mesh cooking failed: {err:?}"),
+            Self::HeightField => write!(f, "heightfield cooking failed"),
+            Self::InvalidData => write!(f, "cooked data was rejected by PhysX"),
+            Self::InvalidFile => write!(f, "invalid cooked geometry file"),
+        }
+    }
+}
+
+impl std::error::Error for CookingError {}
+
+struct MemoryOutputStream(*mut PxDefaultMemoryOutputStream);
+
+impl MemoryOutputStream {
+    fn new() -> Self {
+        Self(unsafe { PxDefaultMemoryOutputStream_new_alloc(get_default_allocator() as *mut PxAllocatorCallback) })
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut physx_sys::PxOutputStream {
+        self.0 as *mut _
+    }
+
+    fn to_vec(&self) -> Vec<u8> {
+        unsafe {
+            let size = PxDefaultMemoryOutputStream_getSize(self.0) as usize;
+            if size == 0 { return vec![]; }
+            std::slice::from_raw_parts(PxDefaultMemoryOutputStream_getData(self.0), size).to_vec()
+        }
+    }
+}
+
+impl Drop for MemoryOutputStream {
+    fn drop(&mut self) {
+        unsafe { PxDefaultMemoryOutputStream_delete(self.0); }
+    }
+}
+
+struct MemoryInputData<'a> {
+    obj: *mut PxDefaultMemoryInputData,
+    _data: std::marker::PhantomData<&'a mut [u8]>,
+}
+
+impl<'a> MemoryInputData<'a> {
+    fn new(data: &'a mut [u8]) -> Self {
+        Self {
+            obj: unsafe { PxDefaultMemoryInputData_new_alloc(data.as_mut_ptr(), data.len() as u32) },
+            _data: std::marker::PhantomData,
+        }
+    }
+
+    fn as_mut(&mut self) -> &mut PxInputStream {
+        unsafe { &mut *(self.obj as *mut PxInputStream) }
+    }
+}
+
+impl Drop for MemoryInputData<'_> {
+    fn drop(&mut self) {
+        unsafe { PxDefaultMemoryInputData_delete(self.obj); }
+    }
+}
+
+impl CookedGeometry {
+    pub fn cook_convex_mesh(cooking: &bpx::Cooking, verts: &[Vec3]) -> Result<Self, CookingError> {
+        if cooking.params().mesh_validation {
+            let report = validate_convex_mesh(cooking, verts);
+            if !report.is_valid() { return Err(CookingError::ConvexMesh(ConvexMeshCookingError::Invalid(report))); }
+        }
+
+        let px_verts = verts.iter().map(|v| v.to_physx()).collect::<Vec<_>>();
+        let mesh_desc = convex_mesh_desc(&px_verts, cooking.params());
+        let mut stream = MemoryOutputStream::new();
+        let mut result = PxConvexMeshCookingResult::eFAILURE;
+
+        let success = unsafe {
+            PxCooking_cookConvexMesh(cooking.as_ptr(), mesh_desc.as_ptr(), stream.as_mut_ptr(), &mut result)
+        };
+
+        if success { return Ok(Self::ConvexMesh(stream.to_vec())); }
+
+        Err(CookingError::ConvexMesh(match result {
+            PxConvexMeshCookingResult::eZERO_AREA_TEST_FAILED => ConvexMeshCookingError::ZeroAreaTestFailed,
+            PxConvexMeshCookingResult::ePOLYGONS_LIMIT_REACHED => ConvexMeshCookingError::PolygonsLimitReached,
+            _ => convex_mesh_failure(cooking, verts),
+        }))
+    }
+
+    pub fn cook_trimesh(cooking: &bpx::Cooking, verts: &[Vec3], indices: &[[u32; 3]]) -> Result<Self, CookingError> {
+        if cooking.params().mesh_validation {
+            let report = validate_triangle_mesh(cooking, verts, indices);
+            if !report.is_valid() { return Err(CookingError::TriangleMesh(TriangleMeshCookingError::Invalid(report))); }
+        }
+
+        let px_verts = verts.iter().map(|v| v.to_physx()).collect::<Vec<_>>();
+        let mesh_desc = triangle_mesh_desc(&px_verts, indices);
+        let mut stream = MemoryOutputStream::new();
+        let mut result = PxTriangleMeshCookingResult::eFAILURE;
+
+        let success = unsafe {
+            PxCooking_cookTriangleMesh(cooking.as_ptr(), mesh_desc.as_ptr(), stream.as_mut_ptr(), &mut result)
+        };
+
+        if success { return Ok(Self::TriangleMesh(stream.to_vec())); }
+
+        Err(CookingError::TriangleMesh(match result {
+            PxTriangleMeshCookingResult::eLARGE_TRIANGLE => TriangleMeshCookingError::LargeTriangle,
+            _ => triangle_mesh_failure(cooking, verts, indices),
+        }))
+    }
+
+    pub fn cook_heightfield(cooking: &bpx::Cooking, heights: &[i16], num_rows: usize, num_cols: usize) -> Result<Self, CookingError> {
+        if heights.len() != num_rows * num_cols { return Err(CookingError::HeightField); }
+
+        let samples = heightfield_samples(heights);
+        let hfield_desc = heightfield_desc(&samples, num_rows, num_cols);
+        let mut stream = MemoryOutputStream::new();
+
+        let success = unsafe {
+            PxCooking_cookHeightField(cooking.as_ptr(), hfield_desc.as_ptr(), stream.as_mut_ptr())
+        };
+
+        if success { Ok(Self::HeightField(stream.to_vec())) } else { Err(CookingError::HeightField) }
+    }
+
+    /// Create geometry from cooked data, this is cheap compared to cooking itself.
+    pub fn create_geometry(&self, physics: &mut bpx::Physics) -> Result<bpx::Geometry, CookingError> {
+        let physics = physics.physics_mut();
+        // input stream requires mutable buffer, even though it only reads from it
+        let mut data = self.data().to_vec();
+        let mut stream = MemoryInputData::new(&mut data);
+
+        let geometry = match self {
+            CookedGeometry::ConvexMesh(_) => physics.create_convex_mesh(stream.as_mut()).map(bpx::Geometry::from),
+            CookedGeometry::TriangleMesh(_) => physics.create_triangle_mesh(stream.as_mut()).map(bpx::Geometry::from),
+            CookedGeometry::HeightField(_) => physics.create_height_field(stream.as_mut()).map(bpx::Geometry::from),
+        };
+
+        geometry.ok_or(CookingError::InvalidData)
+    }
+
+    pub fn data(&self) -> &[u8] {
+        let (CookedGeometry::ConvexMesh(data) | CookedGeometry::TriangleMesh(data) | CookedGeometry::HeightField(data)) = self;
+        data
+    }
+
+    fn kind(&self) -> u8 {
+        match self {
+            CookedGeometry::ConvexMesh(_) => 0,
+            CookedGeometry::TriangleMesh(_) => 1,
+            CookedGeometry::HeightField(_) => 2,
+        }
+    }
+}
+
+const COOKED_FILE_MAGIC: &[u8; 4] = b"BPXC";
+const COOKED_FILE_VERSION: u8 = 1;
+const COOKED_FILE_HEADER_SIZE: usize = 8 + 7 * 4;
+const COOKED_FLAG_TIGHT_BOUNDS: u8 = 1 << 0;
+const COOKED_FLAG_DOUBLE_SIDED: u8 = 1 << 1;
+
+/// Cooked geometry together with the mesh geometry settings, as stored in `.pxcooked` files.
+///
+/// Layout is `"BPXC"`, version, geometry kind, flags, one reserved byte,
+/// scale (3 x f32 LE), rotation (4 x f32 LE), followed by the PhysX cooked stream.
+#[derive(TypeUuid, Debug, Clone, PartialEq)]
+#[uuid = "008d023d-f541-4c14-90ff-5644e3f37b62"]
+pub struct CookedGeometryFile {
+    pub data: CookedGeometry,
+    pub scale: Vec3,
+    /// ignored for heightfields
+    pub rotation: Quat,
+    /// only used for convex meshes
+    pub tight_bounds: bool,
+    /// only used for triangle meshes and heightfields
+    pub double_sided: bool,
+    /// geometry created from the file once loaded, not stored in the file itself
+    pub geometry: Handle<bpx::Geometry>,
+}
+
+impl From<CookedGeometry> for CookedGeometryFile {
+    fn from(data: CookedGeometry) -> Self {
+        Self {
+            data,
+            scale: Vec3::ONE,
+            rotation: Quat::IDENTITY,
+            tight_bounds: false,
+            double_sided: false,
+            geometry: default(),
+        }
+    }
+}
+
+impl CookedGeometryFile {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let data = self.data.data();
+        let mut flags = 0;
+        if self.tight_bounds { flags |= COOKED_FLAG_TIGHT_BOUNDS; }
+        if self.double_sided { flags |= COOKED_FLAG_DOUBLE_SIDED; }
+
+        let mut bytes = Vec::with_capacity(COOKED_FILE_HEADER_SIZE + data.len());
+        bytes.extend_from_slice(COOKED_FILE_MAGIC);
+        bytes.extend_from_slice(&[ COOKED_FILE_VERSION, self.data.kind(), flags, 0 ]);
+        for value in self.scale.to_array().into_iter().chain(self.rotation.to_array()) {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CookingError> {
+        if bytes.len() < COOKED_FILE_HEADER_SIZE
+            || &bytes[0..4] != COOKED_FILE_MAGIC
+            || bytes[4] != COOKED_FILE_VERSION {
+            return Err(CookingError::InvalidFile);
+        }
+
+        let (kind, flags) = (bytes[5], bytes[6]);
+        let mut floats = [0f32; 7];
+        for (idx, value) in floats.iter_mut().enumerate() {
+            let offset = 8 + idx * 4;
+            *value = f32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+        }
+
+        let payload = bytes[COOKED_FILE_HEADER_SIZE..].to_vec();
+        let data = match kind {
+            0 => CookedGeometry::ConvexMesh(payload),
+            1 => CookedGeometry::TriangleMesh(payload),
+            2 => CookedGeometry::HeightField(payload),
+            _ => return Err(CookingError::InvalidFile),
+        };
+
+        Ok(Self {
+            data,
+            scale: Vec3::from_slice(&floats[0..3]),
+            rotation: Quat::from_slice(&floats[3..7]),
+            tight_bounds: flags & COOKED_FLAG_TIGHT_BOUNDS != 0,
+            double_sided: flags & COOKED_FLAG_DOUBLE_SIDED != 0,
+            geometry: default(),
+        })
+    }
+
+    pub fn create_geometry(&self, physics: &mut bpx::Physics) -> Result<bpx::Geometry, CookingError> {
+        let mut geometry = self.data.create_geometry(physics)?.with_scale(self.scale);
+
+        match self.data {
+            CookedGeometry::ConvexMesh(_) => {
+                geometry = geometry.with_rotation(self.rotation).with_tight_bounds(self.tight_bounds);
+            }
+            CookedGeometry::TriangleMesh(_) => {
+                geometry = geometry.with_rotation(self.rotation).with_double_sided(self.double_sided);
+            }
+            CookedGeometry::HeightField(_) => {
+                geometry = geometry.with_double_sided(self.double_sided);
+            }
+        }
+
+        Ok(geometry)
+    }
+}
+
+/// Loads `.pxcooked` files (see [`CookedGeometryFile`]).
+///
+/// PhysX meshes are created from loaded files on the main thread, as `Geometry` sub-asset
+/// of the file, so they can be loaded with `asset_server.load("mesh.pxcooked#Geometry")`.
+#[derive(Default)]
+pub struct CookedGeometryLoader;
+
+impl AssetLoader for CookedGeometryLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let mut file = CookedGeometryFile::from_bytes(bytes)?;
+            file.geometry = load_context.get_handle(AssetPath::new_ref(load_context.path(), Some("Geometry")));
+            load_context.set_default_asset(LoadedAsset::new(file));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["pxcooked"]
+    }
+}
+
+/// Geometries of loaded `.pxcooked` files PhysX refused to create, shapes using them are reported.
+#[derive(Resource, Deref, DerefMut, Default)]
+pub(crate) struct FailedCookedFiles(HashMap<HandleId, CookingError>);
+
+/// Creates geometry of every loaded or modified `.pxcooked` file.
+///
+/// Shapes using geometry of a modified file are re-created with the new geometry.
+pub fn create_cooked_geometries(
+    mut events: EventReader<AssetEvent<CookedGeometryFile>>,
+    files: Res<Assets<CookedGeometryFile>>,
+    mut physics: ResMut<bpx::Physics>,
+    mut geometries: ResMut<Assets<bpx::Geometry>>,
+    mut failed: ResMut<FailedCookedFiles>,
+    mut shared_shapes: ResMut<SharedShapes>,
+    mut shapes: Query<&mut bpx::Shape>,
+) {
+    for event in events.iter() {
+        let (AssetEvent::Created { handle } | AssetEvent::Modified { handle }) = event else { continue; };
+        let Some(file) = files.get(handle) else { continue; };
+
+        match file.create_geometry(&mut physics) {
+            Ok(geometry) => {
+                failed.remove(&file.geometry.id());
+                geometries.set_untracked(&file.geometry, geometry);
+            }
+            Err(err) => {
+                bevy::log::warn!("failed to create geometry from cooked file {:?}: {err}", handle.id());
+                failed.insert(file.geometry.id(), err);
+                geometries.remove(&file.geometry);
+            }
+        }
+
+        if matches!(event, AssetEvent::Modified { .. }) {
+            // live shapes keep the old mesh, so they are re-created by `sync_actor_shapes`
+            shared_shapes.forget_using(file.geometry.id());
+            for mut shape in shapes.iter_mut() {
+                if shape.geometry == file.geometry { shape.set_changed(); }
+            }
+        }
+    }
+}
+
+/// Cooks meshes once and keeps the results in a directory as `.pxcooked` files,
+/// named after a hash of the source data.
+///
+/// Files written here can be shipped with the game and loaded directly through `AssetServer`.
+#[derive(Resource, Debug, Clone)]
+pub struct CookingCache {
+    pub path: PathBuf,
+}
+
+impl CookingCache {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn convex_mesh(
+        &self,
+        physics: &mut bpx::Physics,
+        cooking: &bpx::Cooking,
+        verts: &[Vec3],
+    ) -> Result<bpx::Geometry, CookingError> {
+        let hash = SourceHash::new(0)
+            .params(cooking.params())
+            .floats(verts.iter().flat_map(|v| v.to_array()))
+            .finish();
+        self.get_or_cook(physics, hash, || CookedGeometry::cook_convex_mesh(cooking, verts))
+    }
+
+    pub fn trimesh(
+        &self,
+        physics: &mut bpx::Physics,
+        cooking: &bpx::Cooking,
+        verts: &[Vec3],
+        indices: &[[u32; 3]],
+    ) -> Result<bpx::Geometry, CookingError> {
+        let hash = SourceHash::new(1)
+            .params(cooking.params())
+            .floats(verts.iter().flat_map(|v| v.to_array()))
+            .ints(indices.iter().flatten().copied())
+            .finish();
+        self.get_or_cook(physics, hash, || CookedGeometry::cook_trimesh(cooking, verts, indices))
+    }
+
+    pub fn heightfield(
+        &self,
+        physics: &mut bpx::Physics,
+        cooking: &bpx::Cooking,
+        heights: &[i16],
+        num_rows: usize,
+        num_cols: usize,
+    ) -> Result<bpx::Geometry, CookingError> {
+        let hash = SourceHash::new(2)
+            .params(cooking.params())
+            .ints([ num_rows as u32, num_cols as u32 ])
+            .ints(heights.iter().map(|h| *h as u16 as u32))
+            .finish();
+        self.get_or_cook(physics, hash, || CookedGeometry::cook_heightfield(cooking, heights, num_rows, num_cols))
+    }
+
+    pub fn file_path(&self, hash: u64) -> PathBuf {
+        self.path.join(format!("{hash:016x}.pxcooked"))
+    }
+
+    fn get_or_cook(
+        &self,
+        physics: &mut bpx::Physics,
+        hash: u64,
+        cook: impl FnOnce() -> Result<CookedGeometry, CookingError>,
+    ) -> Result<bpx::Geometry, CookingError> {
+        let path = self.file_path(hash);
+
+        if let Ok(bytes) = std::fs::read(&path) {
+            match CookedGeometryFile::from_bytes(&bytes).and_then(|file| file.create_geometry(physics)) {
+                Ok(geometry) => return Ok(geometry),
+                Err(err) => bevy::log::warn!("ignoring cached geometry {path:?}: {err}"),
+            }
+        }
+
+        let file = CookedGeometryFile::from(cook()?);
+        let write_result = std::fs::create_dir_all(&self.path)
+            .and_then(|_| std::fs::write(&path, file.to_bytes()));
+
+        if let Err(err) = write_result {
+            bevy::log::warn!("unable to write cooked geometry {path:?}: {err}");
+        }
+
+        file.create_geometry(physics)
+    }
+}
+
+// FNV-1a, std hashers are not guaranteed to be stable between releases
+struct SourceHash(u64);
+
+impl SourceHash {
+    fn new(kind: u8) -> Self {
+        Self(0xcbf29ce484222325).bytes(&[ kind ])
+    }
+
+    fn bytes(mut self, bytes: &[u8]) -> Self {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+        self
+    }
+
+    fn floats(self, values: impl IntoIterator<Item = f32>) -> Self {
+        self.ints(values.into_iter().map(f32::to_bits))
+    }
+
+    fn ints(mut self, values: impl IntoIterator<Item = u32>) -> Self {
+        for value in values {
+            self = self.bytes(&value.to_le_bytes());
+        }
+        self
+    }
+
+    // same source cooked with different parameters has to end up in a different file
+    fn params(self, params: &CookingDescriptor) -> Self {
+        self.ints([
+            params.midphase as u32,
+            params.mesh_weld_tolerance.is_some() as u32,
+            params.mesh_cleaning as u32,
+            params.mesh_validation as u32,
+            params.convex_vertex_limit as u32,
+            params.build_triangle_adjacencies as u32,
+            params.suppress_triangle_mesh_remap_table as u32,
+        ])
+        .floats(params.mesh_weld_tolerance)
+    }
+
+    fn finish(self) -> u64 {
+        self.0
+    }
+}
+
+/// Cooks meshes on `AsyncComputeTaskPool`.
+///
+/// Returned handles are filled in once cooking is done, actors that use them
+/// are created after that. Handles that failed to cook stay empty, and shapes using them
+/// are reported with `PhysicsError::Cooking`.
+#[derive(Resource, Default)]
+pub struct AsyncCooking {
+    tasks: Vec<(Handle<bpx::Geometry>, Task<Result<CookedGeometry, CookingError>>)>,
+    failed: HashMap<HandleId, CookingError>,
+}
+
+impl AsyncCooking {
+    fn spawn(
+        &mut self,
+        geometries: &mut Assets<bpx::Geometry>,
+        cook: impl FnOnce() -> Result<CookedGeometry, CookingError> + Send + 'static,
+    ) -> Handle<bpx::Geometry> {
+        let handle = geometries.get_handle(HandleId::random::<bpx::Geometry>());
+        let task = AsyncComputeTaskPool::get().spawn(async move { cook() });
+        self.tasks.push((handle.clone(), task));
+        handle
+    }
+
+    pub fn convex_mesh(
+        &mut self,
+        cooking: &bpx::Cooking,
+        geometries: &mut Assets<bpx::Geometry>,
+        verts: Vec<Vec3>,
+    ) -> Handle<bpx::Geometry> {
+        let cooking = cooking.clone();
+        self.spawn(geometries, move || CookedGeometry::cook_convex_mesh(&cooking, &verts))
+    }
+
+    pub fn trimesh(
+        &mut self,
+        cooking: &bpx::Cooking,
+        geometries: &mut Assets<bpx::Geometry>,
+        verts: Vec<Vec3>,
+        indices: Vec<[u32; 3]>,
+    ) -> Handle<bpx::Geometry> {
+        let cooking = cooking.clone();
+        self.spawn(geometries, move || CookedGeometry::cook_trimesh(&cooking, &verts, &indices))
+    }
+
+    pub fn heightfield(
+        &mut self,
+        cooking: &bpx::Cooking,
+        geometries: &mut Assets<bpx::Geometry>,
+        heights: Vec<i16>,
+        num_rows: usize,
+        num_cols: usize,
+    ) -> Handle<bpx::Geometry> {
+        let cooking = cooking.clone();
+        self.spawn(geometries, move || CookedGeometry::cook_heightfield(&cooking, &heights, num_rows, num_cols))
+    }
+
+    /// Same as [`AsyncCooking::convex_mesh`], but cooked with different parameters than `cooking` was created with.
+    pub fn convex_mesh_with_params(
+        &mut self,
+        physics: &mut bpx::Physics,
+        cooking: &bpx::Cooking,
+        geometries: &mut Assets<bpx::Geometry>,
+        verts: Vec<Vec3>,
+        params: &CookingDescriptor,
+    ) -> Handle<bpx::Geometry> {
+        self.convex_mesh(&cooking.with_params(physics, params), geometries, verts)
+    }
+
+    /// Same as [`AsyncCooking::trimesh`], but cooked with different parameters than `cooking` was created with.
+    pub fn trimesh_with_params(
+        &mut self,
+        physics: &mut bpx::Physics,
+        cooking: &bpx::Cooking,
+        geometries: &mut Assets<bpx::Geometry>,
+        verts: Vec<Vec3>,
+        indices: Vec<[u32; 3]>,
+        params: &CookingDescriptor,
+    ) -> Handle<bpx::Geometry> {
+        self.trimesh(&cooking.with_params(physics, params), geometries, verts, indices)
+    }
+
+    /// Same as [`AsyncCooking::heightfield`], but cooked with different parameters than `cooking` was created with.
+    #[allow(clippy::too_many_arguments)]
+    pub fn heightfield_with_params(
+        &mut self,
+        physics: &mut bpx::Physics,
+        cooking: &bpx::Cooking,
+        geometries: &mut Assets<bpx::Geometry>,
+        heights: Vec<i16>,
+        num_rows: usize,
+        num_cols: usize,
+        params: &CookingDescriptor,
+    ) -> Handle<bpx::Geometry> {
+        self.heightfield(&cooking.with_params(physics, params), geometries, heights, num_rows, num_cols)
+    }
+
+    pub fn is_pending(&self, handle: impl Into<HandleId>) -> bool {
+        let id = handle.into();
+        self.tasks.iter().any(|(handle, _)| handle.id() == id)
+    }
+
+    /// Error of a handle that failed to cook.
+    pub fn error(&self, handle: impl Into<HandleId>) -> Option<CookingError> {
+        self.failed.get(&handle.into()).copied()
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.tasks.len()
+    }
+}
+
+pub fn finish_async_cooking(
+    mut async_cooking: ResMut<AsyncCooking>,
+    mut physics: ResMut<bpx::Physics>,
+    mut geometries: ResMut<Assets<bpx::Geometry>>,
+) {
+    let AsyncCooking { tasks, failed } = &mut *async_cooking;
+
+    tasks.retain_mut(|(handle, task)| {
+        let Some(result) = future::block_on(future::poll_once(task)) else { return true; };
+
+        match result.and_then(|cooked| cooked.create_geometry(&mut physics)) {
+            Ok(geometry) => {
+                geometries.set_untracked(handle.id(), geometry);
+            }
+            Err(err) => {
+                bevy::log::warn!("async cooking of {:?} failed: {err}", handle.id());
+                failed.insert(handle.id(), err);
+            }
+        }
+
+        false
+    });
+}
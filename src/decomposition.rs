@@ -0,0 +1,528 @@
+//! Approximate convex decomposition, loosely following V-HACD.
+//!
+//! Mesh is voxelized (surface voxels plus flood-filled interior), then voxel parts are
+//! split by axis aligned planes until every part is close enough to its convex hull,
+//! or until hull limit is reached. Concavity of a part is the difference between volume
+//! of its convex hull and volume of its voxels, relative to the volume of the whole mesh.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use bevy::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConvexDecompositionParams {
+    /// number of voxels along the longest axis of the mesh
+    pub resolution: u32,
+    /// maximum number of generated hulls
+    pub max_hulls: usize,
+    /// parts with concavity below this value are not split any further
+    pub concavity: f32,
+    /// number of candidate split planes tested along each axis
+    pub plane_samples: u32,
+}
+
+impl Default for ConvexDecompositionParams {
+    fn default() -> Self {
+        Self {
+            resolution: 32,
+            max_hulls: 16,
+            concavity: 0.02,
+            plane_samples: 8,
+        }
+    }
+}
+
+// hull points are snapped to a lattice this many times finer than voxel grid
+const LATTICE_SUBDIVISIONS: i64 = 8;
+
+const EMPTY: u8 = 0;
+const SURFACE: u8 = 1;
+const OUTSIDE: u8 = 2;
+
+struct VoxelGrid {
+    origin: Vec3,
+    voxel_size: f32,
+    dims: [usize; 3],
+    cells: Vec<u8>,
+    // points sampled on mesh surface, together with their voxel
+    samples: Vec<(Vec3, [i32; 3])>,
+}
+
+impl VoxelGrid {
+    fn new(verts: &[Vec3], indices: &[[u32; 3]], resolution: u32) -> Option<Self> {
+        let min = verts.iter().copied().reduce(Vec3::min)?;
+        let max = verts.iter().copied().reduce(Vec3::max)?;
+        let extent = max - min;
+        let longest = extent.max_element();
+        if longest <= 0. { return None; }
+
+        let voxel_size = longest / resolution.max(1) as f32;
+        // one voxel of padding on each side, so outside can be flood filled
+        let origin = min - Vec3::splat(voxel_size);
+        let dims = [
+            (extent.x / voxel_size).ceil() as usize + 3,
+            (extent.y / voxel_size).ceil() as usize + 3,
+            (extent.z / voxel_size).ceil() as usize + 3,
+        ];
+
+        let mut grid = Self {
+            origin,
+            voxel_size,
+            dims,
+            cells: vec![EMPTY; dims[0] * dims[1] * dims[2]],
+            samples: vec![],
+        };
+
+        grid.rasterize(verts, indices);
+        grid.fill_outside();
+        Some(grid)
+    }
+
+    fn index(&self, voxel: [i32; 3]) -> usize {
+        (voxel[2] as usize * self.dims[1] + voxel[1] as usize) * self.dims[0] + voxel[0] as usize
+    }
+
+    fn voxel_of(&self, point: Vec3) -> [i32; 3] {
+        let local = (point - self.origin) / self.voxel_size;
+        [
+            (local.x.floor() as i32).clamp(1, self.dims[0] as i32 - 2),
+            (local.y.floor() as i32).clamp(1, self.dims[1] as i32 - 2),
+            (local.z.floor() as i32).clamp(1, self.dims[2] as i32 - 2),
+        ]
+    }
+
+    fn rasterize(&mut self, verts: &[Vec3], indices: &[[u32; 3]]) {
+        let step = self.voxel_size * 0.5;
+
+        for triangle in indices {
+            let [a, b, c] = triangle.map(|i| verts.get(i as usize).copied());
+            let (Some(a), Some(b), Some(c)) = (a, b, c) else { continue; };
+
+            let longest_edge = a.distance(b).max(b.distance(c)).max(c.distance(a));
+            let n = ((longest_edge / step).ceil() as usize).max(1);
+
+            for i in 0..=n {
+                for j in 0..=(n - i) {
+                    let point = a + (b - a) * (i as f32 / n as f32) + (c - a) * (j as f32 / n as f32);
+                    let voxel = self.voxel_of(point);
+                    let index = self.index(voxel);
+                    self.cells[index] = SURFACE;
+                    self.samples.push((point, voxel));
+                }
+            }
+        }
+    }
+
+    fn fill_outside(&mut self) {
+        let mut queue = VecDeque::from([[0, 0, 0]]);
+        let start = self.index([0, 0, 0]);
+        self.cells[start] = OUTSIDE;
+
+        while let Some(voxel) = queue.pop_front() {
+            for (axis, delta) in [(0, -1), (0, 1), (1, -1), (1, 1), (2, -1), (2, 1)] {
+                let mut next = voxel;
+                next[axis] += delta;
+                if next[axis] < 0 || next[axis] >= self.dims[axis] as i32 { continue; }
+
+                let index = self.index(next);
+                if self.cells[index] == EMPTY {
+                    self.cells[index] = OUTSIDE;
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+
+    // everything that is not reachable from outside is considered solid,
+    // so meshes with holes degrade into a hollow shell
+    fn solid_voxels(&self) -> Vec<[i32; 3]> {
+        let mut result = vec![];
+        for z in 0..self.dims[2] as i32 {
+            for y in 0..self.dims[1] as i32 {
+                for x in 0..self.dims[0] as i32 {
+                    if self.cells[self.index([x, y, z])] != OUTSIDE {
+                        result.push([x, y, z]);
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    fn lattice_to_world(&self, point: [i64; 3]) -> Vec3 {
+        let scale = self.voxel_size / LATTICE_SUBDIVISIONS as f32;
+        self.origin + Vec3::new(point[0] as f32, point[1] as f32, point[2] as f32) * scale
+    }
+
+    fn world_to_lattice(&self, point: Vec3) -> [i64; 3] {
+        let local = (point - self.origin) / self.voxel_size * LATTICE_SUBDIVISIONS as f32;
+        [local.x.round() as i64, local.y.round() as i64, local.z.round() as i64]
+    }
+}
+
+struct Part {
+    voxels: Vec<[i32; 3]>,
+    concavity: f32,
+    splittable: bool,
+}
+
+impl Part {
+    fn new(voxels: Vec<[i32; 3]>, total_volume: f32) -> Self {
+        let hull_volume = ConvexHull::new(&voxel_corner_points(&voxels)).map_or(0., |hull| hull.volume());
+        let concavity = (hull_volume as f32 - voxels.len() as f32).max(0.) / total_volume;
+        Self { voxels, concavity, splittable: true }
+    }
+
+    fn split(&self, params: &ConvexDecompositionParams, total_volume: f32) -> Option<(Part, Part)> {
+        let mut best: Option<(f32, Part, Part)> = None;
+
+        for axis in 0..3 {
+            let min = self.voxels.iter().map(|v| v[axis]).min()?;
+            let max = self.voxels.iter().map(|v| v[axis]).max()?;
+            let span = max - min + 1;
+            if span < 2 { continue; }
+
+            let samples = (params.plane_samples.max(1) as i32).min(span - 1);
+            for sample in 1..=samples {
+                let cut = min + span * sample / (samples + 1);
+                if cut <= min || cut > max { continue; }
+
+                let (left, right): (Vec<_>, Vec<_>) = self.voxels.iter().copied().partition(|v| v[axis] < cut);
+                if left.is_empty() || right.is_empty() { continue; }
+
+                let left = Part::new(left, total_volume);
+                let right = Part::new(right, total_volume);
+                let cost = left.concavity + right.concavity;
+
+                if best.as_ref().map_or(true, |(best_cost, _, _)| cost < *best_cost) {
+                    best = Some((cost, left, right));
+                }
+            }
+        }
+
+        best.map(|(_, left, right)| (left, right))
+    }
+}
+
+// hull of all voxel corners is the same as hull of column extremes
+fn voxel_corner_points(voxels: &[[i32; 3]]) -> Vec<[i64; 3]> {
+    let mut columns: HashMap<(i32, i32), (i32, i32)> = HashMap::new();
+    for [x, y, z] in voxels.iter().copied() {
+        let column = columns.entry((x, y)).or_insert((z, z));
+        column.0 = column.0.min(z);
+        column.1 = column.1.max(z);
+    }
+
+    let mut points = HashSet::new();
+    for ((x, y), (zmin, zmax)) in columns {
+        for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            points.insert([(x + dx) as i64, (y + dy) as i64, zmin as i64]);
+            points.insert([(x + dx) as i64, (y + dy) as i64, zmax as i64 + 1]);
+        }
+    }
+
+    prune_points(points.into_iter().collect())
+}
+
+// hull vertex is always the first or the last point on any axis aligned line through it,
+// so everything else can be dropped before building the hull
+fn prune_points(mut points: Vec<[i64; 3]>) -> Vec<[i64; 3]> {
+    for axis in 0..3 {
+        let (a, b) = ((axis + 1) % 3, (axis + 2) % 3);
+        let mut lines: HashMap<(i64, i64), (i64, i64)> = HashMap::new();
+
+        for point in points.iter() {
+            let line = lines.entry((point[a], point[b])).or_insert((point[axis], point[axis]));
+            line.0 = line.0.min(point[axis]);
+            line.1 = line.1.max(point[axis]);
+        }
+
+        points.retain(|point| {
+            let (min, max) = lines[&(point[a], point[b])];
+            point[axis] == min || point[axis] == max
+        });
+    }
+
+    points
+}
+
+/// Incremental convex hull over integer points, orientation tests are exact.
+struct ConvexHull {
+    points: Vec<[i64; 3]>,
+    faces: Vec<[usize; 3]>,
+}
+
+fn orient(a: [i64; 3], b: [i64; 3], c: [i64; 3], p: [i64; 3]) -> i64 {
+    let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+    let v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+    let w = [p[0] - a[0], p[1] - a[1], p[2] - a[2]];
+
+    u[0] * (v[1] * w[2] - v[2] * w[1])
+        - u[1] * (v[0] * w[2] - v[2] * w[0])
+        + u[2] * (v[0] * w[1] - v[1] * w[0])
+}
+
+impl ConvexHull {
+    /// Returns `None` if all points are coplanar.
+    fn new(points: &[[i64; 3]]) -> Option<Self> {
+        let a = *points.first()?;
+        let b = *points.iter().find(|p| **p != a)?;
+        let c = *points.iter().find(|p| {
+            let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+            let v = [p[0] - a[0], p[1] - a[1], p[2] - a[2]];
+            u[1] * v[2] - u[2] * v[1] != 0 || u[2] * v[0] - u[0] * v[2] != 0 || u[0] * v[1] - u[1] * v[0] != 0
+        })?;
+        let d = *points.iter().find(|p| orient(a, b, c, **p) != 0)?;
+
+        let (b, c) = if orient(a, b, c, d) > 0 { (c, b) } else { (b, c) };
+
+        let mut hull = Self {
+            points: vec![a, b, c, d],
+            faces: vec![[0, 1, 2], [0, 3, 1], [1, 3, 2], [0, 2, 3]],
+        };
+
+        for point in points.iter().copied() {
+            hull.add_point(point);
+        }
+
+        Some(hull)
+    }
+
+    fn add_point(&mut self, point: [i64; 3]) {
+        let visible = self.faces.iter()
+            .map(|f| orient(self.points[f[0]], self.points[f[1]], self.points[f[2]], point) > 0)
+            .collect::<Vec<_>>();
+        if !visible.iter().any(|v| *v) { return; }
+
+        let mut visible_edges = HashSet::new();
+        for (face, _) in self.faces.iter().zip(visible.iter()).filter(|(_, v)| **v) {
+            visible_edges.insert((face[0], face[1]));
+            visible_edges.insert((face[1], face[2]));
+            visible_edges.insert((face[2], face[0]));
+        }
+
+        let index = self.points.len();
+        self.points.push(point);
+
+        let mut faces = self.faces.iter().zip(visible.iter())
+            .filter(|(_, v)| !**v)
+            .map(|(f, _)| *f)
+            .collect::<Vec<_>>();
+
+        // edges of visible region that are not shared between two visible faces form the horizon
+        for (u, v) in visible_edges.iter().copied() {
+            if !visible_edges.contains(&(v, u)) {
+                faces.push([u, v, index]);
+            }
+        }
+
+        self.faces = faces;
+    }
+
+    fn volume(&self) -> f64 {
+        let origin = self.points[0];
+        self.faces.iter()
+            .map(|f| orient(origin, self.points[f[0]], self.points[f[1]], self.points[f[2]]) as f64)
+            .sum::<f64>() / 6.
+    }
+
+    fn normal(&self, face: &[usize; 3]) -> [i128; 3] {
+        let [a, b, c] = face.map(|i| self.points[i].map(|c| c as i128));
+        let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+        let v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+        [u[1] * v[2] - u[2] * v[1], u[2] * v[0] - u[0] * v[2], u[0] * v[1] - u[1] * v[0]]
+    }
+
+    /// Corners of the hull, points lying inside of hull faces or edges are skipped.
+    fn vertices(&self) -> Vec<[i64; 3]> {
+        let mut normals: HashMap<usize, Vec<[i128; 3]>> = HashMap::new();
+        for face in self.faces.iter() {
+            let normal = self.normal(face);
+            for index in face.iter() {
+                normals.entry(*index).or_default().push(normal);
+            }
+        }
+
+        let cross = |u: [i128; 3], v: [i128; 3]| {
+            [u[1] * v[2] - u[2] * v[1], u[2] * v[0] - u[0] * v[2], u[0] * v[1] - u[1] * v[0]]
+        };
+
+        // vertex is a corner if normals of adjacent faces span all three dimensions
+        normals.into_iter()
+            .filter(|(_, normals)| {
+                let first = normals[0];
+                let Some(axis) = normals.iter().map(|n| cross(first, *n)).find(|c| *c != [0, 0, 0]) else { return false; };
+                normals.iter().any(|n| axis[0] * n[0] + axis[1] * n[1] + axis[2] * n[2] != 0)
+            })
+            .map(|(index, _)| self.points[index])
+            .collect()
+    }
+}
+
+/// Split mesh into convex parts, returns vertices of each convex hull.
+pub fn convex_decomposition(
+    verts: &[Vec3],
+    indices: &[[u32; 3]],
+    params: &ConvexDecompositionParams,
+) -> Vec<Vec<Vec3>> {
+    let Some(grid) = VoxelGrid::new(verts, indices, params.resolution) else {
+        return if verts.is_empty() { vec![] } else { vec![verts.to_vec()] };
+    };
+
+    let solid = grid.solid_voxels();
+    if solid.is_empty() { return vec![verts.to_vec()]; }
+
+    let total_volume = solid.len() as f32;
+    let mut parts = vec![Part::new(solid, total_volume)];
+
+    while parts.len() < params.max_hulls.max(1) {
+        let Some((index, part)) = parts.iter().enumerate()
+            .filter(|(_, part)| part.splittable)
+            .max_by(|(_, a), (_, b)| a.concavity.total_cmp(&b.concavity)) else { break; };
+
+        if part.concavity <= params.concavity { break; }
+
+        match part.split(params, total_volume) {
+            Some((left, right)) => {
+                parts.swap_remove(index);
+                parts.push(left);
+                parts.push(right);
+            }
+            None => {
+                parts[index].splittable = false;
+            }
+        }
+    }
+
+    let mut result = vec![];
+
+    for part in parts {
+        let voxels = part.voxels.iter().copied().collect::<HashSet<_>>();
+
+        // surface samples give exact shape of the mesh, interior voxel centers fill the cut faces
+        let half = LATTICE_SUBDIVISIONS / 2;
+        let mut points = grid.samples.iter()
+            .filter(|(_, voxel)| voxels.contains(voxel))
+            .map(|(point, _)| grid.world_to_lattice(*point))
+            .collect::<Vec<_>>();
+        points.extend(part.voxels.iter().filter(|v| grid.cells[grid.index(**v)] != SURFACE).map(|v| [
+            v[0] as i64 * LATTICE_SUBDIVISIONS + half,
+            v[1] as i64 * LATTICE_SUBDIVISIONS + half,
+            v[2] as i64 * LATTICE_SUBDIVISIONS + half,
+        ]));
+
+        let hull = ConvexHull::new(&prune_points(points)).or_else(|| {
+            // flat part, use voxel boxes instead
+            let corners = voxel_corner_points(&part.voxels).into_iter()
+                .map(|p| p.map(|c| c * LATTICE_SUBDIVISIONS))
+                .collect::<Vec<_>>();
+            ConvexHull::new(&corners)
+        });
+
+        if let Some(hull) = hull {
+            result.push(hull.vertices().into_iter().map(|p| grid.lattice_to_world(p)).collect());
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // closed box mesh, triangles are wound outwards
+    fn cuboid(min: Vec3, max: Vec3, verts: &mut Vec<Vec3>, indices: &mut Vec<[u32; 3]>) {
+        let base = verts.len() as u32;
+        for i in 0..8 {
+            verts.push(Vec3::new(
+                if i & 1 == 0 { min.x } else { max.x },
+                if i & 2 == 0 { min.y } else { max.y },
+                if i & 4 == 0 { min.z } else { max.z },
+            ));
+        }
+
+        for [a, b, c] in [
+            [0, 2, 1], [1, 2, 3], [4, 5, 6], [5, 7, 6],
+            [0, 1, 4], [1, 5, 4], [2, 6, 3], [3, 6, 7],
+            [0, 4, 2], [2, 4, 6], [1, 3, 5], [3, 7, 5],
+        ] {
+            indices.push([base + a, base + b, base + c]);
+        }
+    }
+
+    fn assert_solid(hulls: &[Vec<Vec3>]) {
+        for hull in hulls {
+            assert!(hull.len() >= 4, "hull has only {} vertices", hull.len());
+            assert!(hull.iter().all(|v| v.is_finite()));
+        }
+    }
+
+    #[test]
+    fn cube_is_a_single_hull() {
+        let (mut verts, mut indices) = (vec![], vec![]);
+        cuboid(Vec3::splat(-1.), Vec3::splat(1.), &mut verts, &mut indices);
+
+        let hulls = convex_decomposition(&verts, &indices, &default());
+
+        assert_eq!(hulls.len(), 1);
+        assert_eq!(hulls[0].len(), 8);
+        assert_solid(&hulls);
+    }
+
+    #[test]
+    fn l_shape_is_split() {
+        let (mut verts, mut indices) = (vec![], vec![]);
+        cuboid(Vec3::ZERO, Vec3::new(2., 1., 1.), &mut verts, &mut indices);
+        cuboid(Vec3::new(0., 1., 0.), Vec3::new(1., 2., 1.), &mut verts, &mut indices);
+
+        let hulls = convex_decomposition(&verts, &indices, &default());
+
+        assert!(hulls.len() >= 2, "got {} hulls", hulls.len());
+        assert_solid(&hulls);
+
+        // no hull should reach into the empty corner of the L
+        for hull in hulls.iter() {
+            let max = hull.iter().copied().reduce(Vec3::max).unwrap();
+            assert!(max.x < 1.2 || max.y < 1.2, "hull spans both arms up to {max:?}");
+        }
+    }
+
+    #[test]
+    fn hull_limit_is_respected() {
+        let (mut verts, mut indices) = (vec![], vec![]);
+        cuboid(Vec3::ZERO, Vec3::new(2., 1., 1.), &mut verts, &mut indices);
+        cuboid(Vec3::new(0., 1., 0.), Vec3::new(1., 2., 1.), &mut verts, &mut indices);
+
+        let params = ConvexDecompositionParams { max_hulls: 1, ..default() };
+        assert_eq!(convex_decomposition(&verts, &indices, &params).len(), 1);
+    }
+
+    #[test]
+    fn empty_mesh_has_no_hulls() {
+        assert!(convex_decomposition(&[], &[], &default()).is_empty());
+    }
+
+    #[test]
+    fn flat_mesh_gives_solid_hull() {
+        let verts = [Vec3::ZERO, Vec3::X, Vec3::new(1., 0., 1.), Vec3::Z];
+        let indices = [[0, 1, 2], [0, 2, 3]];
+
+        let hulls = convex_decomposition(&verts, &indices, &default());
+
+        assert!(!hulls.is_empty());
+        assert_solid(&hulls);
+    }
+
+    #[test]
+    fn invalid_indices_are_ignored() {
+        let (mut verts, mut indices) = (vec![], vec![]);
+        cuboid(Vec3::splat(-1.), Vec3::splat(1.), &mut verts, &mut indices);
+        indices.push([0, 1, 100]);
+
+        let hulls = convex_decomposition(&verts, &indices, &default());
+
+        assert_eq!(hulls.len(), 1);
+        assert_solid(&hulls);
+    }
+}
@@ -0,0 +1,82 @@
+//! Static triangle meshes with vertices animated at runtime.
+use bevy::math::Affine3A;
+use bevy::prelude::*;
+
+use crate::assets::GeometryInner;
+use crate::components::{ArticulationHandle, RigidDynamicHandle};
+use crate::prelude as bpx;
+use crate::render::DebugRenderSettings;
+use crate::resources::SharedShapes;
+use crate::systems::{refresh_shapes_geometry, transform_aabb, wake_actors_in_regions, ShapeGeometryQuery};
+
+type VerticesFn = Box<dyn FnOnce(&mut [Vec3]) + Send + Sync>;
+
+/// Modifies vertices of triangle mesh geometry, edits are applied before the next simulation step.
+///
+/// Mesh BVH is refitted instead of cooking the mesh again, and every shape using the geometry
+/// gets updated bounds, so it's cheap enough to call each frame for meshes with a few thousand
+/// vertices. Refitting doesn't rebalance the tree, so large deformations degrade query performance.
+#[derive(Resource, Default)]
+pub struct TriangleMeshEdit {
+    edits: Vec<(Handle<bpx::Geometry>, VerticesFn)>,
+}
+
+impl TriangleMeshEdit {
+    /// `f` receives all mesh vertices, see [`bpx::Geometry::modify_trimesh_vertices`] for their order.
+    pub fn modify(&mut self, geometry: Handle<bpx::Geometry>, f: impl FnOnce(&mut [Vec3]) + Send + Sync + 'static) {
+        self.edits.push((geometry, Box::new(f)));
+    }
+
+    /// Replace all vertices, extra vertices are ignored.
+    pub fn set_vertices(&mut self, geometry: Handle<bpx::Geometry>, vertices: Vec<Vec3>) {
+        self.modify(geometry, move |verts| {
+            let len = verts.len().min(vertices.len());
+            verts[..len].copy_from_slice(&vertices[..len]);
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+}
+
+pub fn apply_triangle_mesh_edits(
+    mut commands: Commands,
+    mut mesh_edit: ResMut<TriangleMeshEdit>,
+    mut scene: ResMut<bpx::Scene>,
+    geometries: Res<Assets<bpx::Geometry>>,
+    debug_render: Option<Res<DebugRenderSettings>>,
+    mut shapes: ShapeGeometryQuery,
+    mut shared_shapes: ResMut<SharedShapes>,
+    mut dynamic_actors: Query<&mut RigidDynamicHandle>,
+    mut articulations: Query<&mut ArticulationHandle>,
+) {
+    for (handle, f) in mesh_edit.edits.drain(..) {
+        let Some(geometry) = geometries.get(&handle) else {
+            bevy::log::warn!("BPxTriangleMeshEdit: geometry not found");
+            continue;
+        };
+
+        let Some((min, max)) = geometry.modify_trimesh_vertices(f) else { continue; };
+
+        // mesh space to shape space, PhysX scales along rotated axes: R⁻¹·S·R
+        let GeometryInner::TriangleMesh(ref geom) = geometry.obj else { unreachable!() };
+        let rotation = Mat3::from_quat(geom.rotation);
+        let scale = rotation.transpose() * Mat3::from_diagonal(geom.scale) * rotation;
+        let mesh_transform = GlobalTransform::from(Affine3A::from_mat3(scale));
+        let local_region = transform_aabb(&mesh_transform, min, max);
+
+        let regions = refresh_shapes_geometry(
+            &mut commands,
+            &mut scene,
+            &mut shapes,
+            &mut shared_shapes,
+            &handle,
+            geometry,
+            local_region,
+            debug_render.is_some(),
+        );
+
+        wake_actors_in_regions(&mut scene, &regions, &mut dynamic_actors, &mut articulations);
+    }
+}
@@ -0,0 +1,63 @@
+//! Errors caused by user data, reported as events instead of panics.
+use std::sync::{Arc, Mutex};
+
+use bevy::asset::HandleId;
+use bevy::prelude::*;
+use enumflags2::BitFlags;
+use physx::foundation::ErrorCode;
+
+use crate::cooking::CookingError;
+
+/// Sent as an event when something spawned by the user can't be turned into PhysX objects,
+/// or when PhysX itself reports an error.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PhysicsError {
+    /// geometry or material asset referenced by the entity failed to load,
+    /// actor stays `PhysicsPending` until the asset is available
+    AssetFailed { entity: Entity, asset: HandleId },
+    /// geometry or material asset referenced by the entity doesn't exist and isn't being loaded
+    /// or cooked, actor stays `PhysicsPending` until the asset is available
+    AssetMissing { entity: Entity, asset: HandleId },
+    /// wheel listed in `bpx::Vehicle` of the entity is not a shape of its actor
+    WheelMapping { entity: Entity, wheel: Entity },
+    /// geometry generated for the entity (e.g. by `AsyncSceneCollider`) failed to cook, or geometry `asset`
+    /// of its shape failed in `AsyncCooking` or in a `.pxcooked` file, actor stays `PhysicsPending` in that case
+    Cooking { entity: Entity, asset: Option<HandleId>, error: CookingError },
+    /// PhysX refused to create an object from components of the entity
+    InvalidDescriptor { entity: Entity, message: &'static str },
+    /// reported by PhysX error callback, which has no way to tell the entity
+    PhysX { code: BitFlags<ErrorCode>, message: String, file: String, line: u32 },
+}
+
+impl PhysicsError {
+    /// Entity that caused the error, `None` for errors reported by PhysX.
+    pub fn entity(&self) -> Option<Entity> {
+        match self {
+            Self::AssetFailed { entity, .. } => Some(*entity),
+            Self::AssetMissing { entity, .. } => Some(*entity),
+            Self::WheelMapping { entity, .. } => Some(*entity),
+            Self::Cooking { entity, .. } => Some(*entity),
+            Self::InvalidDescriptor { entity, .. } => Some(*entity),
+            Self::PhysX { .. } => None,
+        }
+    }
+}
+
+impl std::fmt::Display for PhysicsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::AssetFailed { entity, asset } => write!(f, "asset {asset:?} used by {entity:?} failed to load"),
+            Self::AssetMissing { entity, asset } => write!(f, "asset {asset:?} used by {entity:?} does not exist"),
+            Self::WheelMapping { entity, wheel } => write!(f, "wheel {wheel:?} of vehicle {entity:?} is not a shape of its actor"),
+            Self::Cooking { entity, asset: None, error } => write!(f, "cooking geometry for {entity:?} failed: {error}"),
+            Self::Cooking { entity, asset: Some(asset), error } => write!(f, "cooking geometry {asset:?} used by {entity:?} failed: {error}"),
+            Self::InvalidDescriptor { entity, message } => write!(f, "invalid descriptor on {entity:?}: {message}"),
+            Self::PhysX { code, message, file, line } => write!(f, "[{file}:{line}] {code:?}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for PhysicsError {}
+
+// PhysX reports errors from its own threads, they are sent as events by the plugin afterwards
+pub(crate) type PhysicsErrorQueue = Arc<Mutex<Vec<PhysicsError>>>;
@@ -0,0 +1,336 @@
+//! Heightfields with real-unit heights, multiple materials and holes.
+use std::ops::Range;
+
+use bevy::prelude::*;
+use bevy::render::render_resource::TextureFormat;
+use physx_sys::{PxBitAndByte, PxHeightFieldMaterial, PxHeightFieldSample};
+
+use crate::assets::{heightfield_desc, GeometryInner};
+use crate::components::{ArticulationHandle, RigidDynamicHandle};
+use crate::prelude as bpx;
+use crate::render::DebugRenderSettings;
+use crate::resources::SharedShapes;
+use crate::systems::{refresh_shapes_geometry, wake_actors_in_regions, ShapeGeometryQuery};
+
+/// Material index 127 is reserved by PhysX for holes.
+pub const MAX_HEIGHTFIELD_MATERIALS: usize = PxHeightFieldMaterial::eHOLE as usize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeightFieldError {
+    /// heightfield must have at least 2x2 samples, and heights must have `num_rows * num_cols` elements
+    InvalidSize,
+    /// per-cell arrays must have `(num_rows - 1) * (num_cols - 1)` elements
+    InvalidCellCount,
+    TooManyMaterials,
+    /// cell material index doesn't have a matching material handle
+    InvalidMaterialIndex,
+    NonFiniteHeight,
+    CookingFailed,
+}
+
+/// Builds heightfield geometry from `f32` heights.
+///
+/// Rows go along X axis and columns along Z axis. Heights are quantized into `i16`,
+/// with `heightScale` picked to cover the largest absolute height, which is stored
+/// in `y` component of geometry scale (so `with_scale` on the result will change heights).
+///
+/// Cell `(row, col)` is the quad between samples `(row, col)` and `(row + 1, col + 1)`,
+/// per-cell arrays are indexed with `row * (num_cols - 1) + col`.
+#[derive(Debug, Clone)]
+pub struct HeightFieldBuilder {
+    num_rows: usize,
+    num_cols: usize,
+    heights: Vec<f32>,
+    row_scale: f32,
+    column_scale: f32,
+    materials: Vec<Handle<bpx::Material>>,
+    cell_materials: Vec<u8>,
+    holes: Vec<bool>,
+    tess_flags: Vec<bool>,
+}
+
+impl HeightFieldBuilder {
+    /// Heights are indexed with `row * num_cols + col`.
+    pub fn new(num_rows: usize, num_cols: usize, heights: Vec<f32>) -> Self {
+        Self {
+            num_rows,
+            num_cols,
+            heights,
+            row_scale: 1.,
+            column_scale: 1.,
+            materials: vec![],
+            cell_materials: vec![],
+            holes: vec![],
+            tess_flags: vec![],
+        }
+    }
+
+    /// Import heights from a grayscale image, black is `min_height` and white is `max_height`.
+    ///
+    /// Image X axis maps to rows (world X) and image Y axis maps to columns (world Z).
+    /// Supports 16-bit (`R16Uint`, `R16Unorm`) and 8-bit (`R8Unorm`) single-channel images.
+    pub fn from_image(image: &Image, min_height: f32, max_height: f32) -> Option<Self> {
+        let width = image.texture_descriptor.size.width as usize;
+        let height = image.texture_descriptor.size.height as usize;
+
+        let pixels: Vec<f32> = match image.texture_descriptor.format {
+            TextureFormat::R16Uint | TextureFormat::R16Unorm => {
+                image.data.chunks_exact(2)
+                    .map(|px| u16::from_le_bytes([ px[0], px[1] ]) as f32 / u16::MAX as f32)
+                    .collect()
+            }
+            TextureFormat::R8Unorm => {
+                image.data.iter().map(|px| *px as f32 / u8::MAX as f32).collect()
+            }
+            format => {
+                bevy::log::warn!("unsupported heightfield image format: {format:?}");
+                return None;
+            }
+        };
+
+        if pixels.len() != width * height { return None; }
+
+        let mut heights = vec![0.; width * height];
+        for y in 0..height {
+            for x in 0..width {
+                heights[x * height + y] = min_height + pixels[y * width + x] * (max_height - min_height);
+            }
+        }
+
+        Some(Self::new(width, height, heights))
+    }
+
+    /// Distance between samples along X (rows) and Z (columns) axes.
+    pub fn with_spacing(mut self, row_scale: f32, column_scale: f32) -> Self {
+        self.row_scale = row_scale;
+        self.column_scale = column_scale;
+        self
+    }
+
+    /// `cell_materials` contains indices into `materials` for each cell.
+    pub fn with_materials(mut self, materials: Vec<Handle<bpx::Material>>, cell_materials: Vec<u8>) -> Self {
+        self.materials = materials;
+        self.cell_materials = cell_materials;
+        self
+    }
+
+    /// Cells marked `true` have no collision.
+    pub fn with_holes(mut self, holes: Vec<bool>) -> Self {
+        self.holes = holes;
+        self
+    }
+
+    /// Cells marked `true` are split along the diagonal from `(row, col)` to `(row + 1, col + 1)`,
+    /// others from `(row + 1, col)` to `(row, col + 1)`.
+    pub fn with_tess_flags(mut self, tess_flags: Vec<bool>) -> Self {
+        self.tess_flags = tess_flags;
+        self
+    }
+
+    /// Quantization step for heights, this becomes `heightScale` of the geometry.
+    pub fn height_scale(&self) -> f32 {
+        let max = self.heights.iter().fold(0f32, |max, h| max.max(h.abs()));
+        if max > 0. { max / i16::MAX as f32 } else { 1. }
+    }
+
+    pub fn build(&self, physics: &mut bpx::Physics, cooking: &bpx::Cooking) -> Result<bpx::Geometry, HeightFieldError> {
+        let samples = self.samples()?;
+
+        let hfield_desc = heightfield_desc(&samples, self.num_rows, self.num_cols);
+
+        let mesh = cooking.create_height_field(physics.physics_mut(), &hfield_desc)
+            .ok_or(HeightFieldError::CookingFailed)?;
+
+        let mut geometry = bpx::Geometry::from(mesh)
+            .with_scale(Vec3::new(self.row_scale, self.height_scale(), self.column_scale));
+
+        if let GeometryInner::HeightField(ref mut geom) = geometry.obj {
+            geom.materials = self.materials.clone();
+        }
+
+        Ok(geometry)
+    }
+
+    fn samples(&self) -> Result<Vec<PxHeightFieldSample>, HeightFieldError> {
+        let (rows, cols) = (self.num_rows, self.num_cols);
+        if rows < 2 || cols < 2 || self.heights.len() != rows * cols {
+            return Err(HeightFieldError::InvalidSize);
+        }
+
+        let num_cells = (rows - 1) * (cols - 1);
+        for cells in [ self.cell_materials.len(), self.holes.len(), self.tess_flags.len() ] {
+            if cells != 0 && cells != num_cells { return Err(HeightFieldError::InvalidCellCount); }
+        }
+
+        if self.materials.len() > MAX_HEIGHTFIELD_MATERIALS { return Err(HeightFieldError::TooManyMaterials); }
+        if self.heights.iter().any(|h| !h.is_finite()) { return Err(HeightFieldError::NonFiniteHeight); }
+
+        let height_scale = self.height_scale();
+        let mut samples = Vec::with_capacity(rows * cols);
+
+        for row in 0..rows {
+            for col in 0..cols {
+                let height = (self.heights[row * cols + col] / height_scale).round() as i16;
+
+                // last row and column have no cells, so their material is never used
+                let mut material = 0;
+                let mut tess_flag = false;
+
+                if row + 1 < rows && col + 1 < cols {
+                    let cell = row * (cols - 1) + col;
+
+                    if self.holes.get(cell).copied().unwrap_or(false) {
+                        material = PxHeightFieldMaterial::eHOLE as u8;
+                    } else if let Some(index) = self.cell_materials.get(cell).copied() {
+                        if index as usize >= self.materials.len().max(1) {
+                            return Err(HeightFieldError::InvalidMaterialIndex);
+                        }
+                        material = index;
+                    }
+
+                    tess_flag = self.tess_flags.get(cell).copied().unwrap_or(false);
+                }
+
+                samples.push(PxHeightFieldSample {
+                    height,
+                    // high bit of the first material index is the tess flag
+                    materialIndex0: PxBitAndByte { mData: material | if tess_flag { 0x80 } else { 0 } },
+                    materialIndex1: PxBitAndByte { mData: material },
+                });
+            }
+        }
+
+        Ok(samples)
+    }
+}
+
+type HeightFn = Box<dyn FnMut(usize, usize, f32) -> f32 + Send + Sync>;
+
+struct TerrainEditRegion {
+    geometry: Handle<bpx::Geometry>,
+    rows: Range<usize>,
+    cols: Range<usize>,
+    height: HeightFn,
+}
+
+/// Modifies heightfield geometry at runtime, edits are applied before the next simulation step.
+///
+/// Every shape using the geometry is updated, bodies touching the modified region are woken up.
+/// Heights are in the same units as [`HeightFieldBuilder`], but heightfield can't be requantized
+/// in place, so heights outside of `i16::MIN..=i16::MAX` times `heightScale` are clamped.
+#[derive(Resource, Default)]
+pub struct TerrainEdit {
+    edits: Vec<TerrainEditRegion>,
+}
+
+impl TerrainEdit {
+    /// `height` receives row, column and current height of every sample in the region,
+    /// and returns the new height.
+    pub fn modify(
+        &mut self,
+        geometry: Handle<bpx::Geometry>,
+        rows: Range<usize>,
+        cols: Range<usize>,
+        height: impl FnMut(usize, usize, f32) -> f32 + Send + Sync + 'static,
+    ) {
+        self.edits.push(TerrainEditRegion { geometry, rows, cols, height: Box::new(height) });
+    }
+
+    /// Replace heights in a region, `heights` are indexed with `row * num_cols + col`
+    /// relative to the region start.
+    pub fn set_heights(&mut self, geometry: Handle<bpx::Geometry>, start_row: usize, start_col: usize, num_cols: usize, heights: Vec<f32>) {
+        let num_rows = if num_cols == 0 { 0 } else { heights.len() / num_cols };
+        let rows = start_row..start_row + num_rows;
+        let cols = start_col..start_col + num_cols;
+
+        self.modify(geometry, rows, cols, move |row, col, _| {
+            heights[(row - start_row) * num_cols + col - start_col]
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+}
+
+pub fn apply_terrain_edits(
+    mut commands: Commands,
+    mut terrain_edit: ResMut<TerrainEdit>,
+    mut scene: ResMut<bpx::Scene>,
+    geometries: Res<Assets<bpx::Geometry>>,
+    debug_render: Option<Res<DebugRenderSettings>>,
+    mut shapes: ShapeGeometryQuery,
+    mut shared_shapes: ResMut<SharedShapes>,
+    mut dynamic_actors: Query<&mut RigidDynamicHandle>,
+    mut articulations: Query<&mut ArticulationHandle>,
+) {
+    for mut edit in terrain_edit.edits.drain(..) {
+        let Some(geometry) = geometries.get(&edit.geometry) else {
+            bevy::log::warn!("BPxTerrainEdit: geometry not found");
+            continue;
+        };
+
+        let GeometryInner::HeightField(ref geom) = geometry.obj else {
+            bevy::log::warn!("BPxTerrainEdit: geometry is not a heightfield");
+            continue;
+        };
+
+        let mut hfield = geom.hfield.lock().unwrap();
+        let (num_rows, num_cols) = (hfield.get_nb_rows() as usize, hfield.get_nb_columns() as usize);
+        let rows = edit.rows.start..edit.rows.end.min(num_rows);
+        let cols = edit.cols.start..edit.cols.end.min(num_cols);
+        if rows.is_empty() || cols.is_empty() { continue; }
+
+        // keep material indices and tess flags of existing samples
+        let old_samples = hfield.save_cells();
+        let mut samples = Vec::with_capacity(rows.len() * cols.len());
+        let mut height_range = (f32::MAX, f32::MIN);
+
+        for row in rows.clone() {
+            for col in cols.clone() {
+                let old = old_samples[row * num_cols + col];
+                let old_height = old.height as f32 * geom.scale.y;
+                let new_height = (edit.height)(row, col, old_height);
+                let height = (new_height / geom.scale.y).round().clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+
+                height_range.0 = height_range.0.min(old_height).min(height as f32 * geom.scale.y);
+                height_range.1 = height_range.1.max(old_height).max(height as f32 * geom.scale.y);
+                samples.push(PxHeightFieldSample { height, ..old });
+            }
+        }
+
+        let subfield_desc = heightfield_desc(&samples, rows.len(), cols.len());
+        if !hfield.modify_samples(cols.start as i32, rows.start as i32, &subfield_desc, true) {
+            bevy::log::warn!("BPxTerrainEdit: modify_samples failed");
+            continue;
+        }
+
+        // region bounds in heightfield space, extended by one sample to catch bodies on the edges
+        let local_min = Vec3::new(
+            rows.start.saturating_sub(1) as f32 * geom.scale.x,
+            height_range.0,
+            cols.start.saturating_sub(1) as f32 * geom.scale.z,
+        );
+        let local_max = Vec3::new(
+            rows.end as f32 * geom.scale.x,
+            height_range.1,
+            cols.end as f32 * geom.scale.z,
+        );
+
+        // release the heightfield, shape geometry below locks it again
+        drop(hfield);
+
+        let regions = refresh_shapes_geometry(
+            &mut commands,
+            &mut scene,
+            &mut shapes,
+            &mut shared_shapes,
+            &edit.geometry,
+            geometry,
+            (local_min, local_max),
+            debug_render.is_some(),
+        );
+
+        wake_actors_in_regions(&mut scene, &regions, &mut dynamic_actors, &mut articulations);
+    }
+}
@@ -15,26 +15,41 @@ mod type_bridge;
 
 mod systems;
 pub mod assets;
+pub mod bvh;
 pub mod callbacks;
 pub mod components;
+pub mod cooking;
+pub mod decomposition;
+pub mod deformable;
+pub mod errors;
+pub mod heightfield;
 pub mod prelude;
 pub mod resources;
 pub mod render;
+pub mod urdf;
+pub mod vehicle;
 
 // reexport physx to avoid version conflicts
 pub use physx;
 pub use physx_sys;
 
-use resources::{DefaultMaterial, VehicleSimulation, VehicleSimulationMethod};
+use resources::{DefaultMaterial, FailedActors, FailedShapes, SharedShapes, VehicleSimulation, VehicleSimulationMethod};
 
-type PxMaterial = physx::material::PxMaterial<()>;
+type PxMaterial = physx::material::PxMaterial<assets::MaterialUserData>;
 type PxShape = physx::shape::PxShape<Entity, PxMaterial>;
-type PxArticulationLink = physx::articulation_link::PxArticulationLink<(), PxShape>;
+type PxArticulationLink = physx::articulation_link::PxArticulationLink<Entity, PxShape>;
 type PxRigidStatic = physx::rigid_static::PxRigidStatic<Entity, PxShape>;
 type PxRigidDynamic = physx::rigid_dynamic::PxRigidDynamic<Entity, PxShape>;
 type PxArticulation = physx::articulation::PxArticulation<(), PxArticulationLink>;
 type PxArticulationReducedCoordinate =
-    physx::articulation_reduced_coordinate::PxArticulationReducedCoordinate<(), PxArticulationLink>;
+    physx::articulation_reduced_coordinate::PxArticulationReducedCoordinate<Entity, PxArticulationLink>;
+type PxAggregate = physx::aggregate::PxAggregate<
+    PxArticulationLink,
+    PxRigidStatic,
+    PxRigidDynamic,
+    PxArticulation,
+    PxArticulationReducedCoordinate,
+>;
 
 type PxScene = physx::scene::PxScene<
     (),
@@ -80,9 +95,47 @@ impl Default for TolerancesScale {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeshMidphase {
+    /// faster cooking, slower queries
+    Bvh33,
+    /// faster queries and lower memory usage, requires SSE2
+    Bvh34,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CookingDescriptor {
+    pub midphase: MeshMidphase,
+    /// weld triangle mesh vertices closer than this distance, disabled if `None`
+    pub mesh_weld_tolerance: Option<f32>,
+    /// remove duplicate vertices and degenerate triangles, only disable for known clean data
+    pub mesh_cleaning: bool,
+    /// validate meshes before cooking, only disable for known valid data
+    pub mesh_validation: bool,
+    /// maximum number of vertices of computed convex hulls, PhysX allows 4..=255
+    pub convex_vertex_limit: u16,
+    pub build_triangle_adjacencies: bool,
+    pub suppress_triangle_mesh_remap_table: bool,
+}
+
+impl Default for CookingDescriptor {
+    fn default() -> Self {
+        Self {
+            midphase: MeshMidphase::Bvh34,
+            mesh_weld_tolerance: None,
+            mesh_cleaning: true,
+            mesh_validation: true,
+            convex_vertex_limit: 255,
+            build_triangle_adjacencies: false,
+            suppress_triangle_mesh_remap_table: false,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct FoundationDescriptor {
     pub cooking: bool,
+    pub cooking_params: CookingDescriptor,
     pub extensions: bool,
     pub tolerances: TolerancesScale,
 
@@ -96,12 +149,16 @@ pub struct FoundationDescriptor {
     pub vehicles_max_hit_actor_acceleration: f32,
     pub vehicles_sweep_hit_rejection_angles: [ f32; 2 ],
     pub vehicles_simulation_method: VehicleSimulationMethod,
+
+    /// panic on PhysX errors instead of sending them as `PhysicsError` events, useful in tests
+    pub strict: bool,
 }
 
 impl Default for FoundationDescriptor {
     fn default() -> Self {
         Self {
             cooking: true,
+            cooking_params: default(),
             extensions: true,
             tolerances: default(),
             visual_debugger: true,
@@ -117,6 +174,7 @@ impl Default for FoundationDescriptor {
                 sweep_width_scale: 1.,
                 sweep_radius_scale: 1.01,
             },
+            strict: false,
         }
     }
 }
@@ -219,11 +277,21 @@ impl Plugin for PhysXPlugin {
 
         app.add_asset::<bpx::Geometry>();
         app.add_asset::<bpx::Material>();
+        app.add_asset::<urdf::Urdf>();
+        app.init_asset_loader::<urdf::UrdfLoader>();
+        app.add_asset::<bpx::VehicleDescriptor>();
+        app.init_asset_loader::<vehicle::VehicleDescriptorLoader>();
+        app.add_asset::<bpx::CookedGeometryFile>();
+        app.init_asset_loader::<cooking::CookedGeometryLoader>();
 
         app.register_type::<Velocity>();
+        app.register_type::<ShapeSettings>();
+        app.register_type::<MaterialDescriptor>();
+        app.register_type::<assets::CombineMode>();
+        app.register_type::<bpx::VehicleDescriptor>();
 
         if self.foundation.cooking {
-            app.insert_resource(Cooking::new(&mut physics));
+            app.insert_resource(Cooking::new(&mut physics, &self.foundation.cooking_params));
         }
 
         if self.foundation.vehicles {
@@ -231,7 +299,16 @@ impl Plugin for PhysXPlugin {
         }
 
         app.insert_resource(scene);
+        app.add_event::<callbacks::ContactEvent>();
+        app.add_event::<errors::PhysicsError>();
         app.insert_resource(DefaultMaterial::default());
+        app.insert_resource(SharedShapes::default());
+        app.insert_resource(FailedActors::default());
+        app.insert_resource(FailedShapes::default());
+        app.insert_resource(cooking::AsyncCooking::default());
+        app.insert_resource(cooking::FailedCookedFiles::default());
+        app.insert_resource(heightfield::TerrainEdit::default());
+        app.insert_resource(deformable::TriangleMeshEdit::default());
 
         app.register_type::<SimTime>();
         app.insert_resource(SimTime::new(self.timestep));
@@ -242,9 +319,31 @@ impl Plugin for PhysXPlugin {
         let mut stage = SystemStage::parallel();
         stage.add_system(time_sync.before(systems::scene_simulate));
         stage.add_system(systems::apply_user_changes.before(systems::scene_simulate));
+        stage.add_system(systems::solve_inverse_kinematics.before(systems::scene_simulate));
+        stage.add_system(heightfield::apply_terrain_edits.before(systems::sync_actor_shapes));
+        stage.add_system(deformable::apply_triangle_mesh_edits.before(systems::sync_actor_shapes));
+        stage.add_system(systems::sync_shape_materials.before(systems::sync_actor_shapes));
+        stage.add_system(systems::sync_shape_settings.before(systems::scene_simulate));
+        stage.add_system(systems::sync_actor_shapes.after(systems::apply_user_changes).before(systems::scene_simulate));
         stage.add_system(systems::scene_simulate);
-        stage.add_system(systems::create_dynamic_actors.after(systems::scene_simulate));
+        stage.add_system(systems::send_contact_events.after(systems::scene_simulate));
+        stage.add_system(systems::send_physics_errors.after(systems::scene_simulate));
+        stage.add_system(systems::remove_aggregates.after(systems::scene_simulate));
+        stage.add_system(systems::create_aggregates.after(systems::remove_aggregates));
+        stage.add_system(systems::rebuild_changed_actors.after(systems::scene_simulate).before(systems::create_dynamic_actors));
+        stage.add_system(systems::create_async_scene_colliders.after(systems::scene_simulate));
+        stage.add_system(systems::create_convex_decompositions.after(systems::scene_simulate));
+        stage.add_system(cooking::finish_async_cooking.after(systems::scene_simulate));
+        stage.add_system(cooking::create_cooked_geometries.after(systems::scene_simulate));
+        stage.add_system(systems::create_dynamic_actors.after(systems::create_aggregates).after(cooking::finish_async_cooking).after(cooking::create_cooked_geometries));
+        stage.add_system(systems::create_articulations.after(systems::create_aggregates).after(cooking::finish_async_cooking).after(cooking::create_cooked_geometries));
+        stage.add_system(systems::insert_aggregates.after(systems::create_dynamic_actors).after(systems::create_articulations));
+        stage.add_system(systems::remove_static_chunks.after(systems::scene_simulate));
+        stage.add_system(systems::insert_static_chunks.after(systems::create_dynamic_actors).after(systems::remove_static_chunks));
+        stage.add_system(systems::release_unused_shared_shapes.after(systems::scene_simulate));
         stage.add_system(systems::writeback_actors.after(systems::scene_simulate));
+        stage.add_system(systems::writeback_articulations.after(systems::scene_simulate));
+        stage.add_system(urdf::spawn_urdf_robots.after(systems::scene_simulate));
 
         // this needs to happen after globaltransform is applied,
         // and inserting it after(CoreStage::Update) messes with conditional staging;
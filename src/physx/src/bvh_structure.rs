@@ -1,12 +1,16 @@
-use crate::{math::PxBounds3, owner::Owner, traits::Class};
+use crate::{
+    math::{PxBounds3, PxVec3},
+    owner::Owner,
+    traits::Class,
+};
 
 use physx_sys::{
     PxBVHStructure_getBounds,
     PxBVHStructure_getNbBounds,
     //PxBVHStructure_getConcreteTypeName,
-    //PxBVHStructure_overlap,
-    //PxBVHStructure_raycast,
-    //PxBVHStructure_sweep,
+    PxBVHStructure_overlap,
+    PxBVHStructure_raycast,
+    PxBVHStructure_sweep,
     PxBase_release_mut,
 };
 
@@ -51,4 +55,54 @@ impl BvhStructure {
     pub fn get_nb_bounds(&self) -> u32 {
         unsafe { PxBVHStructure_getNbBounds(self.as_ptr()) }
     }
+
+    /// Raycast against the bounds, returns indices of the bounds hit by the ray (at most `max_hits`).
+    pub fn raycast(&self, origin: &PxVec3, unit_dir: &PxVec3, max_dist: f32, max_hits: u32) -> Vec<u32> {
+        let mut hits = vec![0; max_hits as usize];
+        let nb_hits = unsafe {
+            PxBVHStructure_raycast(
+                self.as_ptr(),
+                origin.as_ptr(),
+                unit_dir.as_ptr(),
+                max_dist,
+                max_hits,
+                hits.as_mut_ptr(),
+            )
+        };
+        hits.truncate(nb_hits as usize);
+        hits
+    }
+
+    /// Sweep an AABB against the bounds, returns indices of the bounds hit by the sweep (at most `max_hits`).
+    pub fn sweep(
+        &self,
+        aabb: &PxBounds3,
+        unit_dir: &PxVec3,
+        max_dist: f32,
+        max_hits: u32,
+    ) -> Vec<u32> {
+        let mut hits = vec![0; max_hits as usize];
+        let nb_hits = unsafe {
+            PxBVHStructure_sweep(
+                self.as_ptr(),
+                aabb.as_ptr(),
+                unit_dir.as_ptr(),
+                max_dist,
+                max_hits,
+                hits.as_mut_ptr(),
+            )
+        };
+        hits.truncate(nb_hits as usize);
+        hits
+    }
+
+    /// Overlap an AABB with the bounds, returns indices of the overlapping bounds (at most `max_hits`).
+    pub fn overlap(&self, aabb: &PxBounds3, max_hits: u32) -> Vec<u32> {
+        let mut hits = vec![0; max_hits as usize];
+        let nb_hits = unsafe {
+            PxBVHStructure_overlap(self.as_ptr(), aabb.as_ptr(), max_hits, hits.as_mut_ptr())
+        };
+        hits.truncate(nb_hits as usize);
+        hits
+    }
 }
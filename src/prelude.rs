@@ -6,17 +6,56 @@ pub use super::{
     PhysXPlugin,
     SimTime,
     FoundationDescriptor,
+    CookingDescriptor,
+    MeshMidphase,
     SceneDescriptor,
 };
 
 #[doc(hidden)]
-pub use super::assets::{Geometry, Material};
+pub use super::assets::{Geometry, Material, MaterialDescriptor, MeshValidationReport, SurfaceTag};
 
 #[doc(hidden)]
-pub use super::components::{RigidBody, Shape, ShapeHandle, MassProperties, Velocity, Vehicle, VehicleHandle};
+pub use super::components::{
+    RigidBody, Shape, ShapeHandle, ShapeSettings, MassProperties, ColliderDensity, ComputedMassProperties,
+    Velocity, Vehicle, DescribedVehicle, VehicleHandle,
+    Articulation, ArticulationJoint, ArticulationJointDrive, ArticulationHandle, ArticulationLinkHandle,
+    InverseKinematics, Aggregate, AggregateHandle, StaticChunk, StaticChunkHandle, ActorBvh, PhysicsPending,
+    AsyncSceneCollider, ComputedCollider,
+    ConvexDecompositionCollider,
+};
+
+#[doc(hidden)]
+pub use super::resources::{Physics, Scene, Cooking, VehicleSimulation, RaycastHit, SharedShapes};
 
 #[doc(hidden)]
-pub use super::resources::{Physics, Scene, Cooking, VehicleSimulation};
+pub use super::callbacks::{ContactEvent, ContactEventKind, ContactPoint};
 
 #[doc(hidden)]
 pub use super::render::PhysXDebugRenderPlugin;
+
+#[doc(hidden)]
+pub use super::cooking::{AsyncCooking, CookedGeometry, CookedGeometryFile, CookingCache};
+
+#[doc(hidden)]
+pub use super::decomposition::ConvexDecompositionParams;
+
+#[doc(hidden)]
+pub use super::bvh::Bvh;
+
+#[doc(hidden)]
+pub use super::errors::PhysicsError;
+
+#[doc(hidden)]
+pub use super::vehicle::{
+    VehicleDescriptor, VehicleDriveType, ChassisDescriptor, WheelDescriptor, SuspensionDescriptor,
+    EngineDescriptor, GearsDescriptor, DifferentialDescriptor, DifferentialType,
+};
+
+#[doc(hidden)]
+pub use super::deformable::TriangleMeshEdit;
+
+#[doc(hidden)]
+pub use super::heightfield::{HeightFieldBuilder, HeightFieldError, TerrainEdit};
+
+#[doc(hidden)]
+pub use super::urdf::{Urdf, UrdfRobot};
@@ -69,6 +69,7 @@ impl Plugin for PhysXDebugRenderPlugin {
         });
 
         app.add_system(create_debug_meshes);
+        app.add_system(rebuild_stale_debug_meshes);
         app.add_system(toggle_debug_meshes_visibility);
     }
 }
@@ -93,172 +94,182 @@ impl bevy::pbr::Material for DebugRenderMaterial {
     }
 }
 
-fn create_debug_meshes(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    materials: Res<DebugRenderMaterials>,
-    settings: Res<DebugRenderSettings>,
-    mut geometries: ResMut<Assets<Geometry>>,
-    query: Query<(Entity, &Shape), Added<Shape>>,
-) {
-    for (entity, shape) in query.iter() {
-        let Some(geometry) = geometries.get_mut(&shape.geometry) else { continue; };
-        let mut positions = vec![];
-        let mut indices = vec![];
-        const SPHERE_SEGMENTS: u32 = 24;
-
-        match geometry.obj {
-            GeometryInner::Sphere(geom)  => {
-                for i in 0..SPHERE_SEGMENTS {
-                    let arclen = std::f32::consts::TAU / SPHERE_SEGMENTS as f32 * i as f32;
-                    let (sin, cos) = arclen.sin_cos();
-                    positions.push(Vec3::new(sin * geom.radius, cos * geom.radius, 0.));
-                    positions.push(Vec3::new(sin * geom.radius, 0., cos * geom.radius));
-                    positions.push(Vec3::new(0., sin * geom.radius, cos * geom.radius));
-
-                    for j in 0..3 {
-                        indices.push(i * 3 + j);
-                        indices.push(((i + 1) % SPHERE_SEGMENTS) * 3 + j);
-                    }
-                }
-            },
-            GeometryInner::Plane(_) => {
-                for x in 0..=0 {
-                    indices.push(positions.len() as u32);
-                    positions.push(Vec3::new(0., x as f32, -1000000.));
-                    indices.push(positions.len() as u32);
-                    positions.push(Vec3::new(0., x as f32, 1000000.));
+fn debug_mesh(geometry: &Geometry) -> Mesh {
+    let mut positions = vec![];
+    let mut indices = vec![];
+    const SPHERE_SEGMENTS: u32 = 24;
+
+    match geometry.obj {
+        GeometryInner::Sphere(geom)  => {
+            for i in 0..SPHERE_SEGMENTS {
+                let arclen = std::f32::consts::TAU / SPHERE_SEGMENTS as f32 * i as f32;
+                let (sin, cos) = arclen.sin_cos();
+                positions.push(Vec3::new(sin * geom.radius, cos * geom.radius, 0.));
+                positions.push(Vec3::new(sin * geom.radius, 0., cos * geom.radius));
+                positions.push(Vec3::new(0., sin * geom.radius, cos * geom.radius));
+
+                for j in 0..3 {
+                    indices.push(i * 3 + j);
+                    indices.push(((i + 1) % SPHERE_SEGMENTS) * 3 + j);
                 }
+            }
+        },
+        GeometryInner::Plane(_) => {
+            for x in 0..=0 {
+                indices.push(positions.len() as u32);
+                positions.push(Vec3::new(0., x as f32, -1000000.));
+                indices.push(positions.len() as u32);
+                positions.push(Vec3::new(0., x as f32, 1000000.));
+            }
 
-                for y in 0..=0 {
-                    indices.push(positions.len() as u32);
-                    positions.push(Vec3::new(0., -1000000., y as f32));
-                    indices.push(positions.len() as u32);
-                    positions.push(Vec3::new(0., 1000000., y as f32));
-                }
-            },
-            GeometryInner::Capsule(geom)  => {
-                for i in 0..SPHERE_SEGMENTS+2 {
-                    let (arclen, offset) = if i <= SPHERE_SEGMENTS / 2 {
-                        (std::f32::consts::TAU / SPHERE_SEGMENTS as f32 * i as f32, geom.halfHeight)
-                    } else {
-                        (std::f32::consts::TAU / SPHERE_SEGMENTS as f32 * (i - 1) as f32, -geom.halfHeight)
-                    };
-                    let (sin, cos) = arclen.sin_cos();
-                    positions.push(Vec3::new(sin * geom.radius + offset, cos * geom.radius, 0.));
-                    positions.push(Vec3::new(sin * geom.radius + offset, 0., cos * geom.radius));
-
-                    for j in 0..2 {
-                        indices.push(i * 2 + j);
-                        indices.push(((i + 1) % (SPHERE_SEGMENTS + 2)) * 2 + j);
-                    }
+            for y in 0..=0 {
+                indices.push(positions.len() as u32);
+                positions.push(Vec3::new(0., -1000000., y as f32));
+                indices.push(positions.len() as u32);
+                positions.push(Vec3::new(0., 1000000., y as f32));
+            }
+        },
+        GeometryInner::Capsule(geom)  => {
+            for i in 0..SPHERE_SEGMENTS+2 {
+                let (arclen, offset) = if i <= SPHERE_SEGMENTS / 2 {
+                    (std::f32::consts::TAU / SPHERE_SEGMENTS as f32 * i as f32, geom.halfHeight)
+                } else {
+                    (std::f32::consts::TAU / SPHERE_SEGMENTS as f32 * (i - 1) as f32, -geom.halfHeight)
+                };
+                let (sin, cos) = arclen.sin_cos();
+                positions.push(Vec3::new(sin * geom.radius + offset, cos * geom.radius, 0.));
+                positions.push(Vec3::new(sin * geom.radius + offset, 0., cos * geom.radius));
+
+                for j in 0..2 {
+                    indices.push(i * 2 + j);
+                    indices.push(((i + 1) % (SPHERE_SEGMENTS + 2)) * 2 + j);
                 }
+            }
 
-                let pos_offset = positions.len() as u32;
-                for i in 0..SPHERE_SEGMENTS {
-                    let arclen = std::f32::consts::TAU / SPHERE_SEGMENTS as f32 * i as f32;
-                    let (sin, cos) = arclen.sin_cos();
-                    positions.push(Vec3::new(-geom.halfHeight, sin * geom.radius, cos * geom.radius));
-                    positions.push(Vec3::new(geom.halfHeight, sin * geom.radius, cos * geom.radius));
+            let pos_offset = positions.len() as u32;
+            for i in 0..SPHERE_SEGMENTS {
+                let arclen = std::f32::consts::TAU / SPHERE_SEGMENTS as f32 * i as f32;
+                let (sin, cos) = arclen.sin_cos();
+                positions.push(Vec3::new(-geom.halfHeight, sin * geom.radius, cos * geom.radius));
+                positions.push(Vec3::new(geom.halfHeight, sin * geom.radius, cos * geom.radius));
 
-                    for j in 0..2 {
-                        indices.push(i * 2 + j + pos_offset);
-                        indices.push(((i + 1) % SPHERE_SEGMENTS) * 2 + j + pos_offset);
-                    }
-                }
-            },
-            GeometryInner::Box(geom) => {
-                let ext = geom.halfExtents;
-                positions.push(Vec3::new(-ext.x, -ext.y, -ext.z));
-                positions.push(Vec3::new(-ext.x, -ext.y, ext.z));
-                positions.push(Vec3::new(-ext.x, ext.y, -ext.z));
-                positions.push(Vec3::new(-ext.x, ext.y, ext.z));
-                positions.push(Vec3::new(ext.x, -ext.y, -ext.z));
-                positions.push(Vec3::new(ext.x, -ext.y, ext.z));
-                positions.push(Vec3::new(ext.x, ext.y, -ext.z));
-                positions.push(Vec3::new(ext.x, ext.y, ext.z));
-                for idx in [0, 1, 0, 2, 1, 3, 2, 3, 4, 5, 4, 6, 5, 7, 6, 7, 0, 4, 1, 5, 2, 6, 3, 7] {
-                    indices.push(idx);
-                }
-            },
-            GeometryInner::ConvexMesh(ref geom) => {
-                let mesh = geom.mesh.lock().unwrap();
-                for vertex in mesh.get_vertices() {
-                    positions.push(geom.rotation * vertex.to_bevy() * geom.scale);
+                for j in 0..2 {
+                    indices.push(i * 2 + j + pos_offset);
+                    indices.push(((i + 1) % SPHERE_SEGMENTS) * 2 + j + pos_offset);
                 }
+            }
+        },
+        GeometryInner::Box(geom) => {
+            let ext = geom.halfExtents;
+            positions.push(Vec3::new(-ext.x, -ext.y, -ext.z));
+            positions.push(Vec3::new(-ext.x, -ext.y, ext.z));
+            positions.push(Vec3::new(-ext.x, ext.y, -ext.z));
+            positions.push(Vec3::new(-ext.x, ext.y, ext.z));
+            positions.push(Vec3::new(ext.x, -ext.y, -ext.z));
+            positions.push(Vec3::new(ext.x, -ext.y, ext.z));
+            positions.push(Vec3::new(ext.x, ext.y, -ext.z));
+            positions.push(Vec3::new(ext.x, ext.y, ext.z));
+            for idx in [0, 1, 0, 2, 1, 3, 2, 3, 4, 5, 4, 6, 5, 7, 6, 7, 0, 4, 1, 5, 2, 6, 3, 7] {
+                indices.push(idx);
+            }
+        },
+        GeometryInner::ConvexMesh(ref geom) => {
+            let mesh = geom.mesh.lock().unwrap();
+            for vertex in mesh.get_vertices() {
+                positions.push(geom.rotation * vertex.to_bevy() * geom.scale);
+            }
 
-                let index_buffer = mesh.get_index_buffer();
-                let mut dedup = HashSet::new();
+            let index_buffer = mesh.get_index_buffer();
+            let mut dedup = HashSet::new();
 
-                for idx in 0..mesh.get_nb_polygons() {
-                    let polygon = mesh.get_polygon_data(idx).unwrap();
-                    for i in polygon.index_base..polygon.index_base+polygon.nb_verts {
-                        let next = if i + 1 == polygon.index_base+polygon.nb_verts { polygon.index_base } else { i + 1 };
-                        let p1 = index_buffer[i as usize] as u32;
-                        let p2 = index_buffer[next as usize] as u32;
+            for idx in 0..mesh.get_nb_polygons() {
+                let polygon = mesh.get_polygon_data(idx).unwrap();
+                for i in polygon.index_base..polygon.index_base+polygon.nb_verts {
+                    let next = if i + 1 == polygon.index_base+polygon.nb_verts { polygon.index_base } else { i + 1 };
+                    let p1 = index_buffer[i as usize] as u32;
+                    let p2 = index_buffer[next as usize] as u32;
 
-                        if dedup.insert((p1.min(p2), p1.max(p2))) {
-                            indices.push(p1);
-                            indices.push(p2);
-                        }
+                    if dedup.insert((p1.min(p2), p1.max(p2))) {
+                        indices.push(p1);
+                        indices.push(p2);
                     }
                 }
-            },
-            GeometryInner::TriangleMesh(ref geom) => {
-                let mesh = geom.mesh.lock().unwrap();
-                for vertex in mesh.get_vertices() {
-                    positions.push(geom.rotation * vertex.to_bevy() * geom.scale);
-                }
+            }
+        },
+        GeometryInner::TriangleMesh(ref geom) => {
+            let mesh = geom.mesh.lock().unwrap();
+            for vertex in mesh.get_vertices() {
+                positions.push(geom.rotation * vertex.to_bevy() * geom.scale);
+            }
 
-                let index_buffer = mesh.get_triangles();
-                let length = mesh.get_nb_triangles() * 3;
-                let mut dedup = HashSet::new();
+            let index_buffer = mesh.get_triangles();
+            let length = mesh.get_nb_triangles() * 3;
+            let mut dedup = HashSet::new();
 
-                for idx in (0..).step_by(3) {
-                    if idx + 2 >= length { break; }
+            for idx in (0..).step_by(3) {
+                if idx + 2 >= length { break; }
 
-                    let idx = idx as usize;
-                    let (point1, point2, point3) = match index_buffer {
-                        TriangleMeshIndices::U16(vec) => (vec[idx] as u32, vec[idx+1] as u32, vec[idx+2] as u32),
-                        TriangleMeshIndices::U32(vec) => (vec[idx], vec[idx+1], vec[idx+2]),
-                    };
+                let idx = idx as usize;
+                let (point1, point2, point3) = match index_buffer {
+                    TriangleMeshIndices::U16(vec) => (vec[idx] as u32, vec[idx+1] as u32, vec[idx+2] as u32),
+                    TriangleMeshIndices::U32(vec) => (vec[idx], vec[idx+1], vec[idx+2]),
+                };
 
-                    for (p1, p2) in [(point1, point2), (point2, point3), (point3, point1)] {
-                        if dedup.insert((p1.min(p2), p1.max(p2))) {
-                            indices.push(p1);
-                            indices.push(p2);
-                        }
+                for (p1, p2) in [(point1, point2), (point2, point3), (point3, point1)] {
+                    if dedup.insert((p1.min(p2), p1.max(p2))) {
+                        indices.push(p1);
+                        indices.push(p2);
                     }
                 }
-            },
-            GeometryInner::HeightField(ref geom) => {
-                let mesh = geom.hfield.lock().unwrap();
-                let rows = mesh.get_nb_rows();
-                let columns = mesh.get_nb_columns();
-                let samples = mesh.save_cells();
-
-                for row in 0..rows {
-                    for column in 0..columns {
-                        let sample = samples[(row * columns + column) as usize];
-                        positions.push(geom.scale * Vec3::new(row as f32, sample.height as f32, column as f32));
-
-                        if column != 0 {
-                            indices.push(row * columns + column - 1);
-                            indices.push(row * columns + column);
-                        }
-
-                        if row != 0 {
-                            indices.push((row - 1) * columns + column);
-                            indices.push(row * columns + column);
-                        }
+            }
+        },
+        GeometryInner::HeightField(ref geom) => {
+            let mesh = geom.hfield.lock().unwrap();
+            let rows = mesh.get_nb_rows();
+            let columns = mesh.get_nb_columns();
+            let samples = mesh.save_cells();
+
+            for row in 0..rows {
+                for column in 0..columns {
+                    let sample = samples[(row * columns + column) as usize];
+                    positions.push(geom.scale * Vec3::new(row as f32, sample.height as f32, column as f32));
+
+                    if column != 0 {
+                        indices.push(row * columns + column - 1);
+                        indices.push(row * columns + column);
+                    }
+
+                    if row != 0 {
+                        indices.push((row - 1) * columns + column);
+                        indices.push(row * columns + column);
                     }
                 }
-            },
-        }
+            }
+        },
+    }
 
-        let mut mesh = Mesh::new(PrimitiveTopology::LineList);
-        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
-        mesh.set_indices(Some(Indices::U32(indices)));
+    // debug mesh is a child of the shape entity, so it needs the same correction as the shape pose
+    for position in positions.iter_mut() {
+        *position = geometry.axis_rotation * *position;
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::LineList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
+}
+
+fn create_debug_meshes(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    materials: Res<DebugRenderMaterials>,
+    settings: Res<DebugRenderSettings>,
+    geometries: Res<Assets<Geometry>>,
+    query: Query<(Entity, &Shape), Added<Shape>>,
+) {
+    for (entity, shape) in query.iter() {
+        let Some(geometry) = geometries.get(&shape.geometry) else { continue; };
+        let mesh = debug_mesh(geometry);
 
         let mesh_entity = commands.spawn(DebugRenderMesh)
             .insert(MaterialMeshBundle {
@@ -273,6 +284,32 @@ fn create_debug_meshes(
     }
 }
 
+/// Marks shapes whose geometry was modified in place (e.g. heightfield edits),
+/// so their debug mesh is rebuilt.
+#[derive(Component)]
+pub struct DebugRenderStale;
+
+fn rebuild_stale_debug_meshes(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    geometries: Res<Assets<Geometry>>,
+    query: Query<(Entity, &Shape, Option<&Children>), With<DebugRenderStale>>,
+    debug_meshes: Query<&Handle<Mesh>, With<DebugRenderMesh>>,
+) {
+    for (entity, shape, children) in query.iter() {
+        commands.entity(entity).remove::<DebugRenderStale>();
+
+        let Some(geometry) = geometries.get(&shape.geometry) else { continue; };
+        let mesh_handles = children.into_iter().flatten().filter_map(|child| debug_meshes.get(*child).ok());
+
+        for handle in mesh_handles {
+            if let Some(mesh) = meshes.get_mut(handle) {
+                *mesh = debug_mesh(geometry);
+            }
+        }
+    }
+}
+
 fn toggle_debug_meshes_visibility(
     mut query: Query<&mut Visibility, With<DebugRenderMesh>>,
     settings: Res<DebugRenderSettings>,
@@ -1,7 +1,9 @@
+use bevy::asset::HandleId;
 use bevy::prelude::*;
 use derive_more::{Deref, DerefMut};
 use physx::cooking::{PxCooking, PxCookingParams};
 use physx::prelude::*;
+use physx::shape::Shape as _;
 use physx::traits::Class;
 use physx::vehicles::{
     VehicleDrivableSurfaceToTireFrictionPairs,
@@ -12,8 +14,14 @@ use physx::vehicles::{
 };
 use physx_sys::{
     PxBatchQuery,
+    PxMeshMidPhase,
+    PxMeshPreprocessingFlag,
+    PxMeshPreprocessingFlags,
+    PxMidphaseDesc_setToDefault_mut,
     PxFilterData,
+    PxHitFlag,
     PxHitFlags,
+    PxQueryFilterData_new,
     PxQueryHit,
     PxQueryHitType,
     PxRaycastHit,
@@ -28,19 +36,28 @@ use physx_sys::{
     PxScene_lockWrite_mut,
     PxScene_unlockRead_mut,
     PxScene_unlockWrite_mut,
+    PxSceneQueryExt_raycastSingle,
+    PxShape_getMaterialFromInternalFaceIndex,
     phys_PxVehicleSuspensionRaycasts,
     phys_PxVehicleSuspensionSweeps,
     phys_PxVehicleUpdates,
 };
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::Entry;
 use std::ptr::{null_mut, drop_in_place, null};
+use std::sync::{Arc, Mutex};
 
-use crate::{FoundationDescriptor, SceneDescriptor};
+use crate::assets::SurfaceTag;
+use crate::callbacks::{ContactEvent, ContactEventQueue, OnCollision};
+use crate::components::FilterData;
+use crate::errors::{PhysicsError, PhysicsErrorQueue};
+use crate::{CookingDescriptor, FoundationDescriptor, MeshMidphase, SceneDescriptor};
 
 use super::prelude::*;
 use super::prelude as bpx;
-use super::{PxShape, PxScene};
+use super::{PxRigidStatic, PxShape, PxScene};
 
-struct ErrorCallback;
+struct ErrorCallback(PhysicsErrorQueue);
 
 impl physx::physics::ErrorCallback for ErrorCallback {
     fn report_error(
@@ -50,12 +67,33 @@ impl physx::physics::ErrorCallback for ErrorCallback {
         file: &str,
         line: u32,
     ) {
+        use physx::foundation::ErrorCode;
+
         bevy::log::error!(target: "bevy_physx", "[{file:}:{line:}] {code:40}: {message:}");
+
+        // debug messages and performance warnings are only logged
+        let errors = ErrorCode::InvalidParameter | ErrorCode::InvalidOperation | ErrorCode::OutOfMemory
+            | ErrorCode::InternalError | ErrorCode::Abort;
+
+        if code.intersects(errors) {
+            self.0.lock().unwrap().push(PhysicsError::PhysX {
+                code,
+                message: message.to_owned(),
+                file: file.to_owned(),
+                line,
+            });
+        }
     }
 }
 
 #[derive(Resource, Deref, DerefMut)]
-pub struct Physics(PhysicsFoundation<physx::foundation::DefaultAllocator, PxShape>);
+pub struct Physics {
+    #[deref]
+    #[deref_mut]
+    physics: PhysicsFoundation<physx::foundation::DefaultAllocator, PxShape>,
+    errors: PhysicsErrorQueue,
+    strict: bool,
+}
 
 impl Physics {
     pub fn new(foundation_desc: &FoundationDescriptor) -> Self {
@@ -69,7 +107,8 @@ impl Physics {
         }
         builder.set_length_tolerance(foundation_desc.tolerances.length);
         builder.set_speed_tolerance(foundation_desc.tolerances.speed);
-        builder.with_error_callback(ErrorCallback);
+        let errors = PhysicsErrorQueue::default();
+        builder.with_error_callback(ErrorCallback(errors.clone()));
 
         let physics = builder.build();
 
@@ -95,7 +134,17 @@ impl Physics {
             );
         }
 
-        Self(physics)
+        Self { physics, errors, strict: foundation_desc.strict }
+    }
+
+    /// Errors reported by PhysX since the last call.
+    pub fn take_errors(&self) -> Vec<PhysicsError> {
+        std::mem::take(&mut *self.errors.lock().unwrap())
+    }
+
+    /// PhysX errors cause a panic instead of `PhysicsError` event, see `FoundationDescriptor::strict`.
+    pub fn is_strict(&self) -> bool {
+        self.strict
     }
 }
 
@@ -103,6 +152,33 @@ impl Physics {
 pub struct Scene {
     scene: SceneRwLock<Owner<PxScene>>,
     use_physx_lock: bool,
+    contact_events: ContactEventQueue,
+    // shared shapes only store the entity they were first created for as user data,
+    // so entities of shapes are tracked per actor they are attached to
+    shape_entities: HashMap<(usize, Entity), Entity>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RaycastHit {
+    /// entity of the shape hit
+    pub entity: Entity,
+    /// entity of the actor hit
+    pub actor: Entity,
+    pub position: Vec3,
+    pub normal: Vec3,
+    pub distance: f32,
+    pub face_index: u32,
+    /// surface tag of the material hit, per-face materials are taken into account
+    pub surface: Option<SurfaceTag>,
+}
+
+/// Entity an actor was created for.
+///
+/// # Safety
+/// `actor` must point to an actor created by this crate, all of them store `Entity` as user data.
+pub(crate) unsafe fn actor_entity(actor: *const physx_sys::PxRigidActor) -> Entity {
+    // user data lives in PxActor, so it reads the same through any actor type
+    *(*(actor as *const PxRigidStatic)).get_user_data()
 }
 
 impl Scene {
@@ -122,6 +198,8 @@ impl Scene {
             FilterShaderDescriptor::CallDefaultFirst(f) => FilterShaderDescriptor::CallDefaultFirst(f),
         };
 
+        let contact_events = ContactEventQueue::default();
+
         let scene = physics
             .create(physx::traits::descriptor::SceneDescriptor {
                 gravity: d.gravity.to_physx(),
@@ -154,6 +232,7 @@ impl Scene {
                 thread_count: d.thread_count,
                 gpu_max_num_partitions: d.gpu_max_num_partitions,
                 gpu_compute_version: d.gpu_compute_version,
+                on_collide: Some(OnCollision::new(contact_events.clone())),
                 ..physx::traits::descriptor::SceneDescriptor::new(())
             })
             .unwrap();
@@ -161,6 +240,70 @@ impl Scene {
         Self {
             scene: SceneRwLock::new(scene),
             use_physx_lock: d.flags.contains(SceneFlag::RequireRwLock),
+            contact_events,
+            shape_entities: default(),
+        }
+    }
+
+    pub(crate) fn take_contact_events(&self) -> Vec<ContactEvent> {
+        let events = std::mem::take(&mut *self.contact_events.lock().unwrap());
+
+        events.into_iter().map(|(mut event, shapes)| {
+            for (idx, shape) in shapes.into_iter().enumerate() {
+                if let Some(entity) = self.shape_entities.get(&(shape, event.actors[idx])) {
+                    event.shapes[idx] = *entity;
+                }
+            }
+            event
+        }).collect()
+    }
+
+    pub(crate) fn set_shape_entity(&mut self, shape: *const physx_sys::PxShape, actor: Entity, entity: Entity) {
+        self.shape_entities.insert((shape as usize, actor), entity);
+    }
+
+    pub(crate) fn forget_shape_entity(&mut self, shape: *const physx_sys::PxShape, actor: Entity) {
+        self.shape_entities.remove(&(shape as usize, actor));
+    }
+
+    /// Closest hit along the ray, `direction` doesn't need to be normalized.
+    pub fn raycast(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<RaycastHit> {
+        let scene = self.get();
+        let origin = origin.to_physx_sys();
+        let direction = direction.normalize_or_zero().to_physx_sys();
+
+        unsafe {
+            let mut hit: PxRaycastHit = std::mem::zeroed();
+            let filter_data = PxQueryFilterData_new();
+
+            let found = PxSceneQueryExt_raycastSingle(
+                scene.as_ptr(),
+                &origin,
+                &direction,
+                max_distance,
+                PxHitFlags { mBits: PxHitFlag::eDEFAULT as u16 },
+                &mut hit,
+                &filter_data,
+                null_mut(),
+                null(),
+            );
+
+            if !found || hit.shape.is_null() { return None; }
+
+            let shape = &*(hit.shape as *const PxShape);
+            let actor = actor_entity(hit.actor);
+            let entity = self.shape_entities.get(&(hit.shape as usize, actor)).copied()
+                .unwrap_or_else(|| *shape.get_user_data());
+
+            Some(RaycastHit {
+                entity,
+                actor,
+                position: hit.position.to_bevy(),
+                normal: hit.normal.to_bevy(),
+                distance: hit.distance,
+                face_index: hit.faceIndex,
+                surface: SurfaceTag::from_raw_material(PxShape_getMaterialFromInternalFaceIndex(hit.shape, hit.faceIndex)),
+            })
         }
     }
 
@@ -265,20 +408,159 @@ impl<'t, T> Drop for SceneRwLockWriteGuard<'t, T> {
     }
 }
 
-#[derive(Resource, Deref, DerefMut)]
-pub struct Cooking(Owner<PxCooking>);
+// shared with async cooking tasks, which may outlive the current frame
+#[derive(Resource, Deref, DerefMut, Clone)]
+pub struct Cooking {
+    #[deref]
+    #[deref_mut]
+    cooking: Arc<Owner<PxCooking>>,
+    params: CookingDescriptor,
+    // cookings created by `with_params`, shared by all clones
+    variants: Arc<Mutex<Vec<CookingVariant>>>,
+}
+
+type CookingVariant = (CookingDescriptor, Arc<Owner<PxCooking>>);
 
 impl Cooking {
-    pub fn new(physics: &mut Physics) -> Self {
-        let params = &PxCookingParams::new(&**physics).expect("failed to create cooking params");
-        let cooking = PxCooking::new(physics.foundation_mut(), params).expect("failed to create cooking");
-        Self(cooking)
+    pub fn new(physics: &mut Physics, desc: &CookingDescriptor) -> Self {
+        Self { cooking: Self::create(physics, desc), params: desc.clone(), variants: default() }
+    }
+
+    fn create(physics: &mut Physics, desc: &CookingDescriptor) -> Arc<Owner<PxCooking>> {
+        let mut params = PxCookingParams::new(&**physics).expect("failed to create cooking params");
+
+        let mut preprocess_flags = 0;
+        if let Some(tolerance) = desc.mesh_weld_tolerance {
+            preprocess_flags |= PxMeshPreprocessingFlag::eWELD_VERTICES;
+            params.obj.meshWeldTolerance = tolerance;
+        }
+        if !desc.mesh_cleaning {
+            preprocess_flags |= PxMeshPreprocessingFlag::eDISABLE_CLEAN_MESH;
+        }
+        params.obj.meshPreprocessParams = PxMeshPreprocessingFlags { mBits: preprocess_flags as _ };
+        params.obj.buildTriangleAdjacencies = desc.build_triangle_adjacencies;
+        params.obj.suppressTriangleMeshRemapTable = desc.suppress_triangle_mesh_remap_table;
+
+        let midphase = match desc.midphase {
+            MeshMidphase::Bvh33 => PxMeshMidPhase::eBVH33,
+            MeshMidphase::Bvh34 => PxMeshMidPhase::eBVH34,
+        };
+        unsafe { PxMidphaseDesc_setToDefault_mut(&mut params.obj.midphaseDesc, midphase); }
+
+        Arc::new(PxCooking::new(physics.foundation_mut(), &params).expect("failed to create cooking"))
+    }
+
+    pub fn params(&self) -> &CookingDescriptor {
+        &self.params
+    }
+
+    /// Returns cooking with different parameters, reusing this one if they are the same.
+    ///
+    /// Cooking created for each distinct set of parameters is kept, and reused by later calls.
+    pub fn with_params(&self, physics: &mut Physics, desc: &CookingDescriptor) -> Self {
+        if *desc == self.params { return self.clone(); }
+
+        let mut variants = self.variants.lock().unwrap();
+        let cooking = match variants.iter().find(|(params, _)| params == desc) {
+            Some((_, cooking)) => cooking.clone(),
+            None => {
+                let cooking = Self::create(physics, desc);
+                variants.push((desc.clone(), cooking.clone()));
+                cooking
+            }
+        };
+
+        Self { cooking, params: desc.clone(), variants: self.variants.clone() }
     }
 }
 
 #[derive(Resource, Deref, DerefMut, Default)]
 pub struct DefaultMaterial(Option<Handle<bpx::Material>>);
 
+/// Actors PhysX failed to create, they are not retried, and chunks are added without them.
+#[derive(Resource, Deref, DerefMut, Default)]
+pub(crate) struct FailedActors(HashSet<Entity>);
+
+/// Shapes PhysX failed to create, they are retried only after their `bpx::Shape` changes.
+#[derive(Resource, Deref, DerefMut, Default)]
+pub(crate) struct FailedShapes(HashSet<Entity>);
+
+#[derive(PartialEq, Eq, Hash)]
+pub(crate) struct SharedShapeKey {
+    geometry: HandleId,
+    materials: Vec<HandleId>,
+    query_filter_data: FilterData,
+    simulation_filter_data: FilterData,
+    // f32 bits, as floats are not hashable
+    local_pose: [u32; 7],
+    offsets: [Option<u32>; 2],
+    flags: [bool; 3],
+}
+
+impl SharedShapeKey {
+    pub(crate) fn new(
+        shape_cfg: &bpx::Shape,
+        materials: &[Handle<bpx::Material>],
+        settings: &ShapeSettings,
+        local_pose: &Transform,
+    ) -> Self {
+        let [tx, ty, tz] = local_pose.translation.to_array();
+        let [rx, ry, rz, rw] = local_pose.rotation.to_array();
+
+        Self {
+            geometry: shape_cfg.geometry.id(),
+            materials: materials.iter().map(|handle| handle.id()).collect(),
+            query_filter_data: shape_cfg.query_filter_data,
+            simulation_filter_data: shape_cfg.simulation_filter_data,
+            local_pose: [tx, ty, tz, rx, ry, rz, rw].map(f32::to_bits),
+            offsets: [settings.contact_offset.map(f32::to_bits), settings.rest_offset.map(f32::to_bits)],
+            flags: [settings.scene_query, settings.simulation, settings.visualization],
+        }
+    }
+}
+
+/// Non-exclusive shapes created for `ShapeSettings::shared`.
+///
+/// Each entry holds one reference to its shape, and is released
+/// once no `ShapeHandle` or actor references that shape anymore.
+#[derive(Resource, Default)]
+pub struct SharedShapes {
+    shapes: HashMap<SharedShapeKey, ShapeHandle>,
+}
+
+impl SharedShapes {
+    pub fn len(&self) -> usize {
+        self.shapes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shapes.is_empty()
+    }
+
+    pub(crate) fn get_or_create(
+        &mut self,
+        key: SharedShapeKey,
+        create: impl FnOnce() -> Result<ShapeHandle, PhysicsError>,
+    ) -> Result<ShapeHandle, PhysicsError> {
+        let shape = match self.shapes.entry(key) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(create()?),
+        };
+
+        Ok(shape.share())
+    }
+
+    /// Forgets shared shapes using given geometry or material, so shapes created afterwards
+    /// don't reuse them. Those shapes stay alive as long as any actor uses them.
+    pub(crate) fn forget_using(&mut self, asset: HandleId) {
+        self.shapes.retain(|key, _| key.geometry != asset && !key.materials.contains(&asset));
+    }
+
+    pub(crate) fn release_unused(&mut self) {
+        self.shapes.retain(|_, shape| shape.get_reference_count() > 1);
+    }
+}
+
 #[derive(Default, Debug, Clone, Copy)]
 pub enum VehicleSimulationMethod {
     #[default]
@@ -1,15 +1,57 @@
-use std::ptr::null;
+use std::collections::{HashMap, HashSet};
+use std::ptr::{null, null_mut};
+use bevy::asset::{HandleId, LoadState};
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
+use bevy::scene::SceneInstance;
+use physx::actor::ActorType;
 use physx::prelude::*;
 use physx::scene::Scene;
 use physx::traits::Class;
 use physx_sys::{
+    PxActor_getAggregate,
+    PxActor_getScene,
+    PxActor_getType,
+    PxAggregate_addActor_mut,
+    PxAggregate_addArticulation_mut,
+    PxAggregate_getActors,
+    PxAggregate_getNbActors,
+    PxAggregate_removeActor_mut,
+    PxAggregate_removeArticulation_mut,
+    PxArticulationJointBase_setChildPose_mut,
+    PxArticulationJointBase_setParentPose_mut,
+    PxArticulationBase_getAggregate,
+    PxArticulationLink_getArticulation,
+    PxArticulationLink_getInboundJoint,
     PxFilterData,
+    PxPhysics_createPruningStructure_mut,
+    PxPruningStructure_release_mut,
+    PxRigidActor_attachShape_mut,
+    PxRigidActor_detachShape_mut,
+    PxRigidActor_getNbShapes,
+    PxRigidActor_getShapes,
     PxRigidBodyExt_setMassAndUpdateInertia_mut_1,
+    PxRigidBodyExt_updateMassAndInertia_mut,
     PxRigidBodyExt_updateMassAndInertia_mut_1,
+    PxRigidBody_getCMassLocalPose,
+    PxRigidBody_getMass,
+    PxRigidBody_getMassSpaceInertiaTensor,
+    PxRigidBody_setCMassLocalPose_mut,
+    PxRigidBody_setMassSpaceInertiaTensor_mut,
+    PxRigidBody_setMass_mut,
     PxScene_addActor_mut,
+    PxScene_addActors_mut,
+    PxScene_addActors_mut_1,
+    PxScene_addAggregate_mut,
+    PxScene_addArticulation_mut,
+    PxScene_removeActor_mut,
+    PxScene_removeAggregate_mut,
+    PxScene_removeActors_mut,
     PxShape_getLocalPose,
+    PxShape_isExclusive,
+    PxShape_setGeometry_mut,
     PxShape_setLocalPose_mut,
+    PxShape_setMaterials_mut,
     PxShape_setQueryFilterData_mut,
     PxShape_setSimulationFilterData_mut,
 };
@@ -17,17 +59,54 @@ use physx_sys::{
 use crate::resources::VehicleSimulation;
 
 use super::prelude as bpx;
-use super::{prelude::*, PxRigidDynamic, PxRigidStatic};
-use super::components::{RigidDynamicHandle, RigidStaticHandle};
-use super::resources::DefaultMaterial;
+use super::{prelude::*, PxAggregate, PxArticulationLink, PxArticulationReducedCoordinate, PxRigidDynamic, PxRigidStatic};
+use super::components::{
+    RigidDynamicHandle, RigidStaticHandle, StaticChunkHandle,
+};
+use super::resources::{DefaultMaterial, FailedActors, FailedShapes, SharedShapeKey, SharedShapes};
+use super::bvh::Bvh;
+use super::cooking::{CookingError, FailedCookedFiles};
+use super::errors::PhysicsError;
+use super::render::DebugRenderStale;
 
 type ActorsQuery<'world, 'state, 'a> = Query<'world, 'state,
-    (Entity, &'a bpx::RigidBody, &'a GlobalTransform, Option<&'a MassProperties>, Option<&'a Velocity>, Option<&'a mut Vehicle>),
-    (Without<RigidDynamicHandle>, Without<RigidStaticHandle>, Without<VehicleHandle>)
+    (
+        Entity, &'a bpx::RigidBody, &'a GlobalTransform, Option<&'a MassProperties>, Option<&'a Velocity>,
+        Option<&'a mut Vehicle>, Option<&'a DescribedVehicle>,
+    ),
+    (
+        Without<RigidDynamicHandle>, Without<RigidStaticHandle>, Without<VehicleHandle>, Without<ArticulationLinkHandle>,
+        Without<AsyncSceneCollider>, Without<ConvexDecompositionCollider>,
+    )
+>;
+
+type ArticulationLinksQuery<'world, 'state, 'a> = Query<'world, 'state,
+    (Option<&'a bpx::RigidBody>, Option<&'a Children>, &'a GlobalTransform, Option<&'a bpx::ArticulationJoint>, Option<&'a MassProperties>),
+    Without<ArticulationLinkHandle>
+>;
+
+type AggregatesQuery<'world, 'state, 'a> = Query<'world, 'state,
+    Option<&'a mut AggregateHandle>,
+    With<bpx::Aggregate>
+>;
+
+type StaticChunksQuery<'world, 'state, 'a> = Query<'world, 'state,
+    Option<&'a StaticChunkHandle>,
+    With<bpx::StaticChunk>
+>;
+
+type ChunkActorsQuery<'world, 'state, 'a> = Query<'world, 'state,
+    (Entity, &'a bpx::RigidBody, Option<&'a mut RigidStaticHandle>)
+>;
+
+// nested aggregates and chunks take care of their own actors
+type ChunkBoundariesQuery<'world, 'state> = Query<'world, 'state,
+    (),
+    Or<(With<bpx::Aggregate>, With<bpx::StaticChunk>)>
 >;
 
 type ShapesQuery<'world, 'state, 'a> = Query<'world, 'state,
-    (Entity, Option<&'a bpx::RigidBody>, Option<&'a Children>, Option<&'a bpx::Shape>, Option<&'a GlobalTransform>),
+    (Entity, Option<&'a bpx::RigidBody>, Option<&'a Children>, Option<&'a bpx::Shape>, Option<&'a GlobalTransform>, Option<&'a ShapeSettings>),
     (Without<ShapeHandle>, Without<RigidDynamicHandle>, Without<RigidStaticHandle>)
 >;
 
@@ -82,15 +161,15 @@ pub fn scene_simulate(
 fn find_nested_shapes(
     entity: Entity,
     query: &ShapesQuery,
-    result: &mut Vec<(Entity, bpx::Shape, Option<GlobalTransform>)>,
+    result: &mut Vec<(Entity, bpx::Shape, Option<GlobalTransform>, ShapeSettings)>,
     level: u32,
 ) {
-    if let Ok((entity, bpactor, children, shape_cfg, gtransform)) = query.get(entity) {
+    if let Ok((entity, bpactor, children, shape_cfg, gtransform, settings)) = query.get(entity) {
         // if we find BPxActor which is not the current one (level > 0), don't add its shapes
         if level > 0 && bpactor.is_some() { return; }
 
         if let Some(shape_cfg) = shape_cfg {
-            result.push((entity, shape_cfg.clone(), gtransform.copied()));
+            result.push((entity, shape_cfg.clone(), gtransform.copied(), settings.copied().unwrap_or_default()));
         }
 
         if let Some(children) = children {
@@ -101,6 +180,166 @@ fn find_nested_shapes(
     }
 }
 
+/// Checks that assets used by shapes are available. Shapes can refer to geometries and materials
+/// that are still loading from disk or cooking asynchronously, in which case actor is created later.
+/// Assets that are not going to arrive are reported as `PhysicsError` once per entity.
+#[derive(SystemParam)]
+pub struct ShapeAssets<'w, 's> {
+    asset_server: Res<'w, AssetServer>,
+    async_cooking: Res<'w, AsyncCooking>,
+    failed_files: Res<'w, FailedCookedFiles>,
+    failed_shapes: ResMut<'w, FailedShapes>,
+    errors: EventWriter<'w, 's, PhysicsError>,
+    reported: Local<'s, HashSet<(Entity, HandleId)>>,
+}
+
+impl ShapeAssets<'_, '_> {
+    fn shape_ready(
+        &mut self,
+        entity: Entity,
+        shape_cfg: &bpx::Shape,
+        geometries: &Assets<bpx::Geometry>,
+        materials: &Assets<bpx::Material>,
+    ) -> bool {
+        let Some(geometry) = geometries.get(&shape_cfg.geometry) else {
+            self.check_unavailable(entity, shape_cfg.geometry.id());
+            return false;
+        };
+
+        let mut ready = true;
+
+        for handle in shape_cfg.material_handles(geometry) {
+            // default handle stands for the default material
+            if handle.id() == HandleId::default::<bpx::Material>() || materials.contains(&handle) { continue; }
+
+            self.check_unavailable(entity, handle.id());
+            ready = false;
+        }
+
+        ready
+    }
+
+    fn asset_ready<T: bevy::asset::Asset>(&mut self, entity: Entity, handle: &Handle<T>, assets: &Assets<T>) -> bool {
+        if assets.contains(handle) { return true; }
+
+        self.check_unavailable(entity, handle.id());
+        false
+    }
+
+    // every shape is checked, so that all unavailable assets get reported at once
+    fn nested_shapes_ready(
+        &mut self,
+        entity: Entity,
+        query: &ShapesQuery,
+        geometries: &Assets<bpx::Geometry>,
+        materials: &Assets<bpx::Material>,
+    ) -> bool {
+        let mut found_shapes = vec![];
+        find_nested_shapes(entity, query, &mut found_shapes, 0);
+
+        found_shapes.iter().fold(true, |ready, (entity, shape_cfg, _, _)| {
+            self.shape_ready(*entity, shape_cfg, geometries, materials) && ready
+        })
+    }
+
+    fn subtree_shapes_ready(
+        &mut self,
+        entity: Entity,
+        query: &ShapesQuery,
+        geometries: &Assets<bpx::Geometry>,
+        materials: &Assets<bpx::Material>,
+    ) -> bool {
+        let Ok((_, _, children, shape_cfg, _, _)) = query.get(entity) else { return true; };
+
+        let mut ready = shape_cfg.map_or(true, |shape_cfg| self.shape_ready(entity, shape_cfg, geometries, materials));
+
+        for child in children.into_iter().flatten() {
+            ready &= self.subtree_shapes_ready(*child, query, geometries, materials);
+        }
+
+        ready
+    }
+
+    fn check_unavailable(&mut self, entity: Entity, asset: HandleId) {
+        // geometry that failed to cook never gets loaded, it is reported instead of waiting for it
+        let cooking_error = self.async_cooking.error(asset).or_else(|| self.failed_files.get(&asset).copied());
+        let error = if let Some(error) = cooking_error {
+            PhysicsError::Cooking { entity, asset: Some(asset), error }
+        } else {
+            match self.asset_server.get_load_state(asset) {
+                LoadState::Failed => PhysicsError::AssetFailed { entity, asset },
+                LoadState::NotLoaded | LoadState::Unloaded if !self.async_cooking.is_pending(asset) => {
+                    PhysicsError::AssetMissing { entity, asset }
+                }
+                _ => return,
+            }
+        };
+
+        if self.reported.insert((entity, asset)) {
+            self.report(error);
+        }
+    }
+
+    // shapes PhysX rejected are not created again until their `bpx::Shape` changes
+    fn report_shape(&mut self, entity: Entity, error: PhysicsError) {
+        self.failed_shapes.insert(entity);
+        self.report(error);
+    }
+
+    // systems checking shape assets send all of their errors through here,
+    // as only one `EventWriter` of a kind is allowed per system
+    fn report(&mut self, error: PhysicsError) {
+        bevy::log::warn!("{error}");
+        self.errors.send(error);
+    }
+}
+
+const AGGREGATE_FULL: &str = "aggregate is full, actor is added to the scene on its own";
+
+fn report_error(errors: &mut EventWriter<PhysicsError>, error: PhysicsError) {
+    bevy::log::warn!("{error}");
+    errors.send(error);
+}
+
+// descriptor provides mass properties for vehicles that don't have their own
+fn vehicle_mass_properties(
+    mass_props: Option<&MassProperties>,
+    described: Option<&DescribedVehicle>,
+    descriptors: &Assets<bpx::VehicleDescriptor>,
+) -> Option<MassProperties> {
+    mass_props.cloned().or_else(|| {
+        described
+            .and_then(|described| descriptors.get(&described.descriptor))
+            .map(|descriptor| descriptor.mass_properties())
+    })
+}
+
+// `bpx::Vehicle` takes precedence, vehicle built from a descriptor is only needed to create the handle
+fn create_vehicle_handle(
+    vehicle: Option<&mut Vehicle>,
+    described: Option<&DescribedVehicle>,
+    descriptors: &Assets<bpx::VehicleDescriptor>,
+    physics: &mut bpx::Physics,
+    actor: &mut PxRigidDynamic,
+) -> Option<Result<VehicleHandle, PhysicsError>> {
+    let mut built;
+
+    let vehicle = match (vehicle, described) {
+        (Some(vehicle), _) => vehicle,
+        (None, Some(described)) => {
+            let descriptor = descriptors.get(&described.descriptor)?;
+            built = match descriptor.build(&described.wheels, actor) {
+                Ok(vehicle) => vehicle,
+                Err(error) => return Some(Err(error)),
+            };
+            &mut built
+        }
+        (None, None) => return None,
+    };
+
+    Some(VehicleHandle::new(vehicle, physics, actor))
+}
+
 fn find_and_attach_nested_shapes<T: RigidActor<Shape = crate::PxShape>>(
     commands: &mut Commands,
     entity: Entity,
@@ -111,37 +350,88 @@ fn find_and_attach_nested_shapes<T: RigidActor<Shape = crate::PxShape>>(
     query: &ShapesQuery,
     actor_transform: &GlobalTransform,
     default_material: &mut ResMut<DefaultMaterial>,
+    shared_shapes: &mut ResMut<SharedShapes>,
+    shape_assets: &mut ShapeAssets,
 ) {
     let mut found_shapes = vec![];
     find_nested_shapes(entity, query, &mut found_shapes, 0);
 
-    for (entity, shape_cfg, gtransform) in found_shapes {
-        let bpx::Shape { geometry, material, query_filter_data, simulation_filter_data } = shape_cfg;
-        let geometry = geometries.get_mut(&geometry).expect("geometry not found for BPxGeometry");
-        let mut material = materials.get_mut(&material);
-
-        let relative_transform = gtransform.map(|gtransform| {
-            let xform = actor_transform.affine().inverse() * gtransform.affine();
-            Transform::from_matrix(xform.into())
-        }).unwrap_or_default();
+    for (entity, shape_cfg, gtransform, settings) in found_shapes {
+        let relative_transform = gtransform
+            .map(|gtransform| relative_shape_transform(actor_transform, &gtransform))
+            .unwrap_or_default();
 
-        if material.is_none() {
-            // fetch default material if it exists, create if it doesn't
-            if default_material.is_none() {
-                let material = materials.add(physics.create_material(0.5, 0.5, 0.6, ()).unwrap().into());
-                ***default_material = Some(material);
+        // actor is created without shapes PhysX rejected
+        let mut shape_handle = match create_shape(
+            physics,
+            geometries,
+            materials,
+            default_material,
+            shared_shapes,
+            entity,
+            &shape_cfg,
+            &settings,
+            &relative_transform,
+        ) {
+            Ok(shape_handle) => shape_handle,
+            Err(error) => {
+                shape_assets.report_shape(entity, error);
+                continue;
             }
+        };
+
+        actor.attach_shape(&mut shape_handle);
+
+        commands.entity(entity)
+            .insert(shape_handle);
+    }
+}
+
+fn relative_shape_transform(actor_transform: &GlobalTransform, shape_transform: &GlobalTransform) -> Transform {
+    let xform = actor_transform.affine().inverse() * shape_transform.affine();
+    Transform::from_matrix(xform.into())
+}
+
+fn create_shape(
+    physics: &mut bpx::Physics,
+    geometries: &mut ResMut<Assets<bpx::Geometry>>,
+    materials: &mut ResMut<Assets<bpx::Material>>,
+    default_material: &mut ResMut<DefaultMaterial>,
+    shared_shapes: &mut ResMut<SharedShapes>,
+    entity: Entity,
+    shape_cfg: &bpx::Shape,
+    settings: &ShapeSettings,
+    relative_transform: &Transform,
+) -> Result<ShapeHandle, PhysicsError> {
+    // availability is checked with ShapeAssets before shapes are created
+    let geometry = geometries.get_mut(&shape_cfg.geometry).expect("geometry not found for BPxGeometry");
+    let material_handles = shape_cfg.material_handles(geometry);
+    let bpx::Shape { query_filter_data, simulation_filter_data, .. } = *shape_cfg;
 
-            material = materials.get_mut(default_material.as_ref().as_ref().unwrap());
+    if material_handles.iter().any(|handle| !materials.contains(handle)) {
+        // fetch default material if it exists, create if it doesn't
+        if default_material.is_none() {
+            let Some(material) = bpx::Material::from_descriptor(physics, &default()) else {
+                return Err(PhysicsError::InvalidDescriptor { entity, message: "failed to create default material" });
+            };
+            ***default_material = Some(materials.add(material));
         }
+    }
 
-        let material = material.unwrap(); // we create default material above, so we guarantee it exists
-        let mut shape_handle = ShapeHandle::create_shape(physics, geometry, material, entity);
+    let shape_materials = material_handles.iter().map(|handle| {
+        // we create default material above, so we guarantee it exists
+        materials.get(handle).or_else(|| materials.get(default_material.as_ref().as_ref().unwrap())).unwrap()
+    }).collect::<Vec<_>>();
+
+    let local_pose = geometry.shape_local_pose(relative_transform);
+
+    let create = || {
+        let mut shape_handle = ShapeHandle::create_shape_with_settings(physics, geometry, &shape_materials, settings, entity)?;
 
         unsafe {
             PxShape_setLocalPose_mut(
                 shape_handle.as_mut_ptr(),
-                relative_transform.to_physx().as_ptr(),
+                local_pose.to_physx().as_ptr(),
             );
 
             if query_filter_data != default() {
@@ -155,10 +445,343 @@ fn find_and_attach_nested_shapes<T: RigidActor<Shape = crate::PxShape>>(
             }
         }
 
-        actor.attach_shape(&mut shape_handle);
+        Ok(shape_handle)
+    };
+
+    if settings.shared {
+        let key = SharedShapeKey::new(shape_cfg, &material_handles, settings, &local_pose);
+        shared_shapes.get_or_create(key, create)
+    } else {
+        create()
+    }
+}
+
+// densities of simulation shapes in the order PhysX expects them,
+// `None` if no shape overrides the default density
+fn shape_densities<T: RigidActor<Shape = crate::PxShape>>(
+    actor: &T,
+    default_density: f32,
+    collider_densities: &Query<&ColliderDensity>,
+) -> Option<Vec<f32>> {
+    use physx::shape::Shape;
+
+    let mut overridden = false;
+
+    let densities = actor.get_shapes().into_iter()
+        .filter(|shape| shape.get_flags().contains(ShapeFlag::SimulationShape))
+        .map(|shape| match collider_densities.get(*shape.get_user_data()) {
+            Ok(density) => { overridden = true; **density }
+            Err(_) => default_density,
+        })
+        .collect();
+
+    overridden.then_some(densities)
+}
+
+fn update_mass_properties<T: RigidActor<Shape = crate::PxShape> + Class<physx_sys::PxRigidBody>>(
+    actor: &mut T,
+    mass_props: Option<&MassProperties>,
+    collider_densities: &Query<&ColliderDensity>,
+) -> ComputedMassProperties {
+    match mass_props {
+        Some(MassProperties::Density { density, center }) => unsafe {
+            if let Some(densities) = shape_densities(actor, *density, collider_densities) {
+                PxRigidBodyExt_updateMassAndInertia_mut(
+                    actor.as_mut_ptr(),
+                    densities.as_ptr(),
+                    densities.len() as u32,
+                    center.to_physx_sys().as_ptr(),
+                    false
+                );
+            } else {
+                PxRigidBodyExt_updateMassAndInertia_mut_1(
+                    actor.as_mut_ptr(),
+                    *density,
+                    center.to_physx_sys().as_ptr(),
+                    false
+                );
+            }
+        }
+        Some(MassProperties::Mass { mass, center }) => unsafe {
+            if let Some(densities) = shape_densities(actor, 1., collider_densities) {
+                // distribute mass according to relative densities, then scale it to the total
+                PxRigidBodyExt_updateMassAndInertia_mut(
+                    actor.as_mut_ptr(),
+                    densities.as_ptr(),
+                    densities.len() as u32,
+                    center.to_physx_sys().as_ptr(),
+                    false
+                );
+
+                let scale = *mass / PxRigidBody_getMass(actor.as_ptr()).max(f32::EPSILON);
+                let inertia = PxRigidBody_getMassSpaceInertiaTensor(actor.as_ptr()).to_bevy() * scale;
+                PxRigidBody_setMass_mut(actor.as_mut_ptr(), *mass);
+                PxRigidBody_setMassSpaceInertiaTensor_mut(actor.as_mut_ptr(), inertia.to_physx_sys().as_ptr());
+            } else {
+                PxRigidBodyExt_setMassAndUpdateInertia_mut_1(
+                    actor.as_mut_ptr(),
+                    *mass,
+                    center.to_physx_sys().as_ptr(),
+                    false
+                );
+            }
+        }
+        Some(MassProperties::Explicit { mass, center, inertia, principal_axes }) => unsafe {
+            let pose = Transform::from_translation(*center).with_rotation(*principal_axes);
+            PxRigidBody_setMass_mut(actor.as_mut_ptr(), *mass);
+            PxRigidBody_setCMassLocalPose_mut(actor.as_mut_ptr(), pose.to_physx().as_ptr());
+            PxRigidBody_setMassSpaceInertiaTensor_mut(actor.as_mut_ptr(), inertia.to_physx_sys().as_ptr());
+        }
+        None => unsafe {
+            if let Some(densities) = shape_densities(actor, 1., collider_densities) {
+                PxRigidBodyExt_updateMassAndInertia_mut(
+                    actor.as_mut_ptr(),
+                    densities.as_ptr(),
+                    densities.len() as u32,
+                    null(),
+                    false
+                );
+            }
+        }
+    }
+
+    unsafe {
+        let pose = PxRigidBody_getCMassLocalPose(actor.as_ptr()).to_bevy();
+
+        ComputedMassProperties {
+            mass: PxRigidBody_getMass(actor.as_ptr()),
+            center: pose.translation,
+            inertia: PxRigidBody_getMassSpaceInertiaTensor(actor.as_ptr()).to_bevy(),
+            principal_axes: pose.rotation,
+        }
+    }
+}
+
+// nearest entity matching `found`, starting from `entity` itself and walking up its parents
+fn find_ancestor(entity: Entity, parents: &Query<&Parent>, found: impl Fn(Entity) -> bool) -> Option<Entity> {
+    let mut current = entity;
+
+    loop {
+        if found(current) { return Some(current); }
+        current = **parents.get(current).ok()?;
+    }
+}
+
+fn find_aggregate(entity: Entity, parents: &Query<&Parent>, aggregates: &AggregatesQuery) -> Option<Entity> {
+    find_ancestor(entity, parents, |current| aggregates.contains(current))
+}
+
+// actors nested inside an aggregate have to wait until aggregate itself is created
+fn aggregate_is_pending(aggregate: Option<Entity>, aggregates: &AggregatesQuery) -> bool {
+    aggregate.map_or(false, |aggregate| !matches!(aggregates.get(aggregate), Ok(Some(_))))
+}
+
+// actors of a chunk that is not in the scene yet are added together with it,
+// unless they are inside an aggregate
+fn chunk_is_pending(entity: Entity, aggregate: *mut physx_sys::PxAggregate, parents: &Query<&Parent>, chunks: &StaticChunksQuery) -> bool {
+    aggregate.is_null() && find_ancestor(entity, parents, |current| chunks.contains(current))
+        .map_or(false, |chunk| !matches!(chunks.get(chunk), Ok(Some(_))))
+}
+
+#[derive(Default)]
+struct ChunkActors {
+    actors: Vec<*mut physx_sys::PxRigidActor>,
+    // actors PhysX failed to create, chunk is added without them
+    failed: Vec<Entity>,
+}
+
+// static actors nested inside a chunk are added to the scene together with it,
+// returns false if some of them are not created yet
+fn collect_chunk_actors(
+    entity: Entity,
+    chunk: Entity,
+    in_scene: bool,
+    children: &Query<&Children>,
+    actors: &mut ChunkActorsQuery,
+    boundaries: &ChunkBoundariesQuery,
+    failed_actors: &FailedActors,
+    scene: &mut bpx::Scene,
+    result: &mut ChunkActors,
+) -> bool {
+    if entity != chunk && boundaries.contains(entity) { return true; }
+
+    if let Ok((_, bpx::RigidBody::Static, handle)) = actors.get_mut(entity) {
+        if let Some(mut handle) = handle {
+            let actor : *mut physx_sys::PxRigidActor = handle.get_mut(scene).as_mut_ptr();
+
+            let actor_in_scene = unsafe { !PxActor_getScene(actor as *const physx_sys::PxActor).is_null() };
+            let in_aggregate = unsafe { !PxActor_getAggregate(actor as *const physx_sys::PxActor).is_null() };
+
+            if actor_in_scene == in_scene && !in_aggregate {
+                result.actors.push(actor);
+            }
+        } else if failed_actors.contains(&entity) {
+            result.failed.push(entity);
+        } else {
+            return false;
+        }
+    }
+
+    let mut ready = true;
+
+    if let Ok(nested) = children.get(entity) {
+        for child in nested.iter().copied() {
+            ready &= collect_chunk_actors(child, chunk, in_scene, children, actors, boundaries, failed_actors, scene, result);
+        }
+    }
+
+    ready
+}
+
+// true if the nearest chunk of an actor was removed before its actors were added,
+// and there's no other pending chunk above it to add them instead
+fn waits_for_removed_chunk(
+    entity: Entity,
+    parents: &Query<&Parent>,
+    chunks: &StaticChunksQuery,
+    removed: &HashSet<Entity>,
+) -> bool {
+    let mut current = entity;
+    let mut found_removed = false;
+
+    loop {
+        found_removed |= removed.contains(&current);
+
+        if let Ok(handle) = chunks.get(current) {
+            return found_removed && handle.is_some();
+        }
+
+        let Ok(parent) = parents.get(current) else { return found_removed; };
+        current = **parent;
+    }
+}
+
+// adds actor to its aggregate, or to the scene if there's none, with a BVH over its shapes if requested,
+// returns false if aggregate is full, actor is added to the scene on its own in that case
+fn add_actor_to_scene(
+    physics: &mut bpx::Physics,
+    scene: &mut bpx::Scene,
+    cooking: Option<&bpx::Cooking>,
+    actor: *mut physx_sys::PxRigidActor,
+    aggregate: *mut physx_sys::PxAggregate,
+    with_bvh: bool,
+) -> bool {
+    // structure is copied by the scene, so it's released right after
+    let bvh = cooking
+        .filter(|_| with_bvh)
+        .and_then(|cooking| Bvh::from_actor_shapes(physics, cooking, actor));
+    let bvh = bvh.as_ref().map_or(null(), |bvh| bvh.as_ptr());
+
+    let mut scene = scene.get_mut();
+
+    unsafe {
+        let added = !aggregate.is_null() && PxAggregate_addActor_mut(aggregate, actor as *mut physx_sys::PxActor, bvh);
+
+        if !added {
+            PxScene_addActor_mut(scene.as_mut_ptr(), actor as *mut physx_sys::PxActor, bvh);
+        }
+
+        added || aggregate.is_null()
+    }
+}
+
+pub fn send_contact_events(
+    scene: Res<bpx::Scene>,
+    mut events: EventWriter<ContactEvent>,
+) {
+    events.send_batch(scene.take_contact_events());
+}
+
+pub fn send_physics_errors(
+    physics: Res<bpx::Physics>,
+    mut events: EventWriter<PhysicsError>,
+) {
+    for error in physics.take_errors() {
+        assert!(!physics.is_strict(), "PhysX error in strict mode: {error}");
+        events.send(error);
+    }
+}
+
+pub fn create_aggregates(
+    mut commands: Commands,
+    mut physics: ResMut<bpx::Physics>,
+    new_aggregates: Query<(Entity, &bpx::Aggregate), Without<AggregateHandle>>,
+    mut errors: EventWriter<PhysicsError>,
+) {
+    for (entity, aggregate_cfg) in new_aggregates.iter() {
+        let Some(aggregate) : Option<Owner<PxAggregate>> = physics
+            .create_aggregate(aggregate_cfg.max_actors, aggregate_cfg.self_collision) else {
+            // nested actors would wait for this aggregate forever, so they are added on their own instead
+            report_error(&mut errors, PhysicsError::InvalidDescriptor { entity, message: "failed to create aggregate" });
+            commands.entity(entity).remove::<bpx::Aggregate>();
+            continue;
+        };
 
         commands.entity(entity)
-            .insert(shape_handle);
+            .insert(AggregateHandle::new(aggregate));
+    }
+}
+
+/// Takes aggregate out of the scene when `bpx::Aggregate` component is removed,
+/// its actors stay in the scene on their own.
+///
+/// Despawned aggregates are removed from the scene together with their actors
+/// when `AggregateHandle` is dropped.
+pub fn remove_aggregates(
+    mut commands: Commands,
+    mut scene: ResMut<bpx::Scene>,
+    removed: RemovedComponents<bpx::Aggregate>,
+    mut aggregates: Query<&mut AggregateHandle>,
+) {
+    for entity in removed.iter() {
+        let Ok(mut aggregate) = aggregates.get_mut(entity) else { continue; };
+
+        if aggregate.in_scene {
+            let aggregate_ptr : *mut physx_sys::PxAggregate = aggregate.get_mut(&mut scene).as_mut_ptr();
+            let mut scene = scene.get_mut();
+
+            unsafe {
+                // removed actors (and articulations) are put back into the scene one by one
+                let mut actors = vec![null_mut(); PxAggregate_getNbActors(aggregate_ptr) as usize];
+                let count = PxAggregate_getActors(aggregate_ptr, actors.as_mut_ptr(), actors.len() as u32, 0);
+                actors.truncate(count as usize);
+
+                for actor in actors {
+                    if matches!(ActorType::from(PxActor_getType(actor)), ActorType::ArticulationLink) {
+                        let articulation = PxArticulationLink_getArticulation(actor as *const physx_sys::PxArticulationLink);
+                        // every link of an articulation is listed, only the first one removes it
+                        if PxArticulationBase_getAggregate(articulation).is_null() { continue; }
+                        PxAggregate_removeArticulation_mut(aggregate_ptr, articulation);
+                    } else {
+                        PxAggregate_removeActor_mut(aggregate_ptr, actor);
+                    }
+                }
+
+                PxScene_removeAggregate_mut(scene.as_mut_ptr(), aggregate_ptr, true);
+            }
+
+            aggregate.in_scene = false;
+        }
+
+        commands.entity(entity).remove::<AggregateHandle>();
+    }
+}
+
+pub fn insert_aggregates(
+    mut scene: ResMut<bpx::Scene>,
+    mut aggregates: Query<&mut AggregateHandle>,
+) {
+    for mut aggregate in aggregates.iter_mut() {
+        if aggregate.in_scene { continue; }
+
+        // this runs after actors are created, so everything spawned together
+        // with the aggregate enters the scene at once
+        let aggregate_ptr : *mut physx_sys::PxAggregate = aggregate.get_mut(&mut scene).as_mut_ptr();
+        unsafe {
+            PxScene_addAggregate_mut(scene.get_mut().as_mut_ptr(), aggregate_ptr);
+        }
+
+        aggregate.in_scene = true;
     }
 }
 
@@ -171,13 +794,48 @@ pub fn create_dynamic_actors(
     mut geometries: ResMut<Assets<bpx::Geometry>>,
     mut materials: ResMut<Assets<bpx::Material>>,
     mut default_material: ResMut<DefaultMaterial>,
+    mut shared_shapes: ResMut<SharedShapes>,
+    parents: Query<&Parent>,
+    mut aggregates: AggregatesQuery,
+    static_chunks: StaticChunksQuery,
+    collider_densities: Query<&ColliderDensity>,
+    cooking: Option<Res<bpx::Cooking>>,
+    actor_bvhs: Query<(), With<ActorBvh>>,
+    mut shape_assets: ShapeAssets,
+    pending: Query<(), With<PhysicsPending>>,
+    mut failed: ResMut<FailedActors>,
+    vehicle_descriptors: Res<Assets<bpx::VehicleDescriptor>>,
 ) {
-    for (entity, actor_cfg, actor_transform, mass_props, velocity, vehicle) in new_actors.iter_mut() {
-        let mut scene = scene.get_mut();
+    for (entity, actor_cfg, actor_transform, mass_props, velocity, mut vehicle, described) in new_actors.iter_mut() {
+        // PhysX already reported why, retrying won't help
+        if failed.contains(&entity) { continue; }
+
+        let descriptor_ready = described.map_or(true, |described| {
+            shape_assets.asset_ready(entity, &described.descriptor, &vehicle_descriptors)
+        });
+
+        if !shape_assets.nested_shapes_ready(entity, &query, &geometries, &materials) || !descriptor_ready {
+            if !pending.contains(entity) { commands.entity(entity).insert(PhysicsPending); }
+            continue;
+        }
+
+        let aggregate = find_aggregate(entity, &parents, &aggregates);
+        if aggregate_is_pending(aggregate, &aggregates) { continue; }
+        let aggregate = aggregate
+            .and_then(|aggregate| aggregates.get_mut(aggregate).ok().flatten())
+            .map_or(null_mut(), |mut aggregate| aggregate.get_mut(&mut scene).as_mut_ptr());
 
         match actor_cfg {
-            bpx::RigidBody::Dynamic => {
-                let mut actor : Owner<PxRigidDynamic> = physics.create_dynamic(&actor_transform.to_physx(), entity).unwrap();
+            bpx::RigidBody::Dynamic | bpx::RigidBody::Kinematic => {
+                let Some(mut actor) : Option<Owner<PxRigidDynamic>> = physics.create_dynamic(&actor_transform.to_physx(), entity) else {
+                    shape_assets.report(PhysicsError::InvalidDescriptor { entity, message: "failed to create dynamic actor" });
+                    failed.insert(entity);
+                    continue;
+                };
+
+                if *actor_cfg == bpx::RigidBody::Kinematic {
+                    actor.set_rigid_body_flag(RigidBodyFlag::Kinematic, true);
+                }
 
                 find_and_attach_nested_shapes(
                     &mut commands,
@@ -189,49 +847,42 @@ pub fn create_dynamic_actors(
                     &query,
                     actor_transform,
                     &mut default_material,
+                    &mut shared_shapes,
+                    &mut shape_assets,
                 );
 
-                match mass_props {
-                    Some(MassProperties::Density { density, center }) => unsafe {
-                        PxRigidBodyExt_updateMassAndInertia_mut_1(
-                            actor.as_mut_ptr(),
-                            *density,
-                            center.to_physx_sys().as_ptr(),
-                            false
-                        );
-                    }
-                    Some(MassProperties::Mass { mass, center }) => unsafe {
-                        PxRigidBodyExt_setMassAndUpdateInertia_mut_1(
-                            actor.as_mut_ptr(),
-                            *mass,
-                            center.to_physx_sys().as_ptr(),
-                            false
-                        );
-                    }
-                    None => {}
-                }
+                let mass_props = vehicle_mass_properties(mass_props, described, &vehicle_descriptors);
+                let computed_mass = update_mass_properties(actor.as_mut(), mass_props.as_ref(), &collider_densities);
 
-                if let Some(mut vehicle) = vehicle {
-                    commands.entity(entity)
-                        .insert(VehicleHandle::new(&mut vehicle, &mut physics, &mut actor));
+                match create_vehicle_handle(vehicle.as_deref_mut(), described, &vehicle_descriptors, &mut physics, &mut actor) {
+                    Some(Ok(vehicle)) => { commands.entity(entity).insert(vehicle); }
+                    Some(Err(error)) => shape_assets.report(error),
+                    None => {}
                 }
 
-                if let Some(Velocity { linvel, angvel }) = velocity {
+                // kinematic actors only move towards their targets
+                if let (Some(Velocity { linvel, angvel }), bpx::RigidBody::Dynamic) = (velocity, actor_cfg) {
                     actor.set_linear_velocity(&linvel.to_physx(), false);
                     actor.set_angular_velocity(&angvel.to_physx(), false);
                 }
 
-                // unsafe raw function call is required to avoid consuming actor
-                unsafe {
-                    PxScene_addActor_mut(scene.as_mut_ptr(), actor.as_mut_ptr(), null());
+                let with_bvh = actor_bvhs.contains(entity);
+                if !add_actor_to_scene(&mut physics, &mut scene, cooking.as_deref(), actor.as_mut_ptr(), aggregate, with_bvh) {
+                    shape_assets.report(PhysicsError::InvalidDescriptor { entity, message: AGGREGATE_FULL });
                 }
 
                 commands.entity(entity)
-                    .insert(RigidDynamicHandle::new(actor, *actor_transform));
+                    .insert(RigidDynamicHandle::new(actor, *actor_transform))
+                    .insert(computed_mass)
+                    .remove::<PhysicsPending>();
             }
 
             bpx::RigidBody::Static => {
-                let mut actor : Owner<PxRigidStatic> = physics.create_static(actor_transform.to_physx(), entity).unwrap();
+                let Some(mut actor) : Option<Owner<PxRigidStatic>> = physics.create_static(actor_transform.to_physx(), entity) else {
+                    shape_assets.report(PhysicsError::InvalidDescriptor { entity, message: "failed to create static actor" });
+                    failed.insert(entity);
+                    continue;
+                };
 
                 find_and_attach_nested_shapes(
                     &mut commands,
@@ -243,6 +894,8 @@ pub fn create_dynamic_actors(
                     &query,
                     actor_transform,
                     &mut default_material,
+                    &mut shared_shapes,
+                    &mut shape_assets,
                 );
 
                 if mass_props.is_some() {
@@ -253,61 +906,950 @@ pub fn create_dynamic_actors(
                     bevy::log::warn!("ignoring BPxVelocity component from a static actor");
                 }
 
-                // unsafe raw function call is required to avoid consuming actor
-                unsafe {
-                    PxScene_addActor_mut(scene.as_mut_ptr(), actor.as_mut_ptr(), null());
+                if !chunk_is_pending(entity, aggregate, &parents, &static_chunks) {
+                    let with_bvh = actor_bvhs.contains(entity);
+                    if !add_actor_to_scene(&mut physics, &mut scene, cooking.as_deref(), actor.as_mut_ptr(), aggregate, with_bvh) {
+                        shape_assets.report(PhysicsError::InvalidDescriptor { entity, message: AGGREGATE_FULL });
+                    }
                 }
 
                 commands.entity(entity)
-                    .insert(RigidStaticHandle::new(actor, *actor_transform));
+                    .insert(RigidStaticHandle::new(actor, *actor_transform))
+                    .remove::<PhysicsPending>();
+            }
+
+            bpx::RigidBody::ArticulationLink => {
+                // created together with the whole articulation in create_articulations
             }
         }
     }
 }
 
-pub fn apply_user_changes(
+/// Adds static actors of each new `bpx::StaticChunk` to the scene with a single pruning structure,
+/// once all of them are created.
+pub fn insert_static_chunks(
+    mut commands: Commands,
+    mut physics: ResMut<bpx::Physics>,
     mut scene: ResMut<bpx::Scene>,
-    mut changed_dynamic: Query<(&mut RigidDynamicHandle, &GlobalTransform), Changed<GlobalTransform>>,
-    mut changed_static: Query<(&mut RigidStaticHandle, &GlobalTransform), Changed<GlobalTransform>>,
+    new_chunks: Query<Entity, (With<bpx::StaticChunk>, Without<StaticChunkHandle>)>,
+    children: Query<&Children>,
+    mut actors: ChunkActorsQuery,
+    boundaries: ChunkBoundariesQuery,
+    failed_actors: Res<FailedActors>,
 ) {
-    for (mut handle, xform) in changed_dynamic.iter_mut() {
-        if xform != &handle.cached_transform {
-            handle.cached_transform = *xform;
-            handle.get_mut(&mut scene).set_global_pose(&xform.to_physx(), true);
+    for chunk in new_chunks.iter() {
+        let mut chunk_actors = ChunkActors::default();
+        if !collect_chunk_actors(chunk, chunk, false, &children, &mut actors, &boundaries, &failed_actors, &mut scene, &mut chunk_actors) {
+            continue;
         }
-    }
 
-    for (mut handle, xform) in changed_static.iter_mut() {
-        if xform != &handle.cached_transform {
-            handle.cached_transform = *xform;
-            handle.get_mut(&mut scene).set_global_pose(&xform.to_physx(), true);
+        // actors can be spawned later, e.g. by a scene
+        if chunk_actors.actors.is_empty() { continue; }
+
+        for entity in chunk_actors.failed.iter() {
+            bevy::log::warn!("BPxStaticChunk {chunk:?}: actor {entity:?} failed to create, adding chunk without it");
+        }
+
+        // PhysX objects can't be created while others are changed or released,
+        // so the structure is built on the main thread
+        let mut scene = scene.get_mut();
+
+        unsafe {
+            let structure = PxPhysics_createPruningStructure_mut(
+                physics.physics_mut().as_mut_ptr(),
+                chunk_actors.actors.as_ptr(),
+                chunk_actors.actors.len() as u32,
+            );
+
+            if structure.is_null() {
+                bevy::log::warn!("failed to create pruning structure for BPxStaticChunk, adding actors one by one");
+                PxScene_addActors_mut(
+                    scene.as_mut_ptr(),
+                    chunk_actors.actors.as_ptr() as *const *mut physx_sys::PxActor,
+                    chunk_actors.actors.len() as u32,
+                );
+            } else {
+                // scene keeps its own copy of the trees
+                PxScene_addActors_mut_1(scene.as_mut_ptr(), structure);
+                PxPruningStructure_release_mut(structure);
+            }
         }
+
+        commands.entity(chunk).insert(StaticChunkHandle);
     }
 }
 
-pub fn writeback_actors(
-    scene: Res<bpx::Scene>,
-    global_transforms: Query<&GlobalTransform>,
+/// Takes static actors of each removed `bpx::StaticChunk` out of the scene in a single operation.
+///
+/// Actors of chunks that were removed or despawned before being added are added individually.
+pub fn remove_static_chunks(
+    mut commands: Commands,
+    mut scene: ResMut<bpx::Scene>,
+    removed: RemovedComponents<bpx::StaticChunk>,
+    chunks: Query<(), With<StaticChunkHandle>>,
+    static_chunks: StaticChunksQuery,
+    children: Query<&Children>,
     parents: Query<&Parent>,
-    mut writeback_transform: Query<&mut Transform>,
-    mut actors: Query<(Entity, &mut RigidDynamicHandle, Option<&Parent>, Option<&mut Velocity>)>
+    mut actors: ChunkActorsQuery,
+    boundaries: ChunkBoundariesQuery,
+    failed_actors: Res<FailedActors>,
 ) {
-    for (actor_entity, mut actor, parent, velocity) in actors.iter_mut() {
-        let actor_handle = actor.get(&scene);
-        let xform = actor_handle.get_global_pose();
-        let mut actor_xform = xform.to_bevy();
+    let mut pending_chunks = HashSet::new();
 
-        let next_transform = if let Some(parent_transform) = parent.and_then(|p| global_transforms.get(**p).ok()) {
-            let (_scale, inv_rotation, inv_translation) =
-                parent_transform.affine().inverse().to_scale_rotation_translation();
+    for chunk in removed.iter() {
+        if !chunks.contains(chunk) {
+            pending_chunks.insert(chunk);
+            continue;
+        }
 
-            actor_xform.rotation = inv_rotation * actor_xform.rotation;
-            actor_xform.translation = inv_rotation * actor_xform.translation + inv_translation;
+        // actors that are not created yet will be added individually
+        let mut chunk_actors = ChunkActors::default();
+        collect_chunk_actors(chunk, chunk, true, &children, &mut actors, &boundaries, &failed_actors, &mut scene, &mut chunk_actors);
 
-            parent_transform.mul_transform(actor_xform)
-        } else {
-            actor_xform.into()
-        };
+        if !chunk_actors.actors.is_empty() {
+            unsafe {
+                PxScene_removeActors_mut(
+                    scene.get_mut().as_mut_ptr(),
+                    chunk_actors.actors.as_ptr() as *const *mut physx_sys::PxActor,
+                    chunk_actors.actors.len() as u32,
+                    true,
+                );
+            }
+        }
+
+        commands.entity(chunk).remove::<StaticChunkHandle>();
+    }
+
+    if pending_chunks.is_empty() { return; }
+
+    // static actors that were waiting for a chunk which is gone now,
+    // parents of a despawned chunk are still pointing to it
+    let mut orphans: Vec<*mut physx_sys::PxRigidActor> = vec![];
+
+    for (entity, _, handle) in actors.iter_mut() {
+        let Some(mut handle) = handle else { continue; };
+        if !waits_for_removed_chunk(entity, &parents, &static_chunks, &pending_chunks) { continue; }
+
+        let actor : *mut physx_sys::PxRigidActor = handle.get_mut(&mut scene).as_mut_ptr();
+
+        let in_scene = unsafe { !PxActor_getScene(actor as *const physx_sys::PxActor).is_null() };
+        let in_aggregate = unsafe { !PxActor_getAggregate(actor as *const physx_sys::PxActor).is_null() };
+
+        if !in_scene && !in_aggregate {
+            orphans.push(actor);
+        }
+    }
+
+    if !orphans.is_empty() {
+        unsafe {
+            PxScene_addActors_mut(
+                scene.get_mut().as_mut_ptr(),
+                orphans.as_ptr() as *const *mut physx_sys::PxActor,
+                orphans.len() as u32,
+            );
+        }
+    }
+}
+
+// detaches shapes from an actor that is being replaced and takes it out of the scene,
+// returns these shapes together with the aggregate actor was in
+fn take_actor_shapes(
+    scene: &mut bpx::Scene,
+    actor: *mut physx_sys::PxRigidActor,
+) -> (Vec<*mut physx_sys::PxShape>, *mut physx_sys::PxAggregate) {
+    let mut scene = scene.get_mut();
+
+    unsafe {
+        let mut shapes: Vec<*mut physx_sys::PxShape> = vec![null_mut(); PxRigidActor_getNbShapes(actor) as usize];
+        let count = PxRigidActor_getShapes(actor, shapes.as_mut_ptr(), shapes.len() as u32, 0);
+        shapes.truncate(count as usize);
+
+        for shape in shapes.iter().copied() {
+            PxRigidActor_detachShape_mut(actor, shape, false);
+        }
+
+        let aggregate = PxActor_getAggregate(actor as *const physx_sys::PxActor);
+        if aggregate.is_null() {
+            PxScene_removeActor_mut(scene.as_mut_ptr(), actor as *mut physx_sys::PxActor, true);
+        } else {
+            PxAggregate_removeActor_mut(aggregate, actor as *mut physx_sys::PxActor);
+        }
+
+        (shapes, aggregate)
+    }
+}
+
+fn attach_shapes(scene: &mut bpx::Scene, actor: *mut physx_sys::PxRigidActor, shapes: &[*mut physx_sys::PxShape]) {
+    let _lock = scene.get_mut();
+
+    for shape in shapes.iter().copied() {
+        unsafe { PxRigidActor_attachShape_mut(actor, shape); }
+    }
+}
+
+/// Rebuilds actors in place when `bpx::RigidBody` switches between dynamic and static,
+/// existing shapes are moved over to the new actor, and vehicles are created or removed accordingly.
+///
+/// Switching between dynamic and kinematic only changes the flag of the existing actor.
+pub fn rebuild_changed_actors(
+    mut commands: Commands,
+    mut physics: ResMut<bpx::Physics>,
+    mut scene: ResMut<bpx::Scene>,
+    mut changed: Query<
+        (
+            Entity, &bpx::RigidBody, Option<&MassProperties>, Option<&Velocity>, Option<&mut Vehicle>, Option<&DescribedVehicle>,
+            Option<&mut RigidDynamicHandle>, Option<&mut RigidStaticHandle>, Option<&VehicleHandle>,
+        ),
+        Changed<bpx::RigidBody>
+    >,
+    collider_densities: Query<&ColliderDensity>,
+    mut errors: EventWriter<PhysicsError>,
+    vehicle_descriptors: Res<Assets<bpx::VehicleDescriptor>>,
+    cooking: Option<Res<bpx::Cooking>>,
+    actor_bvhs: Query<(), With<ActorBvh>>,
+    parents: Query<&Parent>,
+    static_chunks: StaticChunksQuery,
+) {
+    for (entity, actor_cfg, mass_props, velocity, mut vehicle, described, dynamic_handle, static_handle, vehicle_handle) in changed.iter_mut() {
+        let (old_actor, pose): (*mut physx_sys::PxRigidActor, PxTransform) = match (actor_cfg, dynamic_handle, static_handle) {
+            (bpx::RigidBody::Static, Some(mut handle), _) => {
+                let mut actor = handle.get_mut(&mut scene);
+                (actor.as_mut_ptr(), actor.get_global_pose())
+            }
+            (bpx::RigidBody::Dynamic | bpx::RigidBody::Kinematic, Some(mut handle), _) => {
+                let mut actor = handle.get_mut(&mut scene);
+                let kinematic = *actor_cfg == bpx::RigidBody::Kinematic;
+
+                if actor.get_rigid_body_flags().contains(RigidBodyFlag::Kinematic) != kinematic {
+                    actor.set_rigid_body_flag(RigidBodyFlag::Kinematic, kinematic);
+                    if !kinematic { actor.wake_up(); }
+                }
+
+                continue;
+            }
+            (bpx::RigidBody::Dynamic | bpx::RigidBody::Kinematic, _, Some(mut handle)) => {
+                let mut actor = handle.get_mut(&mut scene);
+                (actor.as_mut_ptr(), actor.get_global_pose())
+            }
+            (bpx::RigidBody::ArticulationLink, dynamic_handle, static_handle) if dynamic_handle.is_some() || static_handle.is_some() => {
+                bevy::log::warn!("BPxRigidBody can't be changed into an articulation link at runtime");
+                continue;
+            }
+            // actor is not created yet, or its type didn't change
+            _ => continue,
+        };
+
+        let transform = GlobalTransform::from(pose.to_bevy());
+
+        match actor_cfg {
+            bpx::RigidBody::Dynamic | bpx::RigidBody::Kinematic => {
+                // new actor is created before the old one is taken apart, so that one stays if this fails
+                let Some(mut actor) : Option<Owner<PxRigidDynamic>> = physics.create_dynamic(&pose, entity) else {
+                    report_error(&mut errors, PhysicsError::InvalidDescriptor { entity, message: "failed to create dynamic actor" });
+                    continue;
+                };
+
+                if *actor_cfg == bpx::RigidBody::Kinematic {
+                    actor.set_rigid_body_flag(RigidBodyFlag::Kinematic, true);
+                }
+
+                // shapes have to be attached before mass and vehicle setup
+                let (shapes, aggregate) = take_actor_shapes(&mut scene, old_actor);
+                attach_shapes(&mut scene, actor.as_mut_ptr(), &shapes);
+
+                // new actor is put where the old one was
+                let with_bvh = actor_bvhs.contains(entity);
+                if !add_actor_to_scene(&mut physics, &mut scene, cooking.as_deref(), actor.as_mut_ptr(), aggregate, with_bvh) {
+                    report_error(&mut errors, PhysicsError::InvalidDescriptor { entity, message: AGGREGATE_FULL });
+                }
+
+                let mass_props = vehicle_mass_properties(mass_props, described, &vehicle_descriptors);
+                let computed_mass = update_mass_properties(actor.as_mut(), mass_props.as_ref(), &collider_densities);
+
+                if let (Some(Velocity { linvel, angvel }), bpx::RigidBody::Dynamic) = (velocity, actor_cfg) {
+                    actor.set_linear_velocity(&linvel.to_physx(), false);
+                    actor.set_angular_velocity(&angvel.to_physx(), false);
+                }
+
+                match create_vehicle_handle(vehicle.as_deref_mut(), described, &vehicle_descriptors, &mut physics, &mut actor) {
+                    Some(Ok(vehicle)) => { commands.entity(entity).insert(vehicle); }
+                    Some(Err(error)) => report_error(&mut errors, error),
+                    None => {}
+                }
+
+                commands.entity(entity)
+                    .remove::<RigidStaticHandle>()
+                    .insert(RigidDynamicHandle::new(actor, transform))
+                    .insert(computed_mass);
+            }
+
+            bpx::RigidBody::Static => {
+                let Some(mut actor) : Option<Owner<PxRigidStatic>> = physics.create_static(pose, entity) else {
+                    report_error(&mut errors, PhysicsError::InvalidDescriptor { entity, message: "failed to create static actor" });
+                    continue;
+                };
+
+                let (shapes, aggregate) = take_actor_shapes(&mut scene, old_actor);
+                attach_shapes(&mut scene, actor.as_mut_ptr(), &shapes);
+
+                if !chunk_is_pending(entity, aggregate, &parents, &static_chunks) {
+                    let with_bvh = actor_bvhs.contains(entity);
+                    if !add_actor_to_scene(&mut physics, &mut scene, cooking.as_deref(), actor.as_mut_ptr(), aggregate, with_bvh) {
+                        report_error(&mut errors, PhysicsError::InvalidDescriptor { entity, message: AGGREGATE_FULL });
+                    }
+                }
+
+                let mut entity_commands = commands.entity(entity);
+
+                // vehicle has to be released before the actor it's attached to
+                if vehicle_handle.is_some() {
+                    entity_commands.remove::<VehicleHandle>();
+                }
+
+                entity_commands
+                    .remove::<RigidDynamicHandle>()
+                    .remove::<ComputedMassProperties>()
+                    .insert(RigidStaticHandle::new(actor, transform));
+            }
+
+            bpx::RigidBody::ArticulationLink => unreachable!(),
+        }
+    }
+}
+
+pub fn create_async_scene_colliders(
+    mut commands: Commands,
+    mut physics: ResMut<bpx::Physics>,
+    cooking: Option<Res<bpx::Cooking>>,
+    scene_spawner: Res<SceneSpawner>,
+    meshes: Res<Assets<Mesh>>,
+    mut geometries: ResMut<Assets<bpx::Geometry>>,
+    async_colliders: Query<(Entity, &SceneInstance, &AsyncSceneCollider)>,
+    mesh_entities: Query<(&Handle<Mesh>, Option<&Name>, Option<&Parent>, &GlobalTransform)>,
+    names: Query<&Name>,
+    mut errors: EventWriter<PhysicsError>,
+) {
+    for (entity, scene_instance, async_collider) in async_colliders.iter() {
+        if !scene_spawner.instance_is_ready(**scene_instance) { continue; }
+
+        let Some(cooking) = cooking.as_deref() else {
+            bevy::log::warn!("cooking is required for BPxAsyncSceneCollider");
+            commands.entity(entity).remove::<AsyncSceneCollider>();
+            continue;
+        };
+
+        for mesh_entity in scene_spawner.iter_instance_entities(**scene_instance).into_iter().flatten() {
+            let Ok((mesh_handle, name, parent, gtransform)) = mesh_entities.get(mesh_entity) else { continue; };
+
+            // gltf loader names the node, while primitives are its unnamed children
+            let name = name.or_else(|| parent.and_then(|p| names.get(**p).ok()));
+            let shape = name
+                .and_then(|name| async_collider.named_shapes.get(name.as_str()))
+                .copied()
+                .unwrap_or(async_collider.shape);
+
+            let Some(shape) = shape else { continue; };
+            let Some(mesh) = meshes.get(mesh_handle) else { continue; };
+
+            let geometry = match shape {
+                ComputedCollider::TriMesh => bpx::Geometry::trimesh_from_mesh(&mut physics, cooking, mesh)
+                    .map_err(CookingError::TriangleMesh),
+                ComputedCollider::ConvexHull => bpx::Geometry::convex_mesh_from_mesh(&mut physics, cooking, mesh)
+                    .map_err(CookingError::ConvexMesh),
+            };
+
+            match geometry {
+                Ok(geometry) => {
+                    // shape pose doesn't include scale, so it's baked into the geometry
+                    let (scale, _, _) = gtransform.to_scale_rotation_translation();
+                    commands.entity(mesh_entity).insert(bpx::Shape {
+                        geometry: geometries.add(geometry.with_scale(scale)),
+                        material: async_collider.material.clone(),
+                        ..default()
+                    });
+                }
+                Err(error) => {
+                    report_error(&mut errors, PhysicsError::Cooking { entity: mesh_entity, asset: None, error });
+                }
+            }
+        }
+
+        commands.entity(entity).remove::<AsyncSceneCollider>();
+    }
+}
+
+pub fn create_convex_decompositions(
+    mut commands: Commands,
+    mut physics: ResMut<bpx::Physics>,
+    cooking: Option<Res<bpx::Cooking>>,
+    asset_server: Res<AssetServer>,
+    meshes: Res<Assets<Mesh>>,
+    mut geometries: ResMut<Assets<bpx::Geometry>>,
+    colliders: Query<(Entity, &ConvexDecompositionCollider, &GlobalTransform)>,
+    mut errors: EventWriter<PhysicsError>,
+) {
+    for (entity, collider, gtransform) in colliders.iter() {
+        let Some(mesh) = meshes.get(&collider.mesh) else {
+            if asset_server.get_load_state(&collider.mesh) == bevy::asset::LoadState::Failed {
+                bevy::log::warn!("failed to load mesh for BPxConvexDecompositionCollider");
+                commands.entity(entity).remove::<ConvexDecompositionCollider>();
+            }
+            continue;
+        };
+
+        let Some(cooking) = cooking.as_deref() else {
+            bevy::log::warn!("cooking is required for BPxConvexDecompositionCollider");
+            commands.entity(entity).remove::<ConvexDecompositionCollider>();
+            continue;
+        };
+
+        match bpx::Geometry::convex_decomposition_from_mesh(&mut physics, cooking, mesh, &collider.params) {
+            Ok(hulls) => {
+                // shape pose doesn't include scale, so it's baked into the geometry
+                let (scale, _, _) = gtransform.to_scale_rotation_translation();
+
+                commands.entity(entity).with_children(|builder| {
+                    for hull in hulls {
+                        // hull that failed to cook is skipped, the rest of the collider is still usable
+                        let hull = match hull {
+                            Ok(hull) => hull,
+                            Err(err) => {
+                                report_error(&mut errors, PhysicsError::Cooking { entity, asset: None, error: CookingError::ConvexMesh(err) });
+                                continue;
+                            }
+                        };
+
+                        builder.spawn(SpatialBundle::default())
+                            .insert(bpx::Shape {
+                                geometry: geometries.add(hull.with_scale(scale)),
+                                material: collider.material.clone(),
+                                ..default()
+                            });
+                    }
+                });
+            }
+            Err(err) => {
+                report_error(&mut errors, PhysicsError::Cooking { entity, asset: None, error: CookingError::ConvexMesh(err) });
+            }
+        }
+
+        commands.entity(entity).remove::<ConvexDecompositionCollider>();
+    }
+}
+
+fn configure_articulation_joint(
+    link: &mut PxArticulationLink,
+    joint_cfg: &bpx::ArticulationJoint,
+    parent_transform: &GlobalTransform,
+    link_transform: &GlobalTransform,
+) {
+    let joint_ptr = unsafe {
+        PxArticulationLink_getInboundJoint(Class::<physx_sys::PxArticulationLink>::as_ptr(link))
+    };
+    if joint_ptr.is_null() { return; }
+
+    // joint frame is given relative to the child link, so parent frame is
+    // the same frame expressed in parent link coordinates
+    let relative_transform = Transform::from_matrix(
+        (parent_transform.affine().inverse() * link_transform.affine()).into()
+    );
+
+    unsafe {
+        PxArticulationJointBase_setParentPose_mut(joint_ptr, (relative_transform * joint_cfg.frame).to_physx().as_ptr());
+        PxArticulationJointBase_setChildPose_mut(joint_ptr, joint_cfg.frame.to_physx().as_ptr());
+    }
+
+    let joint = unsafe { &mut *(joint_ptr as *mut ArticulationJointReducedCoordinate) };
+    joint.set_joint_type(joint_cfg.joint_type);
+
+    for axis in ArticulationAxis::all_axes().copied() {
+        let motion = joint_cfg.motion[axis as usize];
+        joint.set_motion(axis, motion);
+
+        if matches!(motion, ArticulationMotion::Limited) {
+            let (min, max) = joint_cfg.limits[axis as usize];
+            joint.set_limit(axis, min, max);
+        }
+
+        if let Some(drive) = joint_cfg.drives[axis as usize] {
+            joint.set_drive(axis, drive.stiffness, drive.damping, drive.max_force, drive.drive_type);
+            joint.set_drive_target(drive.target, axis);
+        }
+    }
+}
+
+fn create_articulation_links(
+    commands: &mut Commands,
+    entity: Entity,
+    parent: Option<(*mut PxArticulationLink, GlobalTransform)>,
+    articulation: &mut PxArticulationReducedCoordinate,
+    physics: &mut bpx::Physics,
+    geometries: &mut ResMut<Assets<bpx::Geometry>>,
+    materials: &mut ResMut<Assets<bpx::Material>>,
+    shapes_query: &ShapesQuery,
+    links_query: &ArticulationLinksQuery,
+    default_material: &mut ResMut<DefaultMaterial>,
+    shared_shapes: &mut ResMut<SharedShapes>,
+    collider_densities: &Query<&ColliderDensity>,
+    shape_assets: &mut ShapeAssets,
+    level: u32,
+) -> Result<(), PhysicsError> {
+    let Ok((rigid_body, children, link_transform, joint_cfg, mass_props)) = links_query.get(entity) else { return Ok(()); };
+
+    // another actor or articulation nested inside this one, it will be created separately
+    if level > 0 && rigid_body.is_some() && rigid_body != Some(&bpx::RigidBody::ArticulationLink) { return Ok(()); }
+
+    let mut next_parent = parent;
+
+    if level == 0 || rigid_body.is_some() {
+        let parent_link = parent.map(|(ptr, _)| unsafe { &mut *ptr });
+        let Some(link) = articulation.create_link(parent_link, &link_transform.to_physx(), entity) else {
+            return Err(PhysicsError::InvalidDescriptor { entity, message: "failed to create articulation link" });
+        };
+
+        find_and_attach_nested_shapes(
+            commands,
+            entity,
+            link,
+            physics,
+            geometries,
+            materials,
+            shapes_query,
+            link_transform,
+            default_material,
+            shared_shapes,
+            shape_assets,
+        );
+
+        let computed_mass = update_mass_properties(link, mass_props, collider_densities);
+        commands.entity(entity).insert(computed_mass);
+
+        if let Some((_, parent_transform)) = parent {
+            if joint_cfg.is_none() {
+                bevy::log::warn!("articulation link without BPxArticulationJoint, using fixed joint");
+            }
+
+            let default_joint = bpx::ArticulationJoint::default();
+            configure_articulation_joint(link, joint_cfg.unwrap_or(&default_joint), &parent_transform, link_transform);
+        } else if joint_cfg.is_some() {
+            bevy::log::warn!("ignoring BPxArticulationJoint component from a root link");
+        }
+
+        next_parent = Some((link as *mut _, *link_transform));
+    }
+
+    if let Some(children) = children {
+        for child in children.iter().copied() {
+            create_articulation_links(
+                commands,
+                child,
+                next_parent,
+                articulation,
+                physics,
+                geometries,
+                materials,
+                shapes_query,
+                links_query,
+                default_material,
+                shared_shapes,
+                collider_densities,
+                shape_assets,
+                level + 1,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn create_articulations(
+    mut commands: Commands,
+    mut physics: ResMut<bpx::Physics>,
+    mut scene: ResMut<bpx::Scene>,
+    shapes_query: ShapesQuery,
+    links_query: ArticulationLinksQuery,
+    new_articulations: Query<(Entity, &bpx::Articulation, &GlobalTransform), Without<ArticulationHandle>>,
+    mut geometries: ResMut<Assets<bpx::Geometry>>,
+    mut materials: ResMut<Assets<bpx::Material>>,
+    mut default_material: ResMut<DefaultMaterial>,
+    mut shared_shapes: ResMut<SharedShapes>,
+    parents: Query<&Parent>,
+    mut aggregates: AggregatesQuery,
+    collider_densities: Query<&ColliderDensity>,
+    mut shape_assets: ShapeAssets,
+    pending: Query<(), With<PhysicsPending>>,
+    mut failed: ResMut<FailedActors>,
+) {
+    for (entity, articulation_cfg, root_transform) in new_articulations.iter() {
+        // PhysX already reported why, retrying won't help
+        if failed.contains(&entity) { continue; }
+
+        if !shape_assets.subtree_shapes_ready(entity, &shapes_query, &geometries, &materials) {
+            if !pending.contains(entity) { commands.entity(entity).insert(PhysicsPending); }
+            continue;
+        }
+
+        let aggregate = find_aggregate(entity, &parents, &aggregates);
+        if aggregate_is_pending(aggregate, &aggregates) { continue; }
+
+        let Some(mut articulation) : Option<Owner<PxArticulationReducedCoordinate>> =
+            physics.create_articulation_reduced_coordinate(entity) else {
+            shape_assets.report(PhysicsError::InvalidDescriptor { entity, message: "failed to create articulation" });
+            failed.insert(entity);
+            continue;
+        };
+
+        articulation.set_articulation_flag(ArticulationFlag::FixBase, articulation_cfg.fix_base);
+
+        let created = create_articulation_links(
+            &mut commands,
+            entity,
+            None,
+            articulation.as_mut(),
+            physics.as_mut(),
+            &mut geometries,
+            &mut materials,
+            &shapes_query,
+            &links_query,
+            &mut default_material,
+            &mut shared_shapes,
+            &collider_densities,
+            &mut shape_assets,
+            0,
+        );
+
+        // articulation is released together with links created so far
+        if let Err(error) = created {
+            shape_assets.report(error);
+            failed.insert(entity);
+            continue;
+        }
+
+        // unsafe raw function call is required to avoid consuming articulation
+        match aggregate.and_then(|aggregate| aggregates.get_mut(aggregate).ok().flatten()) {
+            Some(mut aggregate) => {
+                let added = unsafe {
+                    PxAggregate_addArticulation_mut(aggregate.get_mut(&mut scene).as_mut_ptr(), articulation.as_mut_ptr())
+                };
+
+                if !added {
+                    bevy::log::warn!("failed to add articulation to BPxAggregate, increase max_actors");
+                }
+
+                // link indices are assigned only when articulation enters the scene,
+                // so the aggregate can't wait for insert_aggregates
+                if !aggregate.in_scene {
+                    let aggregate_ptr : *mut physx_sys::PxAggregate = aggregate.get_mut(&mut scene).as_mut_ptr();
+                    unsafe {
+                        PxScene_addAggregate_mut(scene.get_mut().as_mut_ptr(), aggregate_ptr);
+                    }
+                    aggregate.in_scene = true;
+                }
+            }
+            None => unsafe {
+                PxScene_addArticulation_mut(scene.get_mut().as_mut_ptr(), articulation.as_mut_ptr());
+            }
+        }
+
+        // link indices are only final after articulation is added to the scene
+        let mut links = vec![Entity::from_raw(u32::MAX); articulation.get_nb_links()];
+        for link in articulation.get_links() {
+            let index = link.get_link_index() as usize;
+            links[index] = *link.get_user_data();
+            commands.entity(*link.get_user_data())
+                .insert(ArticulationLinkHandle { articulation: entity, index });
+        }
+
+        commands.entity(entity)
+            .insert(ArticulationHandle::new(articulation, links, *root_transform))
+            .remove::<PhysicsPending>();
+    }
+}
+
+pub fn apply_user_changes(
+    mut scene: ResMut<bpx::Scene>,
+    mut changed_dynamic: Query<(&mut RigidDynamicHandle, &GlobalTransform), Changed<GlobalTransform>>,
+    mut changed_static: Query<(&mut RigidStaticHandle, &GlobalTransform), Changed<GlobalTransform>>,
+    mut changed_articulation: Query<(&mut ArticulationHandle, &GlobalTransform), Changed<GlobalTransform>>,
+) {
+    for (mut handle, xform) in changed_dynamic.iter_mut() {
+        if xform != &handle.cached_transform {
+            handle.cached_transform = *xform;
+            let mut actor = handle.get_mut(&mut scene);
+
+            // kinematic actors are moved towards the target during simulation to push others away
+            if actor.get_rigid_body_flags().contains(RigidBodyFlag::Kinematic) {
+                actor.set_kinematic_target(&xform.to_physx());
+            } else {
+                actor.set_global_pose(&xform.to_physx(), true);
+            }
+        }
+    }
+
+    for (mut handle, xform) in changed_static.iter_mut() {
+        if xform != &handle.cached_transform {
+            handle.cached_transform = *xform;
+            handle.get_mut(&mut scene).set_global_pose(&xform.to_physx(), true);
+        }
+    }
+
+    for (mut handle, xform) in changed_articulation.iter_mut() {
+        if xform != &handle.cached_transform {
+            handle.cached_transform = *xform;
+            handle.get_mut(&mut scene).teleport_to(&xform.to_physx());
+        }
+    }
+}
+
+fn find_actor(entity: Entity, rigid_bodies: &Query<&bpx::RigidBody>, parents: &Query<&Parent>) -> Option<Entity> {
+    find_ancestor(entity, parents, |current| rigid_bodies.contains(current))
+}
+
+// poses are round-tripped through writeback and transform propagation, so they are compared with a tolerance
+fn same_local_pose(a: &Transform, b: &Transform) -> bool {
+    a.translation.abs_diff_eq(b.translation, 1e-4)
+        && (a.rotation.abs_diff_eq(b.rotation, 1e-5) || a.rotation.abs_diff_eq(-b.rotation, 1e-5))
+}
+
+type ShapeSyncQuery<'world, 'state, 'a> = Query<'world, 'state,
+    (
+        Entity, &'a bpx::Shape, Option<&'a ShapeSettings>, Option<&'a GlobalTransform>,
+        Option<&'a mut ShapeHandle>, ChangeTrackers<bpx::Shape>,
+    ),
+    Or<(Changed<bpx::Shape>, Changed<Transform>, Without<ShapeHandle>)>
+>;
+
+// shapes attached to actors by entity, so they can be detached after the entity is despawned
+// and its `ShapeHandle` is gone; actor keeps its own reference to the shape until then
+#[derive(Default)]
+pub struct AttachedShapes(HashMap<Entity, (Entity, *mut physx_sys::PxShape)>);
+
+// SAFETY: pointers are only compared and passed to PhysX from `sync_actor_shapes`, with the scene locked
+unsafe impl Send for AttachedShapes {}
+unsafe impl Sync for AttachedShapes {}
+
+impl AttachedShapes {
+    fn attach(&mut self, scene: &mut bpx::Scene, entity: Entity, actor_entity: Entity, shape: *mut physx_sys::PxShape) {
+        if let Some((old_actor, old_shape)) = self.0.insert(entity, (actor_entity, shape)) {
+            scene.forget_shape_entity(old_shape, old_actor);
+        }
+        scene.set_shape_entity(shape, actor_entity, entity);
+    }
+
+    fn detach(&mut self, scene: &mut bpx::Scene, entity: Entity) -> Option<(Entity, *mut physx_sys::PxShape)> {
+        let (actor_entity, shape) = self.0.remove(&entity)?;
+        scene.forget_shape_entity(shape, actor_entity);
+        Some((actor_entity, shape))
+    }
+}
+
+/// Keeps shapes of already created actors in sync with the ECS: rebuilds shapes whose `bpx::Shape`
+/// changed, attaches newly added ones, detaches removed or despawned ones, and updates local poses of child colliders
+/// whose `Transform` changed. Mass of affected dynamic actors is recomputed from `MassProperties` afterwards,
+/// as well as when `MassProperties` or `ColliderDensity` change. Shared shapes are replaced instead of moved.
+///
+/// Articulation links and vehicles are not synced, as they depend on their shape layout.
+pub fn sync_actor_shapes(
+    mut commands: Commands,
+    mut physics: ResMut<bpx::Physics>,
+    mut scene: ResMut<bpx::Scene>,
+    mut shape_queries: ParamSet<(
+        ShapeSyncQuery,
+        Query<(Entity, &ShapeHandle), (Changed<ShapeHandle>, With<bpx::Shape>)>,
+    )>,
+    removed_shapes: Query<(), (With<ShapeHandle>, Without<bpx::Shape>)>,
+    removed: RemovedComponents<bpx::Shape>,
+    mut attached: Local<AttachedShapes>,
+    mut dynamic_actors: Query<(&mut RigidDynamicHandle, &GlobalTransform, Option<&MassProperties>), Without<VehicleHandle>>,
+    mut static_actors: Query<(&mut RigidStaticHandle, &GlobalTransform)>,
+    rigid_bodies: Query<&bpx::RigidBody>,
+    parents: Query<&Parent>,
+    mut geometries: ResMut<Assets<bpx::Geometry>>,
+    mut materials: ResMut<Assets<bpx::Material>>,
+    mut default_material: ResMut<DefaultMaterial>,
+    mut shared_shapes: ResMut<SharedShapes>,
+    collider_densities: Query<&ColliderDensity>,
+    changed_densities: Query<Entity, Changed<ColliderDensity>>,
+    changed_mass_props: Query<Entity, (Changed<MassProperties>, With<RigidDynamicHandle>)>,
+    mut shape_assets: ShapeAssets,
+) {
+    let mut updated_actors = HashSet::new();
+
+    for entity in changed_densities.iter() {
+        updated_actors.extend(find_actor(entity, &rigid_bodies, &parents));
+    }
+
+    updated_actors.extend(changed_mass_props.iter());
+
+    // handles created along with their actors, or replaced by other systems
+    for (entity, shape_handle) in shape_queries.p1().iter() {
+        let Some(actor_entity) = find_actor(entity, &rigid_bodies, &parents) else { continue; };
+        attached.attach(&mut scene, entity, actor_entity, shape_handle.as_ptr() as *mut physx_sys::PxShape);
+    }
+
+    for (entity, shape_cfg, settings, gtransform, shape_handle, shape_tracker) in shape_queries.p0().iter_mut() {
+        // shape was created together with its actor since the last run
+        if shape_handle.as_ref().map_or(false, |handle| handle.is_added()) { continue; }
+
+        let Some(actor_entity) = find_actor(entity, &rigid_bodies, &parents) else { continue; };
+
+        // actors that are not created yet will pick up their shapes on creation
+        let actor_transform = if let Ok((_, xform, _)) = dynamic_actors.get(actor_entity) {
+            *xform
+        } else if let Ok((_, xform)) = static_actors.get(actor_entity) {
+            *xform
+        } else {
+            continue;
+        };
+
+        let relative_transform = gtransform
+            .map(|gtransform| relative_shape_transform(&actor_transform, gtransform))
+            .unwrap_or_default();
+
+        let mut old_handle = shape_handle.map(|handle| handle.into_inner());
+        let config_changed = old_handle.is_none() || shape_tracker.is_changed();
+
+        if old_handle.is_none() && !shape_tracker.is_changed() && shape_assets.failed_shapes.contains(&entity) { continue; }
+
+        // transform of the actor itself is not a shape change
+        if !config_changed && entity == actor_entity { continue; }
+
+        // writeback sets `Transform` of child colliders every frame, so only actual pose changes are applied
+        if !config_changed {
+            let (Some(shape_handle), Some(geometry)) = (old_handle.as_ref(), geometries.get(&shape_cfg.geometry)) else { continue; };
+            let local_pose = geometry.shape_local_pose(&relative_transform);
+            let current_pose = unsafe { PxShape_getLocalPose(shape_handle.as_ptr()) }.to_bevy();
+            if same_local_pose(&current_pose, &local_pose) { continue; }
+        }
+
+        // local pose of a shared shape can't be changed without affecting other actors using it
+        let shared = old_handle.as_ref().map_or(false, |handle| !handle.is_exclusive());
+
+        if config_changed || shared {
+            if !shape_assets.shape_ready(entity, shape_cfg, &geometries, &materials) { continue; }
+
+            let mut shape_handle = match create_shape(
+                &mut physics,
+                &mut geometries,
+                &mut materials,
+                &mut default_material,
+                &mut shared_shapes,
+                entity,
+                shape_cfg,
+                &settings.copied().unwrap_or_default(),
+                &relative_transform,
+            ) {
+                Ok(shape_handle) => shape_handle,
+                Err(error) => {
+                    shape_assets.report_shape(entity, error);
+                    continue;
+                }
+            };
+            shape_assets.failed_shapes.remove(&entity);
+
+            if let Ok((mut actor, _, _)) = dynamic_actors.get_mut(actor_entity) {
+                let mut actor = actor.get_mut(&mut scene);
+                if let Some(old_handle) = old_handle.as_deref_mut() { actor.detach_shape(old_handle); }
+                actor.attach_shape(&mut shape_handle);
+            } else if let Ok((mut actor, _)) = static_actors.get_mut(actor_entity) {
+                let mut actor = actor.get_mut(&mut scene);
+                if let Some(old_handle) = old_handle.as_deref_mut() { actor.detach_shape(old_handle); }
+                actor.attach_shape(&mut shape_handle);
+            }
+
+            attached.attach(&mut scene, entity, actor_entity, shape_handle.as_mut_ptr());
+
+            // old handle is released when replaced
+            commands.entity(entity).insert(shape_handle);
+            updated_actors.insert(actor_entity);
+        } else {
+            let Some(shape_handle) = old_handle else { continue; };
+            let Some(geometry) = geometries.get(&shape_cfg.geometry) else { continue; };
+            let local_pose = geometry.shape_local_pose(&relative_transform);
+
+            let _lock = scene.get_mut();
+
+            unsafe {
+                PxShape_setLocalPose_mut(
+                    shape_handle.as_mut_ptr(),
+                    local_pose.to_physx().as_ptr(),
+                );
+            }
+
+            updated_actors.insert(actor_entity);
+        }
+    }
+
+    for entity in removed.iter() {
+        shape_assets.failed_shapes.remove(&entity);
+
+        if removed_shapes.contains(entity) {
+            commands.entity(entity).remove::<ShapeHandle>();
+        }
+
+        // shape entity might be despawned, so actor is the one it was attached to
+        let Some((actor_entity, shape)) = attached.detach(&mut scene, entity) else { continue; };
+
+        let actor: *mut physx_sys::PxRigidActor = if let Ok((mut actor, _, _)) = dynamic_actors.get_mut(actor_entity) {
+            actor.get_mut(&mut scene).as_mut_ptr() as *mut _
+        } else if let Ok((mut actor, _)) = static_actors.get_mut(actor_entity) {
+            actor.get_mut(&mut scene).as_mut_ptr() as *mut _
+        } else {
+            // actor is gone as well, and its shapes with it
+            continue;
+        };
+
+        let _lock = scene.get_mut();
+
+        unsafe {
+            // shape might have been replaced since it was tracked, and released already
+            let mut shapes: Vec<*mut physx_sys::PxShape> = vec![null_mut(); PxRigidActor_getNbShapes(actor) as usize];
+            let count = PxRigidActor_getShapes(actor, shapes.as_mut_ptr(), shapes.len() as u32, 0);
+            shapes.truncate(count as usize);
+
+            if !shapes.contains(&shape) { continue; }
+            PxRigidActor_detachShape_mut(actor, shape, true);
+        }
+
+        updated_actors.insert(actor_entity);
+    }
+
+    for actor_entity in updated_actors {
+        let Ok((mut actor, _, mass_props)) = dynamic_actors.get_mut(actor_entity) else { continue; };
+        let mut actor = actor.get_mut(&mut scene);
+        let actor: &mut PxRigidDynamic = &mut actor;
+        let computed_mass = update_mass_properties(actor, mass_props, &collider_densities);
+        actor.wake_up();
+        commands.entity(actor_entity).insert(computed_mass);
+    }
+}
+
+pub fn writeback_actors(
+    scene: Res<bpx::Scene>,
+    global_transforms: Query<&GlobalTransform>,
+    parents: Query<&Parent>,
+    rigid_bodies: Query<&bpx::RigidBody>,
+    shape_configs: Query<&bpx::Shape>,
+    geometries: Res<Assets<bpx::Geometry>>,
+    mut writeback_transform: Query<&mut Transform>,
+    mut actors: Query<(Entity, &mut RigidDynamicHandle, Option<&Parent>, Option<&mut Velocity>)>
+) {
+    for (actor_entity, mut actor, parent, velocity) in actors.iter_mut() {
+        let actor_handle = actor.get(&scene);
+        let xform = actor_handle.get_global_pose();
+        let mut actor_xform = xform.to_bevy();
+
+        let next_transform = if let Some(parent_transform) = parent.and_then(|p| global_transforms.get(**p).ok()) {
+            let (_scale, inv_rotation, inv_translation) =
+                parent_transform.affine().inverse().to_scale_rotation_translation();
+
+            actor_xform.rotation = inv_rotation * actor_xform.rotation;
+            actor_xform.translation = inv_rotation * actor_xform.translation + inv_translation;
+
+            parent_transform.mul_transform(actor_xform)
+        } else {
+            actor_xform.into()
+        };
 
         if let Ok(mut transform) = writeback_transform.get_mut(actor_entity) {
             // avoid triggering bevy's change tracking if no change
@@ -318,6 +1860,9 @@ pub fn writeback_actors(
         let actor_xform = Transform::from(global_transforms.get(actor_entity).copied().unwrap_or(GlobalTransform::IDENTITY));
 
         for shape in actor_handle.get_shapes() {
+            // shared shapes store the entity they were first created for, which might belong to another actor
+            if !unsafe { PxShape_isExclusive(shape.as_ptr()) } { continue; }
+
             let shape_entity = *shape.get_user_data();
             if shape_entity == actor_entity {
                 // we already updated actor entity above,
@@ -325,7 +1870,14 @@ pub fn writeback_actors(
                 continue;
             }
 
-            let shape_local_xform = unsafe { PxShape_getLocalPose(shape.as_ptr()) }.to_bevy();
+            // shape entity was moved to another actor, which is going to take the shape over
+            if find_actor(shape_entity, &rigid_bodies, &parents) != Some(actor_entity) { continue; }
+
+            // PhysX local pose includes the axis rotation of geometry, which is not part of the entity transform
+            let mut shape_local_xform = unsafe { PxShape_getLocalPose(shape.as_ptr()) }.to_bevy();
+            if let Some(geometry) = shape_configs.get(shape_entity).ok().and_then(|shape_cfg| geometries.get(&shape_cfg.geometry)) {
+                shape_local_xform = geometry.shape_transform(&shape_local_xform);
+            }
             let mut shape_xform = actor_xform * shape_local_xform;
 
             if let Some(parent_transform) = parents.get(shape_entity).ok().and_then(|p| global_transforms.get(**p).ok()) {
@@ -356,3 +1908,311 @@ pub fn writeback_actors(
         actor.cached_transform = next_transform;
     }
 }
+
+pub fn writeback_articulations(
+    scene: Res<bpx::Scene>,
+    global_transforms: Query<&GlobalTransform>,
+    parents: Query<&Parent>,
+    mut writeback_transform: Query<&mut Transform>,
+    mut writeback_velocity: Query<&mut Velocity>,
+    mut articulations: Query<(Entity, &mut ArticulationHandle)>,
+) {
+    for (root_entity, mut articulation) in articulations.iter_mut() {
+        let articulation_handle = articulation.get(&scene);
+        let mut next_transforms = HashMap::new();
+
+        // links are stored parent first, so parent link transforms are already updated
+        for link in articulation_handle.get_links() {
+            let link_entity = *link.get_user_data();
+            let mut link_xform = link.get_global_pose().to_bevy();
+            let global_xform = GlobalTransform::from(link_xform);
+
+            let parent_transform = parents.get(link_entity).ok().and_then(|p| {
+                next_transforms.get(&**p).copied().or_else(|| global_transforms.get(**p).ok().copied())
+            });
+
+            if let Some(parent_transform) = parent_transform {
+                let (_scale, inv_rotation, inv_translation) =
+                    parent_transform.affine().inverse().to_scale_rotation_translation();
+
+                link_xform.rotation = inv_rotation * link_xform.rotation;
+                link_xform.translation = inv_rotation * link_xform.translation + inv_translation;
+            }
+
+            next_transforms.insert(link_entity, global_xform);
+
+            if let Ok(mut transform) = writeback_transform.get_mut(link_entity) {
+                // avoid triggering bevy's change tracking if no change
+                if link_xform != *transform { *transform = link_xform; }
+            }
+
+            if let Ok(mut velocity) = writeback_velocity.get_mut(link_entity) {
+                let newvel = Velocity::new(
+                    link.get_linear_velocity().to_bevy(),
+                    link.get_angular_velocity().to_bevy(),
+                );
+
+                // avoid triggering bevy's change tracking if no change
+                if newvel != *velocity { *velocity = newvel; }
+            }
+        }
+
+        drop(articulation_handle);
+
+        if let Some(root_transform) = next_transforms.get(&root_entity) {
+            articulation.cached_transform = *root_transform;
+        }
+    }
+}
+
+pub fn solve_inverse_kinematics(
+    mut scene: ResMut<bpx::Scene>,
+    solvers: Query<(Entity, &bpx::InverseKinematics, &ArticulationLinkHandle)>,
+    parents: Query<&Parent>,
+    link_handles: Query<&ArticulationLinkHandle>,
+    joints: Query<&bpx::ArticulationJoint>,
+    mut articulations: Query<&mut ArticulationHandle>,
+) {
+    // state of each articulation is computed once and shared by all of its solvers,
+    // drive targets set below don't change it until the next simulation step
+    let mut by_articulation: HashMap<Entity, Vec<(Entity, &bpx::InverseKinematics, usize)>> = HashMap::new();
+    for (entity, ik, link_handle) in solvers.iter() {
+        by_articulation.entry(link_handle.articulation).or_default().push((entity, ik, link_handle.index));
+    }
+
+    for (articulation_entity, targets) in by_articulation {
+        let Ok(mut articulation) = articulations.get_mut(articulation_entity) else { continue; };
+        let Some((positions, jacobian, poses)) = articulation.kinematic_state(&scene) else { continue; };
+        let layout = positions.layout();
+
+        for (entity, ik, link_index) in targets {
+            let Some(current) = poses.get(link_index) else { continue; };
+
+            let error = ik.error(current);
+            if error.iter().all(|e| e.abs() < ik.tolerance) { continue; }
+
+            // root link of fixed base articulation can't move
+            let Some(first_row) = jacobian.link_row(link_index) else { continue; };
+
+            let nb_cols = layout.total_dofs();
+            let mut chain_jacobian = Vec::with_capacity(error.len() * nb_cols);
+            for row in 0..error.len() {
+                for link_index in 0..layout.nb_links() {
+                    for dof in 0..layout.link_dofs(link_index) {
+                        chain_jacobian.push(jacobian.matrix[(first_row + row, jacobian.dof_col(link_index, dof))]);
+                    }
+                }
+            }
+
+            let Some(delta) = ik.solve(&chain_jacobian, nb_cols, &error) else { continue; };
+
+            // only joints between end effector and root are driven by this solver
+            let mut chain = vec![];
+            let mut current_entity = Some(entity);
+            while let Some(link_entity) = current_entity {
+                if let Ok(handle) = link_handles.get(link_entity) {
+                    if handle.articulation != articulation_entity { break; }
+                    chain.push((link_entity, handle.index));
+                }
+                current_entity = parents.get(link_entity).ok().map(|p| **p);
+            }
+
+            for (link_entity, link_index) in chain {
+                let Ok(joint) = joints.get(link_entity) else { continue; };
+                let axes = joint.dof_axes();
+                if axes.len() != layout.link_dofs(link_index) { continue; }
+
+                for (dof, axis) in axes.into_iter().enumerate() {
+                    if joint.drives[axis as usize].is_none() { continue; }
+
+                    let mut target = positions[(link_index, dof)] + delta[layout.index(link_index, dof)];
+
+                    if matches!(joint.motion[axis as usize], ArticulationMotion::Limited) {
+                        let (min, max) = joint.limits[axis as usize];
+                        target = target.clamp(min, max);
+                    }
+
+                    articulation.set_drive_target(&mut scene, link_index, axis, target);
+                }
+            }
+        }
+    }
+}
+
+pub(crate) type ShapeGeometryQuery<'w, 's> =
+    Query<'w, 's, (Entity, &'static mut bpx::Shape, &'static mut ShapeHandle, Option<&'static GlobalTransform>)>;
+
+/// Sets geometry again on every shape using it, as shapes cache mesh bounds,
+/// returns world-space bounds of `local_region` for each of those shapes.
+///
+/// PhysX doesn't allow changing geometry of shared shapes while they are attached,
+/// so those are re-created by `sync_actor_shapes` instead.
+pub(crate) fn refresh_shapes_geometry(
+    commands: &mut Commands,
+    scene: &mut bpx::Scene,
+    shapes: &mut ShapeGeometryQuery,
+    shared_shapes: &mut SharedShapes,
+    handle: &Handle<bpx::Geometry>,
+    geometry: &bpx::Geometry,
+    local_region: (Vec3, Vec3),
+    debug_render: bool,
+) -> Vec<(Vec3, Vec3)> {
+    let _lock = scene.get_mut();
+    let mut regions = vec![];
+
+    shared_shapes.forget_using(handle.id());
+
+    for (entity, mut shape, mut shape_handle, gtransform) in shapes.iter_mut() {
+        if shape.geometry != *handle { continue; }
+
+        if shape_handle.is_exclusive() {
+            geometry.with_px_geometry(|geometry_ptr| unsafe {
+                PxShape_setGeometry_mut(shape_handle.as_mut_ptr(), geometry_ptr);
+            });
+        } else {
+            shape.set_changed();
+        }
+
+        let gtransform = gtransform.copied().unwrap_or_default();
+        regions.push(transform_aabb(&gtransform, local_region.0, local_region.1));
+
+        if debug_render {
+            commands.entity(entity).insert(DebugRenderStale);
+        }
+    }
+
+    regions
+}
+
+/// Wakes up sleeping bodies whose bounds intersect any of the world-space regions,
+/// used after static geometry was modified in place.
+pub(crate) fn wake_actors_in_regions(
+    scene: &mut bpx::Scene,
+    regions: &[(Vec3, Vec3)],
+    dynamic_actors: &mut Query<&mut RigidDynamicHandle>,
+    articulations: &mut Query<&mut ArticulationHandle>,
+) {
+    if regions.is_empty() { return; }
+
+    for mut actor in dynamic_actors.iter_mut() {
+        let mut actor = actor.get_mut(scene);
+        if actor.is_sleeping() && regions.iter().any(|region| aabb_intersects(region, actor.get_world_bounds(0.))) {
+            actor.wake_up();
+        }
+    }
+
+    for mut articulation in articulations.iter_mut() {
+        let mut articulation = articulation.get_mut(scene);
+        if articulation.is_sleeping() && regions.iter().any(|region| aabb_intersects(region, articulation.get_world_bounds(0.))) {
+            articulation.wake_up();
+        }
+    }
+}
+
+pub(crate) fn transform_aabb(transform: &GlobalTransform, min: Vec3, max: Vec3) -> (Vec3, Vec3) {
+    let mut result = (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN));
+    for idx in 0..8 {
+        let corner = Vec3::new(
+            if idx & 1 == 0 { min.x } else { max.x },
+            if idx & 2 == 0 { min.y } else { max.y },
+            if idx & 4 == 0 { min.z } else { max.z },
+        );
+        let corner = transform.transform_point(corner);
+        result = (result.0.min(corner), result.1.max(corner));
+    }
+    result
+}
+
+pub(crate) fn aabb_intersects(region: &(Vec3, Vec3), bounds: PxBounds3) -> bool {
+    let bounds: physx_sys::PxBounds3 = bounds.into();
+    let (min, max) = (bounds.minimum, bounds.maximum);
+    region.0.x <= max.x && region.1.x >= min.x
+        && region.0.y <= max.y && region.1.y >= min.y
+        && region.0.z <= max.z && region.1.z >= min.z
+}
+
+pub fn sync_shape_settings(
+    mut scene: ResMut<bpx::Scene>,
+    mut changed: Query<(&ShapeSettings, &mut ShapeHandle), Changed<ShapeSettings>>,
+    mut shapes: Query<&mut ShapeHandle, Without<ShapeSettings>>,
+    removed: RemovedComponents<ShapeSettings>,
+) {
+    let _lock = scene.get_mut();
+
+    for (settings, mut shape_handle) in changed.iter_mut() {
+        if !shape_handle.is_exclusive() {
+            bevy::log::warn!("BPxShapeSettings changes are not applied to shared shapes");
+            continue;
+        }
+
+        settings.apply(&mut shape_handle);
+    }
+
+    // offsets are left as is, there's no way to tell what they were before
+    for entity in removed.iter() {
+        if let Ok(mut shape_handle) = shapes.get_mut(entity) {
+            if !shape_handle.is_exclusive() { continue; }
+            ShapeSettings::default().apply(&mut shape_handle);
+        }
+    }
+}
+
+pub fn release_unused_shared_shapes(mut shared_shapes: ResMut<SharedShapes>) {
+    shared_shapes.release_unused();
+}
+
+/// Re-assigns materials of shapes whose material asset was replaced or modified.
+///
+/// Shared shapes can't change materials while attached, so they are re-created by `sync_actor_shapes` instead.
+pub fn sync_shape_materials(
+    mut scene: ResMut<bpx::Scene>,
+    mut events: EventReader<AssetEvent<bpx::Material>>,
+    mut shapes: Query<(&mut bpx::Shape, &mut ShapeHandle)>,
+    geometries: Res<Assets<bpx::Geometry>>,
+    materials: Res<Assets<bpx::Material>>,
+    default_material: Res<DefaultMaterial>,
+    mut shared_shapes: ResMut<SharedShapes>,
+) {
+    let modified = events.iter().filter_map(|event| match event {
+        AssetEvent::Modified { handle } => Some(handle.clone_weak()),
+        _ => None,
+    }).collect::<Vec<_>>();
+
+    if modified.is_empty() { return; }
+
+    let _lock = scene.get_mut();
+
+    for handle in modified.iter() {
+        shared_shapes.forget_using(handle.id());
+    }
+
+    for (mut shape, mut shape_handle) in shapes.iter_mut() {
+        let Some(geometry) = geometries.get(&shape.geometry) else { continue; };
+        let material_handles = shape.material_handles(geometry);
+        if !material_handles.iter().any(|handle| modified.contains(handle)) { continue; }
+
+        if !shape_handle.is_exclusive() {
+            shape.set_changed();
+            continue;
+        }
+
+        let shape_materials: Vec<*mut physx_sys::PxMaterial> = material_handles.iter().filter_map(|handle| {
+            let handle = if materials.contains(handle) { handle } else { default_material.as_ref().as_ref()? };
+            // not using get_mut here, it would emit another Modified event
+            materials.get(handle).map(|material| {
+                let ptr: *const physx_sys::PxMaterial = material.as_ptr();
+                ptr as *mut _
+            })
+        }).collect();
+
+        if shape_materials.len() != material_handles.len() { continue; }
+
+        unsafe {
+            PxShape_setMaterials_mut(
+                shape_handle.as_mut_ptr(),
+                shape_materials.as_ptr(),
+                shape_materials.len() as u16,
+            );
+        }
+    }
+}
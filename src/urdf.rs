@@ -0,0 +1,927 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+
+use bevy::asset::{AssetLoader, AssetPath, LoadContext, LoadedAsset, LoadState};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::render::mesh::Indices;
+use bevy::render::render_resource::PrimitiveTopology;
+use bevy::utils::BoxedFuture;
+use physx::prelude::ArticulationAxis;
+
+use crate::prelude as bpx;
+use crate::prelude::{ArticulationJointDrive, MassProperties};
+
+/// Robot description loaded from `.urdf` file.
+///
+/// Mesh references are resolved through the asset server: `package://name/path` is treated
+/// as `name/path` relative to assets folder, other paths are relative to the urdf file.
+/// Only glTF meshes are supported, visual meshes use `#Scene0` and collision meshes
+/// use `#Mesh0/Primitive0` of the referenced file.
+#[derive(TypeUuid, Debug, Clone)]
+#[uuid = "3c5d3bd6-6f5b-4b39-bd4e-2f6c0f0a4a1e"]
+pub struct Urdf {
+    pub name: String,
+    pub links: Vec<UrdfLink>,
+    pub joints: Vec<UrdfJoint>,
+}
+
+#[derive(Debug, Clone)]
+pub struct UrdfLink {
+    pub name: String,
+    pub inertial: Option<UrdfInertial>,
+    pub visuals: Vec<UrdfVisual>,
+    pub collisions: Vec<UrdfCollision>,
+}
+
+#[derive(Debug, Clone)]
+pub struct UrdfInertial {
+    pub origin: Transform,
+    pub mass: f32,
+    /// ixx, ixy, ixz, iyy, iyz, izz
+    pub inertia: [f32; 6],
+}
+
+#[derive(Debug, Clone)]
+pub struct UrdfVisual {
+    pub origin: Transform,
+    pub geometry: UrdfGeometry,
+    pub color: Option<Color>,
+    pub scene: Option<Handle<Scene>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct UrdfCollision {
+    pub origin: Transform,
+    pub geometry: UrdfGeometry,
+    pub mesh: Option<Handle<Mesh>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UrdfGeometry {
+    Box { size: Vec3 },
+    Sphere { radius: f32 },
+    Cylinder { radius: f32, length: f32 },
+    Mesh { path: PathBuf, scale: Vec3 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UrdfJointType {
+    Revolute,
+    Continuous,
+    Prismatic,
+    Fixed,
+    Floating,
+    Planar,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UrdfLimit {
+    pub lower: f32,
+    pub upper: f32,
+    pub effort: f32,
+    pub velocity: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct UrdfJoint {
+    pub name: String,
+    pub joint_type: UrdfJointType,
+    pub parent: String,
+    pub child: String,
+    pub origin: Transform,
+    pub axis: Vec3,
+    pub limit: Option<UrdfLimit>,
+}
+
+#[derive(Debug)]
+pub enum UrdfError {
+    Utf8(std::str::Utf8Error),
+    Xml(roxmltree::Error),
+    MissingElement { element: String, child: &'static str },
+    MissingAttribute { element: String, attribute: &'static str },
+    InvalidNumber(String),
+    UnknownJointType(String),
+    UnknownGeometry(String),
+    /// joints don't form a tree, contains name of the joint closing the cycle
+    JointCycle(String),
+}
+
+impl std::fmt::Display for UrdfError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Utf8(err) => write!(f, "urdf is not valid utf-8: {err}"),
+            Self::Xml(err) => write!(f, "unable to parse urdf: {err}"),
+            Self::MissingElement { element, child } => write!(f, "<{element}> is missing <{child}>"),
+            Self::MissingAttribute { element, attribute } => write!(f, "<{element}> is missing attribute \"{attribute}\""),
+            Self::InvalidNumber(value) => write!(f, "invalid number: \"{value}\""),
+            Self::UnknownJointType(value) => write!(f, "unknown joint type: \"{value}\""),
+            Self::UnknownGeometry(value) => write!(f, "unknown geometry: <{value}>"),
+            Self::JointCycle(joint) => write!(f, "joint \"{joint}\" forms a cycle"),
+        }
+    }
+}
+
+impl std::error::Error for UrdfError {}
+
+impl From<roxmltree::Error> for UrdfError {
+    fn from(value: roxmltree::Error) -> Self {
+        Self::Xml(value)
+    }
+}
+
+impl From<std::str::Utf8Error> for UrdfError {
+    fn from(value: std::str::Utf8Error) -> Self {
+        Self::Utf8(value)
+    }
+}
+
+type XmlNode<'a, 'input> = roxmltree::Node<'a, 'input>;
+
+fn child<'a, 'input>(node: XmlNode<'a, 'input>, name: &str) -> Option<XmlNode<'a, 'input>> {
+    node.children().find(|n| n.has_tag_name(name))
+}
+
+fn attribute<'a>(node: XmlNode<'a, '_>, attribute: &'static str) -> Result<&'a str, UrdfError> {
+    node.attribute(attribute).ok_or_else(|| UrdfError::MissingAttribute {
+        element: node.tag_name().name().to_string(),
+        attribute,
+    })
+}
+
+fn parse_floats(value: &str) -> Result<Vec<f32>, UrdfError> {
+    value.split_whitespace()
+        .map(|v| v.parse::<f32>().map_err(|_| UrdfError::InvalidNumber(value.to_string())))
+        .collect()
+}
+
+fn parse_float(value: &str) -> Result<f32, UrdfError> {
+    value.trim().parse::<f32>().map_err(|_| UrdfError::InvalidNumber(value.to_string()))
+}
+
+fn parse_vec3(value: &str) -> Result<Vec3, UrdfError> {
+    match parse_floats(value)?[..] {
+        [x, y, z] => Ok(Vec3::new(x, y, z)),
+        _ => Err(UrdfError::InvalidNumber(value.to_string())),
+    }
+}
+
+fn parse_origin(node: Option<XmlNode>) -> Result<Transform, UrdfError> {
+    let Some(node) = node else { return Ok(Transform::IDENTITY); };
+    let translation = node.attribute("xyz").map(parse_vec3).transpose()?.unwrap_or(Vec3::ZERO);
+    let rpy = node.attribute("rpy").map(parse_vec3).transpose()?.unwrap_or(Vec3::ZERO);
+
+    // urdf uses fixed axis roll-pitch-yaw, which is the same as Rz(yaw) * Ry(pitch) * Rx(roll)
+    Ok(Transform {
+        translation,
+        rotation: Quat::from_euler(EulerRot::ZYX, rpy.z, rpy.y, rpy.x),
+        scale: Vec3::ONE,
+    })
+}
+
+fn parse_color(node: Option<XmlNode>) -> Result<Option<Color>, UrdfError> {
+    let Some(color) = node.and_then(|n| child(n, "color")) else { return Ok(None); };
+    let rgba = attribute(color, "rgba")?;
+    match parse_floats(rgba)?[..] {
+        [r, g, b, a] => Ok(Some(Color::rgba(r, g, b, a))),
+        _ => Err(UrdfError::InvalidNumber(rgba.to_string())),
+    }
+}
+
+impl Urdf {
+    /// Parse urdf document, `resolve` is used to turn mesh filenames into asset paths.
+    pub fn parse(text: &str, resolve: impl Fn(&str) -> PathBuf) -> Result<Self, UrdfError> {
+        let document = roxmltree::Document::parse(text)?;
+        let robot = document.root_element();
+
+        // materials can be defined globally and referenced by name
+        let mut materials = HashMap::new();
+        for material in robot.children().filter(|n| n.has_tag_name("material")) {
+            if let Some(color) = parse_color(Some(material))? {
+                materials.insert(attribute(material, "name")?.to_string(), color);
+            }
+        }
+
+        let parse_geometry = |node: XmlNode| -> Result<UrdfGeometry, UrdfError> {
+            let geometry = child(node, "geometry").ok_or_else(|| UrdfError::MissingElement {
+                element: node.tag_name().name().to_string(),
+                child: "geometry",
+            })?;
+            let Some(shape) = geometry.children().find(|n| n.is_element()) else {
+                return Err(UrdfError::MissingElement { element: "geometry".into(), child: "box|sphere|cylinder|mesh" });
+            };
+
+            match shape.tag_name().name() {
+                "box" => Ok(UrdfGeometry::Box { size: parse_vec3(attribute(shape, "size")?)? }),
+                "sphere" => Ok(UrdfGeometry::Sphere { radius: parse_float(attribute(shape, "radius")?)? }),
+                "cylinder" => Ok(UrdfGeometry::Cylinder {
+                    radius: parse_float(attribute(shape, "radius")?)?,
+                    length: parse_float(attribute(shape, "length")?)?,
+                }),
+                "mesh" => Ok(UrdfGeometry::Mesh {
+                    path: resolve(attribute(shape, "filename")?),
+                    scale: shape.attribute("scale").map(parse_vec3).transpose()?.unwrap_or(Vec3::ONE),
+                }),
+                other => Err(UrdfError::UnknownGeometry(other.to_string())),
+            }
+        };
+
+        let mut links = vec![];
+        for link in robot.children().filter(|n| n.has_tag_name("link")) {
+            let inertial = child(link, "inertial").map(|inertial| -> Result<_, UrdfError> {
+                let mass = child(inertial, "mass").map(|m| attribute(m, "value")).transpose()?;
+                let inertia = child(inertial, "inertia");
+                let mut values = [0.; 6];
+
+                if let Some(inertia) = inertia {
+                    for (idx, name) in ["ixx", "ixy", "ixz", "iyy", "iyz", "izz"].into_iter().enumerate() {
+                        if let Some(value) = inertia.attribute(name) {
+                            values[idx] = parse_float(value)?;
+                        }
+                    }
+                }
+
+                Ok(UrdfInertial {
+                    origin: parse_origin(child(inertial, "origin"))?,
+                    mass: mass.map(parse_float).transpose()?.unwrap_or(0.),
+                    inertia: values,
+                })
+            }).transpose()?;
+
+            let mut visuals = vec![];
+            for visual in link.children().filter(|n| n.has_tag_name("visual")) {
+                let material = child(visual, "material");
+                let color = match parse_color(material)? {
+                    Some(color) => Some(color),
+                    None => material.and_then(|m| m.attribute("name")).and_then(|name| materials.get(name).copied()),
+                };
+
+                visuals.push(UrdfVisual {
+                    origin: parse_origin(child(visual, "origin"))?,
+                    geometry: parse_geometry(visual)?,
+                    color,
+                    scene: None,
+                });
+            }
+
+            let mut collisions = vec![];
+            for collision in link.children().filter(|n| n.has_tag_name("collision")) {
+                collisions.push(UrdfCollision {
+                    origin: parse_origin(child(collision, "origin"))?,
+                    geometry: parse_geometry(collision)?,
+                    mesh: None,
+                });
+            }
+
+            links.push(UrdfLink {
+                name: attribute(link, "name")?.to_string(),
+                inertial,
+                visuals,
+                collisions,
+            });
+        }
+
+        let mut joints = vec![];
+        for joint in robot.children().filter(|n| n.has_tag_name("joint")) {
+            let joint_type = match attribute(joint, "type")? {
+                "revolute" => UrdfJointType::Revolute,
+                "continuous" => UrdfJointType::Continuous,
+                "prismatic" => UrdfJointType::Prismatic,
+                "fixed" => UrdfJointType::Fixed,
+                "floating" => UrdfJointType::Floating,
+                "planar" => UrdfJointType::Planar,
+                other => return Err(UrdfError::UnknownJointType(other.to_string())),
+            };
+
+            let link_name = |name: &'static str| -> Result<String, UrdfError> {
+                let node = child(joint, name).ok_or_else(|| UrdfError::MissingElement {
+                    element: "joint".into(),
+                    child: name,
+                })?;
+                Ok(attribute(node, "link")?.to_string())
+            };
+
+            let limit = child(joint, "limit").map(|limit| -> Result<_, UrdfError> {
+                let value = |name: &str| limit.attribute(name).map(parse_float).transpose().map(|v| v.unwrap_or(0.));
+                Ok(UrdfLimit {
+                    lower: value("lower")?,
+                    upper: value("upper")?,
+                    effort: value("effort")?,
+                    velocity: value("velocity")?,
+                })
+            }).transpose()?;
+
+            joints.push(UrdfJoint {
+                name: attribute(joint, "name")?.to_string(),
+                joint_type,
+                parent: link_name("parent")?,
+                child: link_name("child")?,
+                origin: parse_origin(child(joint, "origin"))?,
+                axis: child(joint, "axis").map(|a| attribute(a, "xyz")).transpose()?
+                    .map(parse_vec3).transpose()?.unwrap_or(Vec3::X),
+                limit,
+            });
+        }
+
+        let urdf = Self {
+            name: robot.attribute("name").unwrap_or_default().to_string(),
+            links,
+            joints,
+        };
+
+        urdf.check_tree()?;
+        Ok(urdf)
+    }
+
+    // links reachable from the root have to be visited only once
+    fn check_tree(&self) -> Result<(), UrdfError> {
+        let Some(root_link) = self.root_link() else {
+            // every link is a child of some joint
+            return match self.joints.first() {
+                Some(joint) => Err(UrdfError::JointCycle(joint.name.clone())),
+                None => Ok(()),
+            };
+        };
+
+        let mut visited = HashSet::from([root_link.name.as_str()]);
+        let mut queue = VecDeque::from([root_link.name.as_str()]);
+
+        while let Some(parent_name) = queue.pop_front() {
+            for joint in self.joints.iter().filter(|joint| joint.parent == parent_name) {
+                if !visited.insert(joint.child.as_str()) {
+                    return Err(UrdfError::JointCycle(joint.name.clone()));
+                }
+                queue.push_back(joint.child.as_str());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Link that is not a child of any joint.
+    pub fn root_link(&self) -> Option<&UrdfLink> {
+        self.links.iter().find(|link| self.joints.iter().all(|joint| joint.child != link.name))
+    }
+
+    pub fn link(&self, name: &str) -> Option<&UrdfLink> {
+        self.links.iter().find(|link| link.name == name)
+    }
+}
+
+#[derive(Default)]
+pub struct UrdfLoader;
+
+impl AssetLoader for UrdfLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let base_dir = load_context.path().parent().map(Path::to_path_buf).unwrap_or_default();
+            let resolve = |filename: &str| {
+                match filename.strip_prefix("package://") {
+                    Some(path) => PathBuf::from(path),
+                    None => base_dir.join(filename.strip_prefix("file://").unwrap_or(filename)),
+                }
+            };
+
+            let mut urdf = Urdf::parse(std::str::from_utf8(bytes)?, resolve)?;
+            let mut dependencies = vec![];
+
+            for link in urdf.links.iter_mut() {
+                for visual in link.visuals.iter_mut() {
+                    let UrdfGeometry::Mesh { path, .. } = &visual.geometry else { continue; };
+                    if !is_gltf(path) {
+                        bevy::log::warn!("unsupported visual mesh format: {path:?}");
+                        continue;
+                    }
+                    let asset_path = AssetPath::new(path.clone(), Some("Scene0".into()));
+                    visual.scene = Some(load_context.get_handle(asset_path.clone()));
+                    dependencies.push(asset_path);
+                }
+
+                for collision in link.collisions.iter_mut() {
+                    let UrdfGeometry::Mesh { path, .. } = &collision.geometry else { continue; };
+                    if !is_gltf(path) {
+                        bevy::log::warn!("unsupported collision mesh format: {path:?}");
+                        continue;
+                    }
+                    let asset_path = AssetPath::new(path.clone(), Some("Mesh0/Primitive0".into()));
+                    collision.mesh = Some(load_context.get_handle(asset_path.clone()));
+                    dependencies.push(asset_path);
+                }
+            }
+
+            load_context.set_default_asset(LoadedAsset::new(urdf).with_dependencies(dependencies));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["urdf"]
+    }
+}
+
+fn is_gltf(path: &Path) -> bool {
+    matches!(path.extension().and_then(|ext| ext.to_str()), Some("gltf" | "glb"))
+}
+
+/// Spawns robot described by urdf as an articulation.
+///
+/// Entity with this component becomes the root link, other links are spawned as its
+/// descendants. URDF is Z-up, so rotate this entity to match bevy's Y-up if needed.
+/// Robot is not spawned if any of its collision meshes fails to load, `PhysicsError::AssetFailed`
+/// is sent instead.
+#[derive(Component, Clone, Default)]
+pub struct UrdfRobot {
+    pub urdf: Handle<Urdf>,
+    pub fix_base: bool,
+    /// drive added to every movable joint, max force is taken from joint effort limit if set
+    pub joint_drive: Option<ArticulationJointDrive>,
+    pub material: Handle<bpx::Material>,
+}
+
+fn cylinder_mesh(radius: f32, length: f32, segments: usize) -> Mesh {
+    // cylinder along Z axis, as urdf defines it
+    let mut positions = vec![];
+    let mut normals = vec![];
+    let mut indices = vec![];
+
+    for i in 0..segments {
+        let (sin, cos) = (std::f32::consts::TAU / segments as f32 * i as f32).sin_cos();
+        let side = [cos * radius, sin * radius];
+
+        for z in [-length / 2., length / 2.] {
+            positions.push([side[0], side[1], z]);
+            normals.push([cos, sin, 0.]);
+        }
+
+        for z in [-length / 2., length / 2.] {
+            positions.push([side[0], side[1], z]);
+            normals.push([0., 0., z.signum()]);
+        }
+    }
+
+    let center = positions.len() as u32;
+    positions.push([0., 0., -length / 2.]);
+    normals.push([0., 0., -1.]);
+    positions.push([0., 0., length / 2.]);
+    normals.push([0., 0., 1.]);
+
+    for i in 0..segments as u32 {
+        let next = (i + 1) % segments as u32;
+        let (a, b, c, d) = (i * 4, i * 4 + 1, next * 4, next * 4 + 1);
+        indices.extend([a, c, b, b, c, d]);
+        indices.extend([center, next * 4 + 2, i * 4 + 2]);
+        indices.extend([center + 1, i * 4 + 3, next * 4 + 3]);
+    }
+
+    let uvs = vec![[0., 0.]; positions.len()];
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
+}
+
+// returns geometry and rotation that needs to be applied to shape local pose
+fn create_collision_geometry(
+    collision: &UrdfCollision,
+    physics: &mut bpx::Physics,
+    cooking: Option<&bpx::Cooking>,
+    meshes: &Assets<Mesh>,
+) -> Option<(bpx::Geometry, Quat)> {
+    match &collision.geometry {
+        UrdfGeometry::Box { size } => Some((bpx::Geometry::cuboid(size.x, size.y, size.z), Quat::IDENTITY)),
+        UrdfGeometry::Sphere { radius } => Some((bpx::Geometry::ball(*radius), Quat::IDENTITY)),
+        UrdfGeometry::Cylinder { radius, length } => {
+            let Some(cooking) = cooking else {
+                bevy::log::warn!("cooking is required for cylinder collisions");
+                return None;
+            };
+            // urdf cylinder is aligned to Z axis
+            let geometry = match bpx::Geometry::cylinder_z(physics, cooking, length / 2., *radius, 24) {
+                Ok(geometry) => geometry,
+                Err(err) => {
+                    bevy::log::warn!("failed to cook cylinder collision: {err:?}");
+                    return None;
+                }
+            };
+            Some((geometry, Quat::IDENTITY))
+        }
+        UrdfGeometry::Mesh { path, scale } => {
+            let Some(cooking) = cooking else {
+                bevy::log::warn!("cooking is required for mesh collisions");
+                return None;
+            };
+            let Some(mesh) = collision.mesh.as_ref().and_then(|handle| meshes.get(handle)) else {
+                bevy::log::warn!("collision mesh {path:?} is not loaded");
+                return None;
+            };
+            // articulation links are dynamic, so only convex meshes are allowed
+            match bpx::Geometry::convex_mesh_from_mesh(physics, cooking, mesh) {
+                Ok(geometry) => Some((geometry.with_scale(*scale), Quat::IDENTITY)),
+                Err(err) => {
+                    bevy::log::warn!("failed to cook collision mesh {path:?}: {err:?}");
+                    None
+                }
+            }
+        }
+    }
+}
+
+fn spawn_link_contents(
+    builder: &mut ChildBuilder,
+    link: &UrdfLink,
+    robot: &UrdfRobot,
+    physics: &mut bpx::Physics,
+    cooking: Option<&bpx::Cooking>,
+    meshes: &mut Assets<Mesh>,
+    standard_materials: &mut Assets<StandardMaterial>,
+    geometries: &mut Assets<bpx::Geometry>,
+) {
+    for collision in link.collisions.iter() {
+        let Some((geometry, rotation)) = create_collision_geometry(collision, physics, cooking, meshes) else { continue; };
+        let mut transform = collision.origin;
+        transform.rotation *= rotation;
+
+        builder.spawn(SpatialBundle::from_transform(transform))
+            .insert(bpx::Shape {
+                geometry: geometries.add(geometry),
+                material: robot.material.clone(),
+                ..default()
+            });
+    }
+
+    for visual in link.visuals.iter() {
+        let material = standard_materials.add(visual.color.unwrap_or(Color::GRAY).into());
+        let mesh = match visual.geometry {
+            UrdfGeometry::Box { size } => meshes.add(shape::Box::new(size.x, size.y, size.z).into()),
+            UrdfGeometry::Sphere { radius } => meshes.add(shape::UVSphere { radius, ..default() }.into()),
+            UrdfGeometry::Cylinder { radius, length } => meshes.add(cylinder_mesh(radius, length, 24)),
+            UrdfGeometry::Mesh { scale, .. } => {
+                let Some(scene) = visual.scene.clone() else { continue; };
+                builder.spawn(SceneBundle {
+                    scene,
+                    transform: visual.origin.with_scale(scale),
+                    ..default()
+                });
+                continue;
+            }
+        };
+
+        builder.spawn(PbrBundle {
+            mesh,
+            material,
+            transform: visual.origin,
+            ..default()
+        });
+    }
+}
+
+fn link_components(link: &UrdfLink) -> (bpx::RigidBody, Name, Option<MassProperties>) {
+    let mass_props = link.inertial.as_ref()
+        .filter(|inertial| inertial.mass > 0.)
+        .map(|inertial| {
+            let center = inertial.origin.translation;
+
+            // without inertia tensor PhysX computes it from the shapes
+            if inertial.inertia.iter().all(|value| *value == 0.) {
+                return MassProperties::mass_with_center(inertial.mass, center);
+            }
+
+            let (inertia, axes) = diagonalize_inertia(inertial.inertia);
+            MassProperties::explicit_with_axes(inertial.mass, center, inertia, inertial.origin.rotation * axes)
+        });
+
+    (bpx::RigidBody::ArticulationLink, Name::new(link.name.clone()), mass_props)
+}
+
+/// Principal moments and axes of a symmetric inertia tensor given as ixx, ixy, ixz, iyy, iyz, izz,
+/// found with cyclic Jacobi rotations.
+fn diagonalize_inertia([ixx, ixy, ixz, iyy, iyz, izz]: [f32; 6]) -> (Vec3, Quat) {
+    let mut a = [[ixx, ixy, ixz], [ixy, iyy, iyz], [ixz, iyz, izz]];
+    let mut v = [[1., 0., 0.], [0., 1., 0.], [0., 0., 1.]];
+
+    for _ in 0..32 {
+        let (p, q) = [(0, 1), (0, 2), (1, 2)].into_iter()
+            .max_by(|&(i, j), &(k, l)| a[i][j].abs().total_cmp(&a[k][l].abs()))
+            .unwrap();
+
+        let scale = a[0][0].abs() + a[1][1].abs() + a[2][2].abs();
+        if a[p][q].abs() <= scale * 1e-7 { break; }
+
+        // rotation in (p, q) plane zeroing a[p][q]
+        let theta = (a[q][q] - a[p][p]) / (2. * a[p][q]);
+        let t = theta.signum() / (theta.abs() + (theta * theta + 1.).sqrt());
+        let c = 1. / (t * t + 1.).sqrt();
+        let s = t * c;
+
+        for row in a.iter_mut().chain(v.iter_mut()) {
+            let (kp, kq) = (row[p], row[q]);
+            row[p] = c * kp - s * kq;
+            row[q] = s * kp + c * kq;
+        }
+
+        for k in 0..3 {
+            let (pk, qk) = (a[p][k], a[q][k]);
+            a[p][k] = c * pk - s * qk;
+            a[q][k] = s * pk + c * qk;
+        }
+    }
+
+    // eigenvectors are columns of v, flip one to keep it a rotation
+    let mut axes = Mat3::from_cols(
+        Vec3::new(v[0][0], v[1][0], v[2][0]),
+        Vec3::new(v[0][1], v[1][1], v[2][1]),
+        Vec3::new(v[0][2], v[1][2], v[2][2]),
+    );
+    if axes.determinant() < 0. { axes.z_axis = -axes.z_axis; }
+
+    (Vec3::new(a[0][0], a[1][1], a[2][2]).max(Vec3::ZERO), Quat::from_mat3(&axes).normalize())
+}
+
+fn joint_component(joint: &UrdfJoint, drive: Option<ArticulationJointDrive>) -> bpx::ArticulationJoint {
+    let (mut result, axis) = match joint.joint_type {
+        UrdfJointType::Revolute | UrdfJointType::Continuous => (bpx::ArticulationJoint::revolute(), ArticulationAxis::Twist),
+        UrdfJointType::Prismatic => (bpx::ArticulationJoint::prismatic(), ArticulationAxis::X),
+        UrdfJointType::Fixed => return bpx::ArticulationJoint::fixed(),
+        UrdfJointType::Floating | UrdfJointType::Planar => {
+            bevy::log::warn!("unsupported urdf joint type {:?} in {}, using fixed joint", joint.joint_type, joint.name);
+            return bpx::ArticulationJoint::fixed();
+        }
+    };
+
+    // physx joints move along X axis of the joint frame, so we rotate X to match urdf axis
+    let axis_dir = joint.axis.try_normalize().unwrap_or(Vec3::X);
+    result = result.with_frame(Transform::from_rotation(Quat::from_rotation_arc(Vec3::X, axis_dir)));
+
+    if let Some(limit) = joint.limit {
+        if joint.joint_type != UrdfJointType::Continuous {
+            result = result.with_limit(axis, limit.lower, limit.upper);
+        }
+    }
+
+    if let Some(mut drive) = drive {
+        if let Some(limit) = joint.limit.filter(|limit| limit.effort > 0.) {
+            drive.max_force = limit.effort;
+        }
+        result = result.with_drive(axis, drive);
+    }
+
+    result
+}
+
+pub fn spawn_urdf_robots(
+    mut commands: Commands,
+    mut physics: ResMut<bpx::Physics>,
+    cooking: Option<Res<bpx::Cooking>>,
+    asset_server: Res<AssetServer>,
+    urdfs: Res<Assets<Urdf>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut standard_materials: ResMut<Assets<StandardMaterial>>,
+    mut geometries: ResMut<Assets<bpx::Geometry>>,
+    robots: Query<(Entity, &UrdfRobot), Without<bpx::Articulation>>,
+    mut errors: EventWriter<bpx::PhysicsError>,
+    mut failed: Local<HashSet<Entity>>,
+) {
+    failed.retain(|entity| robots.contains(*entity));
+
+    for (entity, robot) in robots.iter() {
+        let Some(urdf) = urdfs.get(&robot.urdf) else { continue; };
+
+        // collision meshes are needed to create shapes, wait until they are loaded
+        let mut pending = false;
+        let mut failed_mesh = None;
+        for handle in urdf.links.iter()
+            .flat_map(|link| link.collisions.iter())
+            .filter_map(|collision| collision.mesh.as_ref())
+        {
+            match asset_server.get_load_state(handle) {
+                LoadState::NotLoaded | LoadState::Loading => pending = true,
+                LoadState::Failed => failed_mesh = Some(handle.id()),
+                _ => {}
+            }
+        }
+
+        // spawning without a collider would silently change robot dynamics, so refuse it
+        if let Some(asset) = failed_mesh {
+            if failed.insert(entity) {
+                let error = bpx::PhysicsError::AssetFailed { entity, asset };
+                bevy::log::warn!("{error}");
+                errors.send(error);
+            }
+            continue;
+        }
+        failed.remove(&entity);
+        if pending { continue; }
+
+        let Some(root_link) = urdf.root_link() else {
+            bevy::log::warn!("urdf {} has no root link", urdf.name);
+            commands.entity(entity).insert(bpx::Articulation { fix_base: robot.fix_base });
+            continue;
+        };
+
+        let (rigid_body, name, mass_props) = link_components(root_link);
+        let mut root = commands.entity(entity);
+        root.insert(bpx::Articulation { fix_base: robot.fix_base })
+            .insert(rigid_body)
+            .insert(name);
+        if let Some(mass_props) = mass_props { root.insert(mass_props); }
+
+        root.with_children(|builder| {
+            spawn_link_contents(
+                builder, root_link, robot, &mut physics, cooking.as_deref(),
+                &mut meshes, &mut standard_materials, &mut geometries,
+            );
+        });
+
+        // spawn child links breadth first, each one nested inside its parent link
+        let mut link_entities = HashMap::from([(root_link.name.as_str(), entity)]);
+        let mut queue = VecDeque::from([root_link.name.as_str()]);
+
+        while let Some(parent_name) = queue.pop_front() {
+            let parent_entity = link_entities[parent_name];
+
+            for joint in urdf.joints.iter().filter(|joint| joint.parent == parent_name) {
+                let Some(link) = urdf.link(&joint.child) else {
+                    bevy::log::warn!("urdf joint {} references unknown link {}", joint.name, joint.child);
+                    continue;
+                };
+
+                // parsed urdf is checked for cycles, but fields are public
+                if link_entities.contains_key(link.name.as_str()) {
+                    bevy::log::warn!("{}", UrdfError::JointCycle(joint.name.clone()));
+                    continue;
+                }
+
+                let (rigid_body, name, mass_props) = link_components(link);
+                let mut child = commands.spawn(SpatialBundle::from_transform(joint.origin));
+                child.insert(rigid_body)
+                    .insert(name)
+                    .insert(joint_component(joint, robot.joint_drive));
+                if let Some(mass_props) = mass_props { child.insert(mass_props); }
+
+                child.with_children(|builder| {
+                    spawn_link_contents(
+                        builder, link, robot, &mut physics, cooking.as_deref(),
+                        &mut meshes, &mut standard_materials, &mut geometries,
+                    );
+                });
+
+                let child = child.id();
+                commands.entity(parent_entity).add_child(child);
+                link_entities.insert(link.name.as_str(), child);
+                queue.push_back(link.name.as_str());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROBOT: &str = r#"
+        <robot name="arm">
+            <material name="blue"><color rgba="0 0 1 1"/></material>
+            <link name="base">
+                <inertial>
+                    <mass value="2"/>
+                    <inertia ixx="1" ixy="0" ixz="0" iyy="2" iyz="0" izz="3"/>
+                </inertial>
+                <visual>
+                    <geometry><box size="1 2 3"/></geometry>
+                    <material name="blue"/>
+                </visual>
+                <collision>
+                    <origin xyz="0 0 0.5"/>
+                    <geometry><mesh filename="package://arm/base.glb" scale="2 2 2"/></geometry>
+                </collision>
+            </link>
+            <link name="upper">
+                <collision><geometry><cylinder radius="0.1" length="1"/></geometry></collision>
+            </link>
+            <joint name="shoulder" type="revolute">
+                <parent link="base"/>
+                <child link="upper"/>
+                <origin xyz="0 0 1" rpy="0 0 1.5707963"/>
+                <axis xyz="0 1 0"/>
+                <limit lower="-1" upper="1" effort="10" velocity="2"/>
+            </joint>
+        </robot>
+    "#;
+
+    fn parse(text: &str) -> Result<Urdf, UrdfError> {
+        Urdf::parse(text, |path| PathBuf::from(path.trim_start_matches("package://")))
+    }
+
+    fn origin(xml: &str) -> Transform {
+        let document = roxmltree::Document::parse(xml).unwrap();
+        parse_origin(Some(document.root_element())).unwrap()
+    }
+
+    fn two_link_robot(joints: &str) -> String {
+        format!(r#"<robot name="r"><link name="a"/><link name="b"/>{joints}</robot>"#)
+    }
+
+    #[test]
+    fn parses_links_and_joints() {
+        let urdf = parse(ROBOT).unwrap();
+
+        assert_eq!(urdf.name, "arm");
+        assert_eq!(urdf.root_link().unwrap().name, "base");
+
+        let base = urdf.link("base").unwrap();
+        let inertial = base.inertial.as_ref().unwrap();
+        assert_eq!(inertial.mass, 2.);
+        assert_eq!(inertial.inertia, [1., 0., 0., 2., 0., 3.]);
+        assert_eq!(base.visuals[0].color, Some(Color::rgba(0., 0., 1., 1.)));
+        assert!(matches!(base.visuals[0].geometry, UrdfGeometry::Box { size } if size == Vec3::new(1., 2., 3.)));
+        assert_eq!(base.collisions[0].origin.translation, Vec3::new(0., 0., 0.5));
+        assert!(matches!(
+            &base.collisions[0].geometry,
+            UrdfGeometry::Mesh { path, scale } if path == Path::new("arm/base.glb") && *scale == Vec3::splat(2.)
+        ));
+
+        let joint = &urdf.joints[0];
+        assert_eq!(joint.joint_type, UrdfJointType::Revolute);
+        assert_eq!((joint.parent.as_str(), joint.child.as_str()), ("base", "upper"));
+        assert_eq!(joint.axis, Vec3::Y);
+        let limit = joint.limit.unwrap();
+        assert_eq!((limit.lower, limit.upper, limit.effort, limit.velocity), (-1., 1., 10., 2.));
+    }
+
+    #[test]
+    fn reports_invalid_documents() {
+        assert!(matches!(
+            parse(&two_link_robot(r#"<joint name="j" type="hinge"><parent link="a"/><child link="b"/></joint>"#)),
+            Err(UrdfError::UnknownJointType(name)) if name == "hinge"
+        ));
+        assert!(matches!(
+            parse(&two_link_robot(r#"<joint name="j" type="fixed"><parent link="a"/></joint>"#)),
+            Err(UrdfError::MissingElement { child: "child", .. })
+        ));
+        assert!(matches!(
+            parse(r#"<robot><link name="a"><collision><geometry><box size="1 x 1"/></geometry></collision></link></robot>"#),
+            Err(UrdfError::InvalidNumber(_))
+        ));
+    }
+
+    #[test]
+    fn rpy_is_fixed_axis_roll_pitch_yaw() {
+        // yaw alone rotates X to Y
+        let yaw = origin(r#"<origin rpy="0 0 1.5707963"/>"#);
+        assert!((yaw.rotation * Vec3::X).abs_diff_eq(Vec3::Y, 1e-5));
+
+        // roll is applied first, yaw last
+        let rpy = origin(r#"<origin xyz="1 2 3" rpy="0.1 0.2 0.3"/>"#);
+        let expected = Quat::from_rotation_z(0.3) * Quat::from_rotation_y(0.2) * Quat::from_rotation_x(0.1);
+        assert!(rpy.rotation.abs_diff_eq(expected, 1e-5));
+        assert_eq!(rpy.translation, Vec3::new(1., 2., 3.));
+    }
+
+    #[test]
+    fn rejects_joint_cycles() {
+        // every link is a child, so there is no root
+        let cycle = two_link_robot(r#"
+            <joint name="ab" type="fixed"><parent link="a"/><child link="b"/></joint>
+            <joint name="ba" type="fixed"><parent link="b"/><child link="a"/></joint>
+        "#);
+        assert!(matches!(parse(&cycle), Err(UrdfError::JointCycle(name)) if name == "ab"));
+
+        // link with two parents
+        let diamond = r#"<robot name="r"><link name="a"/><link name="b"/><link name="c"/>
+            <joint name="ab" type="fixed"><parent link="a"/><child link="b"/></joint>
+            <joint name="ac" type="fixed"><parent link="a"/><child link="c"/></joint>
+            <joint name="bc" type="fixed"><parent link="b"/><child link="c"/></joint>
+        </robot>"#;
+        assert!(matches!(parse(diamond), Err(UrdfError::JointCycle(name)) if name == "bc"));
+    }
+
+    #[test]
+    fn diagonal_inertia_is_unchanged() {
+        let (diagonal, rotation) = diagonalize_inertia([1., 0., 0., 2., 0., 3.]);
+
+        assert_eq!(diagonal, Vec3::new(1., 2., 3.));
+        assert!(rotation.abs_diff_eq(Quat::IDENTITY, 1e-6));
+    }
+
+    #[test]
+    fn rotated_inertia_is_reconstructed() {
+        let axes = Mat3::from_quat(Quat::from_euler(EulerRot::XYZ, 0.3, -0.7, 1.1));
+        let tensor = axes * Mat3::from_diagonal(Vec3::new(1., 2., 4.)) * axes.transpose();
+        let [x, y, z] = [tensor.x_axis, tensor.y_axis, tensor.z_axis];
+
+        let (diagonal, rotation) = diagonalize_inertia([x.x, y.x, z.x, y.y, z.y, z.z]);
+        let rotation = Mat3::from_quat(rotation);
+        let result = rotation * Mat3::from_diagonal(diagonal) * rotation.transpose();
+
+        let mut sorted = diagonal.to_array();
+        sorted.sort_by(f32::total_cmp);
+        assert!(Vec3::from(sorted).abs_diff_eq(Vec3::new(1., 2., 4.), 1e-4));
+        assert!(result.abs_diff_eq(tensor, 1e-4));
+    }
+}
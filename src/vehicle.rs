@@ -0,0 +1,436 @@
+//! Declarative vehicle setup, loaded from `.vehicle.ron` files.
+//!
+//! `bpx::VehicleDescriptor` replaces manual `VehicleWheelsSimData` and drive sim data setup:
+//! wheel offsets are taken from poses of wheel shapes, and sprung masses are computed
+//! from mass properties of the actor when the vehicle is created (see `bpx::DescribedVehicle`).
+use std::collections::HashMap;
+
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::{FromReflect, TypeUuid};
+use bevy::utils::BoxedFuture;
+use physx::prelude::*;
+use physx::traits::Class;
+use physx::vehicles::*;
+use physx_sys::PxShape_getLocalPose;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::PhysicsError;
+use crate::prelude as bpx;
+use crate::prelude::*;
+use super::PxRigidDynamic;
+
+// PhysX keeps torque curve in a fixed size table
+const MAX_TORQUE_CURVE_ENTRIES: usize = 8;
+
+// PhysX wheel data is limited to 20 wheels per vehicle
+const MAX_WHEELS: usize = 20;
+
+/// Vehicle description, wheels are listed in the same order as in `bpx::DescribedVehicle`.
+///
+/// For `VehicleDriveType::Drive4W` first four wheels are front left, front right, rear left
+/// and rear right, Ackermann geometry is computed from their positions.
+///
+/// Up axis is Y, suspension travels along -Y of the actor.
+#[derive(TypeUuid, Debug, Clone, PartialEq, Reflect, FromReflect, Serialize, Deserialize)]
+#[uuid = "8f2c6ad1-4b0e-4a53-9d7c-7e1f0c2b5a94"]
+#[serde(default)]
+pub struct VehicleDescriptor {
+    pub drive: VehicleDriveType,
+    pub chassis: ChassisDescriptor,
+    pub wheels: Vec<WheelDescriptor>,
+    pub engine: EngineDescriptor,
+    pub gears: GearsDescriptor,
+    pub clutch_strength: f32,
+    /// only used by `VehicleDriveType::Drive4W`
+    pub differential: DifferentialDescriptor,
+    /// only used by `VehicleDriveType::Drive4W`, 0 is no Ackermann correction, 1 is full correction
+    pub ackermann_accuracy: f32,
+}
+
+impl Default for VehicleDescriptor {
+    fn default() -> Self {
+        Self {
+            drive: default(),
+            chassis: default(),
+            wheels: vec![],
+            engine: default(),
+            gears: default(),
+            clutch_strength: 10.,
+            differential: default(),
+            ackermann_accuracy: 1.,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Reflect, FromReflect, Serialize, Deserialize)]
+pub enum VehicleDriveType {
+    NoDrive,
+    #[default]
+    Drive4W,
+    /// wheels with `WheelDescriptor::driven` get engine torque
+    DriveNW,
+    DriveTank,
+}
+
+/// Used as `bpx::MassProperties` of the actor, unless it has its own.
+#[derive(Debug, Clone, Copy, PartialEq, Reflect, FromReflect, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChassisDescriptor {
+    pub mass: f32,
+    pub center: Vec3,
+}
+
+impl Default for ChassisDescriptor {
+    fn default() -> Self {
+        Self { mass: 1500., center: Vec3::ZERO }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Reflect, FromReflect, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WheelDescriptor {
+    pub radius: f32,
+    /// full width of the wheel, not half width as in `bpx::Geometry::cylinder`
+    pub width: f32,
+    pub mass: f32,
+    pub damping_rate: f32,
+    pub max_brake_torque: f32,
+    pub max_hand_brake_torque: f32,
+    pub max_steer: f32,
+    pub toe_angle: f32,
+    /// only used by `VehicleDriveType::DriveNW`
+    pub driven: bool,
+    /// index into friction pairs set up in `bpx::VehicleSimulation`
+    pub tire_type: u32,
+    pub suspension: SuspensionDescriptor,
+    /// point where suspension and tire forces are applied, relative to the wheel center
+    pub force_application_offset: Vec3,
+}
+
+impl Default for WheelDescriptor {
+    fn default() -> Self {
+        Self {
+            radius: 0.5,
+            width: 0.3,
+            mass: 20.,
+            damping_rate: 0.25,
+            max_brake_torque: 1500.,
+            max_hand_brake_torque: 0.,
+            max_steer: 0.,
+            toe_angle: 0.,
+            driven: false,
+            tire_type: 0,
+            suspension: default(),
+            force_application_offset: Vec3::ZERO,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Reflect, FromReflect, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SuspensionDescriptor {
+    pub spring_strength: f32,
+    pub spring_damper_rate: f32,
+    pub max_compression: f32,
+    pub max_droop: f32,
+}
+
+impl Default for SuspensionDescriptor {
+    fn default() -> Self {
+        Self {
+            spring_strength: 35000.,
+            spring_damper_rate: 4500.,
+            max_compression: 0.3,
+            max_droop: 0.1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Reflect, FromReflect, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EngineDescriptor {
+    pub peak_torque: f32,
+    pub max_omega: f32,
+    pub moi: f32,
+    /// normalized torque (fraction of `peak_torque`) vs normalized revs (fraction of `max_omega`),
+    /// up to 8 points
+    pub torque_curve: Vec<(f32, f32)>,
+}
+
+impl Default for EngineDescriptor {
+    fn default() -> Self {
+        Self {
+            peak_torque: 500.,
+            max_omega: 600.,
+            moi: 1.,
+            torque_curve: vec![(0., 0.8), (0.33, 1.), (1., 0.8)],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Reflect, FromReflect, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GearsDescriptor {
+    pub reverse: f32,
+    /// ratios of forward gears, starting with the first one
+    pub forward: Vec<f32>,
+    pub final_ratio: f32,
+    pub switch_time: f32,
+}
+
+impl Default for GearsDescriptor {
+    fn default() -> Self {
+        Self {
+            reverse: -4.,
+            forward: vec![4., 2., 1.5, 1.1, 1.],
+            final_ratio: 4.,
+            switch_time: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Reflect, FromReflect, Serialize, Deserialize)]
+pub enum DifferentialType {
+    #[default]
+    LimitedSlip4WD,
+    LimitedSlipFrontWD,
+    LimitedSlipRearWD,
+    Open4WD,
+    OpenFrontWD,
+    OpenRearWD,
+}
+
+impl From<DifferentialType> for VehicleDifferential4WType {
+    fn from(value: DifferentialType) -> Self {
+        match value {
+            DifferentialType::LimitedSlip4WD => Self::LS4WD,
+            DifferentialType::LimitedSlipFrontWD => Self::LSFrontWD,
+            DifferentialType::LimitedSlipRearWD => Self::LSRearWD,
+            DifferentialType::Open4WD => Self::Open4WD,
+            DifferentialType::OpenFrontWD => Self::OpenFrontWD,
+            DifferentialType::OpenRearWD => Self::OpenRearWD,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Reflect, FromReflect, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DifferentialDescriptor {
+    pub diff_type: DifferentialType,
+    pub front_rear_split: f32,
+    pub front_left_right_split: f32,
+    pub rear_left_right_split: f32,
+    pub centre_bias: f32,
+    pub front_bias: f32,
+    pub rear_bias: f32,
+}
+
+impl Default for DifferentialDescriptor {
+    fn default() -> Self {
+        Self {
+            diff_type: default(),
+            front_rear_split: 0.45,
+            front_left_right_split: 0.5,
+            rear_left_right_split: 0.5,
+            centre_bias: 1.3,
+            front_bias: 1.3,
+            rear_bias: 1.3,
+        }
+    }
+}
+
+impl VehicleDescriptor {
+    pub(crate) fn mass_properties(&self) -> MassProperties {
+        MassProperties::mass_with_center(self.chassis.mass, self.chassis.center)
+    }
+
+    /// Wheels are mapped to shapes of `actor`, which should have its shapes and mass properties set.
+    pub(crate) fn build(&self, wheels: &[Entity], actor: &PxRigidDynamic) -> Result<bpx::Vehicle, PhysicsError> {
+        use physx::shape::Shape;
+
+        let entity = *actor.get_user_data();
+        let invalid = |message| PhysicsError::InvalidDescriptor { entity, message };
+
+        if wheels.len() != self.wheels.len() {
+            return Err(invalid("BPxDescribedVehicle wheels don't match wheels of BPxVehicleDescriptor"));
+        }
+
+        if self.wheels.len() > MAX_WHEELS {
+            return Err(invalid("BPxVehicleDescriptor has more than 20 wheels"));
+        }
+
+        if self.engine.torque_curve.len() > MAX_TORQUE_CURVE_ENTRIES {
+            return Err(invalid("BPxVehicleDescriptor torque curve has more than 8 points"));
+        }
+
+        if self.gears.forward.len() + 2 > VehicleGearsRatio::GEARS_RATIO_COUNT as usize {
+            return Err(invalid("BPxVehicleDescriptor has too many gears"));
+        }
+
+        let shape_offsets = actor.get_shapes().into_iter()
+            .map(|shape| (*shape.get_user_data(), unsafe { PxShape_getLocalPose(shape.as_ptr()) }.to_bevy().translation))
+            .collect::<HashMap<_, _>>();
+
+        let offsets = wheels.iter()
+            .map(|wheel| shape_offsets.get(wheel).copied().ok_or(PhysicsError::WheelMapping { entity, wheel: *wheel }))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let center = actor.get_c_mass_local_pose().translation().to_bevy();
+        let mass = actor.get_mass();
+
+        let sprung_masses = vehicle_compute_sprung_masses(
+            &offsets.iter().map(|offset| offset.to_physx()).collect::<Vec<_>>(),
+            center.to_physx(),
+            mass,
+            VehicleUtilGravityDirection::Y,
+        );
+
+        let mut wheels_sim_data = VehicleWheelsSimData::new(wheels.len() as u32)
+            .ok_or_else(|| invalid("failed to create wheels sim data for BPxVehicleDescriptor"))?;
+
+        wheels_sim_data.set_chassis_mass(mass);
+
+        for (idx, wheel) in self.wheels.iter().enumerate() {
+            let cmass_offset = offsets[idx] - center;
+            let idx = idx as u32;
+
+            wheels_sim_data.set_wheel_data(idx, VehicleWheelData {
+                radius: wheel.radius,
+                width: wheel.width,
+                mass: wheel.mass,
+                moi: 0.5 * wheel.mass * wheel.radius * wheel.radius,
+                damping_rate: wheel.damping_rate,
+                max_brake_torque: wheel.max_brake_torque,
+                max_hand_brake_torque: wheel.max_hand_brake_torque,
+                max_steer: wheel.max_steer,
+                toe_angle: wheel.toe_angle,
+            });
+
+            wheels_sim_data.set_tire_data(idx, VehicleTireData {
+                tire_type: wheel.tire_type,
+                ..default()
+            });
+
+            wheels_sim_data.set_suspension_data(idx, VehicleSuspensionData {
+                spring_strength: wheel.suspension.spring_strength,
+                spring_damper_rate: wheel.suspension.spring_damper_rate,
+                max_compression: wheel.suspension.max_compression,
+                max_droop: wheel.suspension.max_droop,
+                sprung_mass: sprung_masses[idx as usize],
+                ..default()
+            });
+
+            wheels_sim_data.set_susp_travel_direction(idx, Vec3::NEG_Y.to_physx());
+            wheels_sim_data.set_wheel_centre_offset(idx, cmass_offset.to_physx());
+            wheels_sim_data.set_susp_force_app_point_offset(idx, (cmass_offset + wheel.force_application_offset).to_physx());
+            wheels_sim_data.set_tire_force_app_point_offset(idx, (cmass_offset + wheel.force_application_offset).to_physx());
+        }
+
+        let wheels = wheels.to_vec();
+
+        Ok(match self.drive {
+            VehicleDriveType::NoDrive => bpx::Vehicle::NoDrive { wheels, wheels_sim_data },
+            VehicleDriveType::Drive4W => {
+                let &[front_left, front_right, rear_left, rear_right, ..] = offsets.as_slice() else {
+                    return Err(invalid("BPxVehicle::Drive4W requires at least 4 wheels"));
+                };
+
+                let mut drive_sim_data = PxVehicleDriveSimData4W::default();
+                self.setup_drive(&mut drive_sim_data);
+
+                drive_sim_data.set_diff_data(VehicleDifferential4WData {
+                    front_rear_split: self.differential.front_rear_split,
+                    front_left_right_split: self.differential.front_left_right_split,
+                    rear_left_right_split: self.differential.rear_left_right_split,
+                    centre_bias: self.differential.centre_bias,
+                    front_bias: self.differential.front_bias,
+                    rear_bias: self.differential.rear_bias,
+                    diff_type: self.differential.diff_type.into(),
+                });
+                drive_sim_data.set_ackermann_geometry_data(VehicleAckermannGeometryData {
+                    accuracy: self.ackermann_accuracy,
+                    front_width: front_left.distance(front_right),
+                    rear_width: rear_left.distance(rear_right),
+                    axle_separation: ((front_left + front_right) - (rear_left + rear_right)).length() / 2.,
+                });
+
+                bpx::Vehicle::Drive4W { wheels, wheels_sim_data, drive_sim_data: Box::new(drive_sim_data) }
+            }
+            VehicleDriveType::DriveNW => {
+                let mut drive_sim_data = PxVehicleDriveSimDataNW::default();
+                self.setup_drive(&mut drive_sim_data);
+
+                let mut diff = VehicleDifferentialNWData::default();
+                for (idx, wheel) in self.wheels.iter().enumerate() {
+                    diff.set_driven_wheel(idx as u32, wheel.driven);
+                }
+                drive_sim_data.set_diff_data(diff);
+
+                bpx::Vehicle::DriveNW { wheels, wheels_sim_data, drive_sim_data: Box::new(drive_sim_data) }
+            }
+            VehicleDriveType::DriveTank => {
+                let mut drive_sim_data = PxVehicleDriveSimData::default();
+                self.setup_drive(&mut drive_sim_data);
+
+                bpx::Vehicle::DriveTank { wheels, wheels_sim_data, drive_sim_data: Box::new(drive_sim_data) }
+            }
+        })
+    }
+
+    fn setup_drive(&self, drive_sim_data: &mut impl VehicleDriveSimData) {
+        let mut engine = VehicleEngineData {
+            peak_torque: self.engine.peak_torque,
+            max_omega: self.engine.max_omega,
+            moi: self.engine.moi,
+            ..default()
+        };
+
+        for (idx, point) in self.engine.torque_curve.iter().enumerate() {
+            engine.torque_curve.data_pairs[idx] = *point;
+        }
+        engine.torque_curve.nb_data_pairs = self.engine.torque_curve.len() as u32;
+
+        let mut gears = VehicleGearsData {
+            final_ratio: self.gears.final_ratio,
+            switch_time: self.gears.switch_time,
+            // reverse and neutral are counted as gears
+            nb_ratios: self.gears.forward.len() as u32 + 2,
+            ..default()
+        };
+
+        gears.set_gear_ratio(VehicleGearsRatio::Reverse, self.gears.reverse);
+        gears.set_gear_ratio(VehicleGearsRatio::Neutral, 0.);
+        for (idx, ratio) in self.gears.forward.iter().enumerate() {
+            gears.ratios[VehicleGearsRatio::First as usize + idx] = *ratio;
+        }
+
+        drive_sim_data.set_engine_data(engine);
+        drive_sim_data.set_gears_data(gears);
+        drive_sim_data.set_clutch_data(VehicleClutchData {
+            strength: self.clutch_strength,
+            ..default()
+        });
+    }
+}
+
+#[derive(Default)]
+pub struct VehicleDescriptorLoader;
+
+impl AssetLoader for VehicleDescriptorLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let descriptor: VehicleDescriptor = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(descriptor));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["vehicle.ron"]
+    }
+}